@@ -8,6 +8,9 @@ pub trait Address: Display {}
 /// Represents "T1 - T2"
 pub struct Sub<T1, T2>(T1, T2);
 
+/// Represents "T1 + T2"
+pub struct Add<T1, T2>(T1, T2);
+
 /*
  * Addr<Reg64>
  */
@@ -37,3 +40,87 @@ impl Display for Addr<Sub<Reg64, i64>> {
 }
 
 impl Address for Addr<Sub<Reg64, i64>> {}
+
+impl std::ops::Add<i64> for Addr<Reg64> {
+    type Output = Addr<Add<Reg64, i64>>;
+
+    fn add(self, rhs: i64) -> Self::Output {
+        Addr(Add(self.0, rhs))
+    }
+}
+
+/*
+ * Addr<Add<Reg64, i64>>
+ */
+impl Display for Addr<Add<Reg64, i64>> {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "[{} + {}]", (self.0).0, (self.0).1)
+    }
+}
+
+impl Address for Addr<Add<Reg64, i64>> {}
+
+/// Represents a symbol such as "_main".
+/// アドレスとして使う場合はRIP相対アドレッシングになる。
+pub struct Label(pub String);
+
+/*
+ * Addr<Label>
+ */
+impl Display for Addr<Label> {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "[rip + {}]", (self.0).0)
+    }
+}
+
+impl Address for Addr<Label> {}
+
+/// Represents the GOT entry of a symbol such as "_abs".
+/// 他の翻訳単位で定義されているかもしれない関数のアドレスを読むのに使う。
+pub struct GotEntry(pub String);
+
+/*
+ * Addr<GotEntry>
+ */
+impl Display for Addr<GotEntry> {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "[rip + {}@GOTPCREL]", (self.0).0)
+    }
+}
+
+impl Address for Addr<GotEntry> {}
+
+/// アクセスするメモリのサイズを明示したアドレスを定義する
+///
+/// ```ignore
+/// pub struct BytePtr<A>(pub A);
+///
+/// impl<A: Address> Display for BytePtr<A> { .. }  // "BYTE PTR [rax]"
+/// ```
+macro_rules! sized_ptr {
+    ($(#[$outer:meta])* $ty:ident => $keyword:expr) => {
+        $(#[$outer])*
+        pub struct $ty<A>(pub A);
+
+        impl<A: Address> Display for $ty<A> {
+            fn fmt(&self, f: &mut Formatter) -> Result {
+                write!(f, "{} PTR {}", $keyword, self.0)
+            }
+        }
+
+        impl<A: Address> Address for $ty<A> {}
+    };
+}
+
+sized_ptr!(
+    /// 1 byte のメモリ
+    BytePtr => "BYTE"
+);
+sized_ptr!(
+    /// 2 byte のメモリ
+    WordPtr => "WORD"
+);
+sized_ptr!(
+    /// 4 byte のメモリ
+    DwordPtr => "DWORD"
+);
@@ -1,7 +1,7 @@
 use super::Asm;
 use std::{
     fs::OpenOptions,
-    io::{Result as IoResult, Write},
+    io::{BufWriter, Result as IoResult, Write},
     ops::AddAssign,
     path::Path,
 };
@@ -30,13 +30,29 @@ impl AsmBuf {
         Ok(())
     }
 
+    /// 書き込みはバッファリングし、最後にflushして書き込みのエラーを返す
     pub fn output_stdout(&self) -> IoResult<()> {
-        self.output(&mut std::io::stdout())
+        let mut stdout = BufWriter::new(std::io::stdout().lock());
+        self.output(&mut stdout)?;
+        stdout.flush()
     }
 
+    /// 書き込みはバッファリングし、最後にflushして書き込みのエラーを返す
     pub fn output_file(&self, path: impl AsRef<Path>) -> IoResult<()> {
-        let mut file = OpenOptions::new().create(true).write(true).open(path)?;
-        self.output(&mut file)
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        let mut file = BufWriter::new(file);
+        self.output(&mut file)?;
+        file.flush()
+    }
+}
+
+impl Default for AsmBuf {
+    fn default() -> Self {
+        AsmBuf::new()
     }
 }
 
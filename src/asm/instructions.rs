@@ -1,8 +1,12 @@
-use super::{addr::Address, reg::*, Asm};
+use super::{
+    addr::{Address, BytePtr, DwordPtr, WordPtr},
+    reg::*,
+    Asm,
+};
 
 /// Instructionを表す構造体を定義する
 ///
-/// ```
+/// ```ignore
 /// pub struct Mov<T1, T2>(pub T1, pub T2);
 ///
 /// pub fn mov<T1, T2>(T1: T1, T2, T2) -> Mov<T1, T2> {
@@ -61,7 +65,7 @@ macro_rules! instruction {
 
 /// `Asm` trait を実装する
 ///
-/// ```
+/// ```ignore
 /// impl Asm for Mov<Reg64, Reg64> {
 ///     fn write(&self, w: &mut dyn std::io::Write) -> std::io::Result<()> {
 ///         write!(w, "  {} {}, {}\n", Self::opcode(), self.0, self.1)
@@ -121,6 +125,23 @@ instruction! {cmp =>
 impl_asm!(Cmp<Reg64, i64>);
 impl_asm!(Cmp<Reg64, Reg64>);
 
+// ucomisd
+instruction! {ucomisd =>
+    /// `T1` と `T2` を倍精度浮動小数点数として比較し、その結果を
+    /// ZF, PF, CF にセットする。
+    /// `T1` < `T2` ならCF、`T1` == `T2` ならZFがセットされる。
+    /// どちらかがNaNの場合は全てのフラグがセットされる。
+    pub struct Ucomisd<T1, T2>
+}
+impl_asm!(Ucomisd<RegXmm, RegXmm>);
+
+// ucomiss
+instruction! {ucomiss =>
+    /// `ucomisd` の単精度浮動小数点数版
+    pub struct Ucomiss<T1, T2>
+}
+impl_asm!(Ucomiss<RegXmm, RegXmm>);
+
 // mov
 instruction! {mov =>
     /// `T2` の値を `T1` にコピーする
@@ -128,8 +149,70 @@ instruction! {mov =>
     pub struct Mov<T1, T2>
 }
 impl_asm!(Mov<Reg64, Reg64>);
+impl_asm!(Mov<Reg64, i64>);
+impl_asm!(Mov<Reg32, Reg32>);
 impl_asm!(Mov<A, Reg64> where A: Address);
+impl_asm!(Mov<A, Reg32> where A: Address);
+impl_asm!(Mov<A, Reg16> where A: Address);
+impl_asm!(Mov<A, Reg8> where A: Address);
 impl_asm!(Mov<Reg64, A> where A: Address);
+impl_asm!(Mov<Reg32, A> where A: Address);
+
+// movq
+instruction! {movq =>
+    /// `T2` の下位64bitを `T1` にコピーする。
+    /// 汎用レジスタとXMMレジスタの間でビット列をそのまま移す
+    pub struct Movq<T1, T2>
+}
+impl_asm!(Movq<RegXmm, Reg64>);
+impl_asm!(Movq<Reg64, RegXmm>);
+
+// movd
+instruction! {movd =>
+    /// `T2` の下位32bitを `T1` にコピーする。
+    /// 書き込み先の上位のbitはゼロクリアされる
+    pub struct Movd<T1, T2>
+}
+impl_asm!(Movd<Reg32, RegXmm>);
+
+// movsd
+instruction! {movsd =>
+    /// 倍精度浮動小数点数を1つコピーする
+    pub struct Movsd<T1, T2>
+}
+impl_asm!(Movsd<A, RegXmm> where A: Address);
+
+// movss
+instruction! {movss =>
+    /// 単精度浮動小数点数を1つコピーする
+    pub struct Movss<T1, T2>
+}
+impl_asm!(Movss<A, RegXmm> where A: Address);
+
+// lea
+instruction! {lea =>
+    /// `T2` のアドレスを計算し、その値を `T1` にコピーする
+    pub struct Lea<T1, T2>
+}
+impl_asm!(Lea<Reg64, A> where A: Address);
+
+// movsx
+instruction! {movsx =>
+    /// `T2` の値を符号拡張して `T1` にコピーする
+    pub struct Movsx<T1, T2>
+}
+impl_asm!(Movsx<Reg64, Reg8>);
+impl_asm!(Movsx<Reg64, Reg16>);
+impl_asm!(Movsx<Reg64, BytePtr<A>> where A: Address);
+impl_asm!(Movsx<Reg64, WordPtr<A>> where A: Address);
+
+// movsxd
+instruction! {movsxd =>
+    /// 32bitの `T2` の値を符号拡張して `T1` にコピーする
+    pub struct Movsxd<T1, T2>
+}
+impl_asm!(Movsxd<Reg64, Reg32>);
+impl_asm!(Movsxd<Reg64, DwordPtr<A>> where A: Address);
 
 // movzx
 instruction! {movzx =>
@@ -137,6 +220,51 @@ instruction! {movzx =>
     pub struct Movzx<T1, T2>
 }
 impl_asm!(Movzx<Reg64, Reg8>);
+impl_asm!(Movzx<Reg64, Reg16>);
+impl_asm!(Movzx<Reg64, BytePtr<A>> where A: Address);
+impl_asm!(Movzx<Reg64, WordPtr<A>> where A: Address);
+
+// cvtsi2sd
+instruction! {cvtsi2sd =>
+    /// 符号付き整数の `T2` を倍精度浮動小数点数に変換して `T1` にセットする
+    pub struct Cvtsi2sd<T1, T2>
+}
+impl_asm!(Cvtsi2sd<RegXmm, Reg64>);
+
+// cvtsi2ss
+instruction! {cvtsi2ss =>
+    /// 符号付き整数の `T2` を単精度浮動小数点数に変換して `T1` にセットする
+    pub struct Cvtsi2ss<T1, T2>
+}
+impl_asm!(Cvtsi2ss<RegXmm, Reg64>);
+
+// cvttsd2si
+instruction! {cvttsd2si =>
+    /// 倍精度浮動小数点数の `T2` を0方向に丸めた符号付き整数に変換して `T1` にセットする
+    pub struct Cvttsd2si<T1, T2>
+}
+impl_asm!(Cvttsd2si<Reg64, RegXmm>);
+
+// cvttss2si
+instruction! {cvttss2si =>
+    /// 単精度浮動小数点数の `T2` を0方向に丸めた符号付き整数に変換して `T1` にセットする
+    pub struct Cvttss2si<T1, T2>
+}
+impl_asm!(Cvttss2si<Reg64, RegXmm>);
+
+// cvtsd2ss
+instruction! {cvtsd2ss =>
+    /// 倍精度浮動小数点数の `T2` を単精度浮動小数点数に変換して `T1` にセットする
+    pub struct Cvtsd2ss<T1, T2>
+}
+impl_asm!(Cvtsd2ss<RegXmm, RegXmm>);
+
+// cvtss2sd
+instruction! {cvtss2sd =>
+    /// 単精度浮動小数点数の `T2` を倍精度浮動小数点数に変換して `T1` にセットする
+    pub struct Cvtss2sd<T1, T2>
+}
+impl_asm!(Cvtss2sd<RegXmm, RegXmm>);
 
 // pop
 instruction! {pop =>
@@ -159,11 +287,19 @@ instruction! {ret =>
 }
 impl_asm!(Ret);
 
+// call
+instruction! {call =>
+    /// `T` の値が指すアドレスの関数を呼び出す
+    pub struct Call<T>
+}
+impl_asm!(Call<Reg64>);
+
 // add
 instruction! {add =>
     /// `T1` = `T1` + `T2`
     pub struct Add<T1, T2>
 }
+impl_asm!(Add<Reg64, i64>);
 impl_asm!(Add<Reg64, Reg64>);
 
 // sub
@@ -174,6 +310,43 @@ instruction! {sub =>
 impl_asm!(Sub<Reg64, i64>);
 impl_asm!(Sub<Reg64, Reg64>);
 
+// neg
+instruction! {neg =>
+    /// `T` = -`T`
+    pub struct Neg<T>
+}
+impl_asm!(Neg<Reg64>);
+
+// and
+instruction! {and =>
+    /// `T1` = `T1` & `T2`
+    pub struct And<T1, T2>
+}
+impl_asm!(And<Reg64, i64>);
+impl_asm!(And<Reg8, Reg8>);
+
+// or
+instruction! {or =>
+    /// `T1` = `T1` | `T2`
+    pub struct Or<T1, T2>
+}
+impl_asm!(Or<Reg64, Reg64>);
+impl_asm!(Or<Reg8, Reg8>);
+
+// xor
+instruction! {xor =>
+    /// `T1` = `T1` ^ `T2`
+    pub struct Xor<T1, T2>
+}
+impl_asm!(Xor<Reg64, Reg64>);
+
+// shr
+instruction! {shr =>
+    /// `T1` を `T2` bitだけ論理右シフトする
+    pub struct Shr<T1, T2>
+}
+impl_asm!(Shr<Reg64, i64>);
+
 // imul
 instruction! {imul =>
     pub struct Imul<T1, T2>
@@ -192,6 +365,39 @@ instruction! {idiv =>
 }
 impl_asm!(Idiv<Reg64>);
 
+// div
+instruction! {div =>
+    /// RDX:RAX を符号なし整数として `T` で割り、
+    /// 商をRAXに、余りをRDXにセットする
+    pub struct Div<T>
+}
+impl_asm!(Div<Reg64>);
+
+/// SSEの算術命令を定義する
+macro_rules! sse_arith {
+    ($($lower:ident => $ty:ident: $doc:expr;)*) => {
+        $(
+            instruction! {$lower =>
+                #[doc = $doc]
+                pub struct $ty<T1, T2>
+            }
+            impl_asm!($ty<RegXmm, RegXmm>);
+        )*
+    };
+}
+
+sse_arith! {
+    addsd => Addsd: "倍精度浮動小数点数の `T1` = `T1` + `T2`";
+    subsd => Subsd: "倍精度浮動小数点数の `T1` = `T1` - `T2`";
+    mulsd => Mulsd: "倍精度浮動小数点数の `T1` = `T1` * `T2`";
+    divsd => Divsd: "倍精度浮動小数点数の `T1` = `T1` / `T2`";
+    addss => Addss: "単精度浮動小数点数の `T1` = `T1` + `T2`";
+    subss => Subss: "単精度浮動小数点数の `T1` = `T1` - `T2`";
+    mulss => Mulss: "単精度浮動小数点数の `T1` = `T1` * `T2`";
+    divss => Divss: "単精度浮動小数点数の `T1` = `T1` / `T2`";
+    xorps => Xorps: "`T1` = `T1` ^ `T2`。同じレジスタを指定すると0にできる";
+}
+
 // sete
 instruction! {sete =>
     /// ZF（ゼロフラグ）がセットされていれば（ZF == 1 であれば）
@@ -227,3 +433,62 @@ instruction! {setle =>
     pub struct Setle<T>
 }
 impl_asm!(Setle<Reg8>);
+
+// setb
+instruction! {setb =>
+    /// CF（キャリーフラグ）がセットされていれば
+    /// 指定された場所に1を書き込む。
+    /// セットされていなければ0を書き込む。
+    /// 符号なし整数の比較で使う。
+    pub struct Setb<T>
+}
+impl_asm!(Setb<Reg8>);
+
+// setbe
+instruction! {setbe =>
+    /// CF（キャリーフラグ）かZF（ゼロフラグ）がセットされていれば
+    /// 指定された場所に1を書き込む。
+    /// セットされていなければ0を書き込む。
+    /// 符号なし整数の比較で使う。
+    pub struct Setbe<T>
+}
+impl_asm!(Setbe<Reg8>);
+
+// seta
+instruction! {seta =>
+    /// CF（キャリーフラグ）とZF（ゼロフラグ）がどちらもセットされていなければ
+    /// 指定された場所に1を書き込む。
+    /// セットされていれば0を書き込む。
+    /// 符号なし整数や浮動小数点数の比較で使う。
+    pub struct Seta<T>
+}
+impl_asm!(Seta<Reg8>);
+
+// setae
+instruction! {setae =>
+    /// CF（キャリーフラグ）がセットされていなければ
+    /// 指定された場所に1を書き込む。
+    /// セットされていれば0を書き込む。
+    /// 符号なし整数や浮動小数点数の比較で使う。
+    pub struct Setae<T>
+}
+impl_asm!(Setae<Reg8>);
+
+// setp
+instruction! {setp =>
+    /// PF（パリティフラグ）がセットされていれば
+    /// 指定された場所に1を書き込む。
+    /// セットされていなければ0を書き込む。
+    /// 浮動小数点数の比較では、オペランドがNaNであることを表す。
+    pub struct Setp<T>
+}
+impl_asm!(Setp<Reg8>);
+
+// setnp
+instruction! {setnp =>
+    /// PF（パリティフラグ）がセットされていなければ
+    /// 指定された場所に1を書き込む。
+    /// セットされていれば0を書き込む。
+    pub struct Setnp<T>
+}
+impl_asm!(Setnp<Reg8>);
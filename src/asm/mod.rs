@@ -3,9 +3,9 @@ mod buf;
 pub mod instructions;
 pub mod reg;
 
-pub use addr::Addr;
+pub use addr::{Addr, BytePtr, DwordPtr, GotEntry, Label, WordPtr};
 pub use buf::AsmBuf;
-pub use reg::{Reg16, Reg32, Reg64, Reg8};
+pub use reg::{Reg16, Reg32, Reg64, Reg8, RegXmm};
 
 use std::io::{Result as IoResult, Write};
 
@@ -25,6 +25,6 @@ pub fn arbitrary(s: impl Into<String>) -> Arbitrary {
 
 impl Asm for Arbitrary {
     fn write(&self, w: &mut dyn Write) -> IoResult<()> {
-        write!(w, "{}\n", self.0)
+        writeln!(w, "{}", self.0)
     }
 }
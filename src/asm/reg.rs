@@ -6,6 +6,7 @@ pub enum Reg {
     Reg32(Reg32),
     Reg16(Reg16),
     Reg8(Reg8),
+    Xmm(RegXmm),
 }
 
 impl Display for Reg {
@@ -15,6 +16,7 @@ impl Display for Reg {
             Reg::Reg32(reg) => reg.fmt(f),
             Reg::Reg16(reg) => reg.fmt(f),
             Reg::Reg8(reg) => reg.fmt(f),
+            Reg::Xmm(reg) => reg.fmt(f),
         }
     }
 }
@@ -254,3 +256,32 @@ impl Display for Reg8 {
         }
     }
 }
+
+/// SSEの128bitレジスタ。
+/// 浮動小数点数の演算と、浮動小数点数の引数と返り値の受け渡しに使う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegXmm {
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+}
+
+impl Display for RegXmm {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            RegXmm::XMM0 => write!(f, "xmm0"),
+            RegXmm::XMM1 => write!(f, "xmm1"),
+            RegXmm::XMM2 => write!(f, "xmm2"),
+            RegXmm::XMM3 => write!(f, "xmm3"),
+            RegXmm::XMM4 => write!(f, "xmm4"),
+            RegXmm::XMM5 => write!(f, "xmm5"),
+            RegXmm::XMM6 => write!(f, "xmm6"),
+            RegXmm::XMM7 => write!(f, "xmm7"),
+        }
+    }
+}
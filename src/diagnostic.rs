@@ -0,0 +1,99 @@
+use crate::token::{SourceMap, Span};
+use std::fmt::{Display, Error as FmtError, Formatter};
+
+/// 字句解析やパースで報告するエラーの最大数のデフォルト値
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// 診断メッセージの重要度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// ソースコード上の範囲を伴う診断メッセージ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    pub msg: String,
+}
+
+impl Diagnostic {
+    pub fn error(span: impl Into<Span>, msg: impl Into<String>) -> Self {
+        Diagnostic {
+            span: span.into(),
+            severity: Severity::Error,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn warning(span: impl Into<Span>, msg: impl Into<String>) -> Self {
+        Diagnostic {
+            span: span.into(),
+            severity: Severity::Warning,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// `source_map` を参照し、該当するソースコードを含めた文字列を返す
+    pub fn display(&self, source_map: &SourceMap) -> String {
+        source_map.display(self.span, self.to_string().as_str())
+    }
+}
+
+/// ソースコードの位置を含まない、メッセージのみの表示
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(f, "{}: {}", self.severity, self.msg)
+    }
+}
+
+/// コンパイルが失敗したことを表すエラー。
+/// 失敗の原因となった診断メッセージを保持する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompileError {
+    /// `source_map` を参照し、該当するソースコードを含めた文字列を返す
+    pub fn display(&self, source_map: &SourceMap) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.display(source_map) + "\n")
+            .collect()
+    }
+}
+
+impl From<Diagnostic> for CompileError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        CompileError {
+            diagnostics: vec![diagnostic],
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        for diagnostic in self.diagnostics.iter() {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompileError {}
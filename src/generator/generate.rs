@@ -0,0 +1,109 @@
+use super::subroutine::{static_label, SubroutineGen};
+use crate::{
+    asm::{arbitrary, AsmBuf},
+    diagnostic::CompileError,
+    parser::ast::*,
+};
+
+pub struct Generator();
+
+impl Generator {
+    pub fn new() -> Self {
+        Generator()
+    }
+
+    pub fn gen<'a>(&mut self, program: &Program<'a>, buf: &mut AsmBuf) -> Result<(), CompileError> {
+        *buf += arbitrary(".intel_syntax noprefix");
+
+        // "extern" 宣言された変数は他の翻訳単位で定義されている
+        for global in program.globals.iter().filter(|g| !g.is_extern()) {
+            self.gen_global_var(global, buf);
+        }
+
+        for func_def in &program.func_defs {
+            self.gen_func_def(func_def, buf)?;
+        }
+
+        self.gen_prelude(buf);
+        SubroutineGen::new().gen_subroutine(&program.stmts, program.locals_len, buf)?;
+        Ok(())
+    }
+
+    /// グローバル変数を配置する。
+    /// 初期化子のある変数は `.data` セクションに、無い変数は `.bss` セクションに配置する。
+    /// "const" の付いた変数は、初期化子の有無に関わらず読み取り専用の `.const` セクション
+    /// (ELFの `.rodata` に相当する) に配置する。
+    /// ただし関数や変数のアドレスを含む変数は、ロード時に書き換えられるので `.data` セクションに配置する。
+    /// 初期値の無い部分は0で埋める
+    pub fn gen_global_var(&self, global: &GlobalVar, buf: &mut AsmBuf) {
+        let var = &global.decl.var;
+        let size = var.ty.size();
+        let has_reloc = global
+            .init_values
+            .iter()
+            .any(|init| matches!(init.value, StaticValue::Func(_) | StaticValue::Var(..)));
+        let section = if var.quals.is_const && !has_reloc {
+            ".const"
+        } else if global.decl.init.is_some() {
+            ".data"
+        } else {
+            ".bss"
+        };
+
+        let label = static_label(var.loc);
+        *buf += arbitrary(section);
+        // 内部結合のシンボルは他の翻訳単位に公開しない
+        if global.linkage == Linkage::External {
+            *buf += arbitrary(format!(".global {}", label));
+        }
+        *buf += arbitrary(format!(".balign {}", var.ty.align()));
+        *buf += arbitrary(format!("{}:", label));
+
+        let mut filled = 0;
+        for init in &global.init_values {
+            if init.offset > filled {
+                *buf += arbitrary(format!(".zero {}", init.offset - filled));
+            }
+            // 型のサイズに切り詰めた初期値を出力する。
+            // 関数や変数のアドレスはリンカに解決させる
+            *buf += arbitrary(match (init.value, init.ty.size()) {
+                (StaticValue::Num(value), 1) => format!(".byte {}", value as u8),
+                (StaticValue::Num(value), 2) => format!(".short {}", value as u16),
+                (StaticValue::Num(value), 4) => format!(".long {}", value as u32),
+                (StaticValue::Num(value), _) => format!(".quad {}", value),
+                (StaticValue::Func(name), _) => format!(".quad _{}", name),
+                (StaticValue::Var(loc, 0), _) => format!(".quad {}", static_label(loc)),
+                (StaticValue::Var(loc, offset), _) => {
+                    format!(".quad {}+{}", static_label(loc), offset)
+                }
+            });
+            filled = init.offset + init.ty.size();
+        }
+        if size > filled {
+            *buf += arbitrary(format!(".zero {}", size - filled));
+        }
+    }
+
+    /// 関数定義を `.text` セクションに配置する
+    pub fn gen_func_def(&self, func_def: &FuncDef, buf: &mut AsmBuf) -> Result<(), CompileError> {
+        *buf += arbitrary(".text");
+        if func_def.linkage == Linkage::External {
+            *buf += arbitrary(format!(".global _{}", func_def.ident.name));
+        }
+        *buf += arbitrary(format!("_{}:", func_def.ident.name));
+        SubroutineGen::new().gen_func(func_def, buf)?;
+        Ok(())
+    }
+
+    pub fn gen_prelude(&self, buf: &mut AsmBuf) {
+        *buf += arbitrary(".text");
+        *buf += arbitrary(".global _main");
+        *buf += arbitrary("_main:");
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator::new()
+    }
+}
@@ -1,7 +1,7 @@
-mod generator;
+mod generate;
 mod subroutine;
 
-pub use generator::Generator;
+pub use generate::Generator;
 
 /// GlobalにUniqueな数値を取得する。
 /// GlobalにUniqueなラベルを生成するのに使用する
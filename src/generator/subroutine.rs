@@ -1,19 +1,87 @@
 use super::get_unique_num;
 use crate::{
-    asm::{arbitrary, instructions::*, Addr, AsmBuf, Reg64::*, Reg8::*},
+    asm::{
+        arbitrary, instructions::*, Addr, AsmBuf, BytePtr, DwordPtr, GotEntry, Label, Reg16::*,
+        Reg32::*, Reg64, Reg64::*, Reg8::*, RegXmm, RegXmm::*, WordPtr,
+    },
+    diagnostic::Diagnostic,
     parser::ast::*,
+    ty::{align_to, FloatType, IntType, Type},
 };
 
+type GResult = Result<(), Diagnostic>;
+
+/// 関数の引数を渡すレジスタ。
+/// 7個目以降の引数はスタックに積んで渡す。
+const ARG_REGS: [Reg64; 6] = [RDI, RSI, RDX, RCX, R8, R9];
+
+/// 浮動小数点数の引数を渡すレジスタ。
+/// 9個目以降の浮動小数点数の引数はスタックに積んで渡す。
+const FLOAT_ARG_REGS: [RegXmm; 8] = [XMM0, XMM1, XMM2, XMM3, XMM4, XMM5, XMM6, XMM7];
+
+/// `va_list` の `gp_offset` の上限。
+/// 汎用レジスタの後にXMMレジスタが退避されている
+const GP_SAVE_AREA_SIZE: usize = 8 * ARG_REGS.len();
+
+/// `va_list` の `fp_offset` の上限
+const REG_SAVE_AREA_SIZE: usize = GP_SAVE_AREA_SIZE + 16 * FLOAT_ARG_REGS.len();
+
+/// 引数を渡す場所
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgLoc {
+    Reg(Reg64),
+    Xmm(RegXmm),
+    Stack,
+}
+
+/// System V ABI に従って、それぞれの引数を渡す場所を決める。
+/// 浮動小数点数はXMMレジスタで、それ以外は汎用レジスタで渡し、
+/// レジスタが足りなくなった引数は先頭から順にスタックに積む。
+fn classify_args(tys: impl Iterator<Item = Type>) -> Vec<ArgLoc> {
+    let mut regs = ARG_REGS.iter();
+    let mut xmm_regs = FLOAT_ARG_REGS.iter();
+
+    tys.map(|ty| {
+        let loc = match ty {
+            Type::Float(_) => xmm_regs.next().map(|reg| ArgLoc::Xmm(*reg)),
+            Type::Int(_) | Type::FuncPtr(_) | Type::Ptr(_) | Type::VaList => {
+                regs.next().map(|reg| ArgLoc::Reg(*reg))
+            }
+            Type::Array(_) | Type::Struct(_) => {
+                unreachable!("aggregates cannot be passed as arguments")
+            }
+            Type::Void => unreachable!("void values cannot be passed as arguments"),
+        };
+        loc.unwrap_or(ArgLoc::Stack)
+    })
+    .collect()
+}
+
 /// サブルーチンのコードを生成するジェネレータ
 pub struct SubroutineGen {
     /// `call` によって積まれるreturn addressも **含めた** スタックの長さ.
     /// 16 byte alignするときに使う。
     stack_len: usize,
+    /// プロローグで確保したローカル変数の領域の大きさ
+    frame_size: usize,
+}
+
+/// 静的な領域に配置された変数のラベル。
+/// "static" の付いたローカル変数は、同じ名前の変数と衝突しないよう番号を付ける
+pub fn static_label(loc: VarLoc) -> String {
+    match loc {
+        VarLoc::Global(name) => format!("_{}", name),
+        VarLoc::Static(name, num) => format!("_{}.{}", name, num),
+        VarLoc::Local(_) => unreachable!("local variables have no label"),
+    }
 }
 
 impl SubroutineGen {
     pub fn new() -> Self {
-        SubroutineGen { stack_len: 0 }
+        SubroutineGen {
+            stack_len: 0,
+            frame_size: 0,
+        }
     }
 
     pub fn inc_stack_len(&mut self) {
@@ -24,24 +92,104 @@ impl SubroutineGen {
         self.stack_len -= 8;
     }
 
-    pub fn gen_subroutine<'a>(mut self, stmts: &[Stmt<'a>], buf: &mut AsmBuf) {
-        self.gen_prologue(26, buf);
+    pub fn gen_subroutine<'a>(
+        mut self,
+        stmts: &[Stmt<'a>],
+        locals_len: usize,
+        buf: &mut AsmBuf,
+    ) -> GResult {
+        self.gen_prologue(locals_len, buf);
+
+        // 最後のstmtが式文であれば、その値をmain関数の返り値にする
+        let (last_expr, stmts) = match stmts.split_last() {
+            Some((Stmt::Expr(StmtExpr { expr, .. }), init)) => (Some(expr), init),
+            _ => (None, stmts),
+        };
 
         for stmt in stmts {
-            self.gen_stmt(stmt, buf);
+            self.gen_stmt(stmt, buf)?;
+        }
+
+        match last_expr {
+            Some(expr) => {
+                self.gen_expr(expr, buf)?;
+                // C言語のABIでは返り値はRAXレジスタに入れる
+                *buf += pop(RAX);
+                self.dec_stack_len();
+            }
+            // returnせずに終わりに達した場合は0を返す
+            None => *buf += mov(RAX, 0),
+        }
+
+        self.gen_epilogue(buf);
+
+        Ok(())
+    }
+
+    /// 関数定義のコードを生成する
+    pub fn gen_func<'a>(mut self, func_def: &FuncDef<'a>, buf: &mut AsmBuf) -> GResult {
+        self.gen_prologue(func_def.locals_len, buf);
+
+        // 可変長引数を取る場合は、va_argで読めるよう引数レジスタをすべて退避しておく
+        if let Some(reg_save_area) = func_def.reg_save_area {
+            for (i, reg) in ARG_REGS.iter().enumerate() {
+                *buf += mov(Addr(RBP) - (reg_save_area - 8 * i) as i64, *reg);
+            }
+            // XMMレジスタは16 byteずつ退避する。
+            // 可変長引数の浮動小数点数はdoubleなので、下位8 byteだけ書き込めばよい
+            for (i, reg) in FLOAT_ARG_REGS.iter().enumerate() {
+                let offset = reg_save_area - GP_SAVE_AREA_SIZE - 16 * i;
+                *buf += movsd(Addr(RBP) - offset as i64, *reg);
+            }
+        }
+
+        // 引数を仮引数のローカル変数にコピーする。
+        // 整数の引数は型に応じて拡張された状態で渡されるので、8 byteのまま書き込んでよい
+        let locs = classify_args(func_def.param_vars.iter().map(|var| var.ty));
+        let mut stack_params = 0;
+        for (var, loc) in func_def.param_vars.iter().zip(locs) {
+            let offset = match var.loc {
+                VarLoc::Local(offset) => {
+                    self.check_in_frame(offset);
+                    offset as i64
+                }
+                VarLoc::Global(_) | VarLoc::Static(..) => {
+                    unreachable!("parameters are always local")
+                }
+            };
+
+            match (loc, var.ty) {
+                (ArgLoc::Reg(reg), _) => *buf += mov(Addr(RBP) - offset, reg),
+                (ArgLoc::Xmm(reg), Type::Float(FloatType::Float)) => {
+                    *buf += movss(Addr(RBP) - offset, reg)
+                }
+                (ArgLoc::Xmm(reg), _) => *buf += movsd(Addr(RBP) - offset, reg),
+                (ArgLoc::Stack, _) => {
+                    // return addressと退避したRBPの上に、レジスタに載らなかった引数が積まれている
+                    let arg_offset = 16 + 8 * stack_params;
+                    stack_params += 1;
+                    *buf += mov(RAX, Addr(RBP) + arg_offset);
+                    *buf += mov(Addr(RBP) - offset, RAX);
+                }
+            }
         }
 
-        // 最後にスタックに残っていた値をRAXレジスタにpopする。
-        // C言語のABIでは返り値はRAXレジスタに入れる。
-        // もうstack_lenの値は使わないのでdec_stack_lenしない
-        *buf += pop(RAX);
+        for stmt in &func_def.body.stmts {
+            self.gen_stmt(stmt, buf)?;
+        }
 
+        // returnせずに関数の終わりに達した場合は0を返す
+        *buf += mov(RAX, 0);
         self.gen_epilogue(buf);
+
+        Ok(())
     }
 
     // プロローグコードを修正
     // サブルーチンに移行するたびに呼び出す
-    pub fn gen_prologue(&mut self, stack_bytes: i64, buf: &mut AsmBuf) {
+    //
+    // `locals_len` はパーサが割り当てたローカル変数の領域の大きさ
+    pub fn gen_prologue(&mut self, locals_len: usize, buf: &mut AsmBuf) {
         // return addressの分
         self.inc_stack_len();
 
@@ -53,7 +201,15 @@ impl SubroutineGen {
         *buf += mov(RBP, RSP);
 
         // stack領域の確保 (スタックポインタの移動)
-        *buf += sub(RSP, 8 * stack_bytes);
+        // RSPが16 byteにalignされたままになるよう、16 byte単位で確保する
+        // パーサは領域の大きさを32bitの即値で表せる範囲に制限している
+        self.frame_size = align_to(locals_len, 16);
+        assert!(
+            self.frame_size <= i32::MAX as usize,
+            "stack frame of {} bytes does not fit in imm32",
+            self.frame_size
+        );
+        *buf += sub(RSP, self.frame_size as i64);
     }
 
     /// エピローグコードを生成
@@ -74,30 +230,71 @@ impl SubroutineGen {
         *buf += ret();
     }
 
-    /// １つのstmtを処理するようなコードを生成する
-    pub fn gen_stmt<'a>(&mut self, stmt: &Stmt<'a>, buf: &mut AsmBuf) {
-        match stmt {
-            Stmt::Expr(expr) => self.gen_expr(expr, buf),
+    /// スタックの長さが `expected` と一致しているか検査する。
+    /// 一致しない場合はコード生成の誤りなので、その場でpanicする
+    fn check_stack_len(&self, expected: usize, at: &str) {
+        assert_eq!(
+            self.stack_len, expected,
+            "stack is unbalanced at {}: expected {} bytes, found {} bytes",
+            at, expected, self.stack_len
+        );
+    }
 
-            // ローカル変数にスタックトップの値を代入する
-            Stmt::Assign(StmtAssign {
-                lhs_offset, rhs, ..
-            }) => {
-                self.gen_expr(rhs, buf);
+    /// １つのstmtを処理するようなコードを生成する。
+    /// stmtの前後でスタックの長さが変わらないことを検査する
+    pub fn gen_stmt<'a>(&mut self, stmt: &Stmt<'a>, buf: &mut AsmBuf) -> GResult {
+        let stack_len = self.stack_len;
+        self.gen_stmt_body(stmt, buf)?;
+        self.check_stack_len(stack_len, "the end of a statement");
+        Ok(())
+    }
 
+    fn gen_stmt_body<'a>(&mut self, stmt: &Stmt<'a>, buf: &mut AsmBuf) -> GResult {
+        match stmt {
+            // 式文の値は使わないので捨てる
+            Stmt::Expr(StmtExpr { expr, .. }) => {
+                self.gen_expr(expr, buf)?;
                 *buf += pop(RAX);
                 self.dec_stack_len();
+            }
+
+            // "static" の付いた変数は静的な領域で初期化されている
+            Stmt::Decl(StmtDecl {
+                storage_class: Some(StorageClass::Static(_)),
+                ..
+            }) => {}
 
-                *buf += mov(Addr(RBP) - *lhs_offset as i64, RAX);
+            // 宣言子の順に、初期化式があればその値を変数に代入する
+            Stmt::Decl(StmtDecl { declarators, .. }) => {
+                for InitDeclarator { var, init, .. } in declarators {
+                    match init {
+                        None => {}
+                        Some((_, Initializer::Expr(init))) => {
+                            self.gen_expr(init, buf)?;
+                            self.gen_assign(var, buf);
+                        }
+                        Some((_, Initializer::List(list))) => self.gen_init_list(var, list, buf)?,
+                    }
+                }
             }
 
+            // 型の定義と空のstmtはコードを生成しない
+            Stmt::TypeDecl(_) | Stmt::Null(_) => {}
+
             Stmt::Return(StmtReturn { expr, .. }) => {
-                // 式を評価する（ようなコードを生成する）
-                self.gen_expr(expr, buf);
+                if let Some(expr) = expr {
+                    // 式を評価する（ようなコードを生成する）
+                    self.gen_expr(expr, buf)?;
 
-                // 評価結果を取り出す
-                *buf += pop(RAX);
-                self.dec_stack_len();
+                    // 評価結果を取り出す
+                    *buf += pop(RAX);
+                    self.dec_stack_len();
+
+                    // 浮動小数点数はXMM0で返す
+                    if let Type::Float(_) = expr.ty() {
+                        *buf += movq(XMM0, RAX);
+                    }
+                }
 
                 // エピローグ
                 self.gen_epilogue(buf);
@@ -110,25 +307,28 @@ impl SubroutineGen {
                 ..
             }) => {
                 // 式を評価する（ようなコードを生成する）
-                self.gen_expr(cond, buf);
+                self.gen_expr(cond, buf)?;
 
                 // 評価結果を取り出す
                 *buf += pop(RAX);
                 self.dec_stack_len();
 
                 // 取り出した値が0と等しいかどうか
-                *buf += cmp(RAX, 0);
+                self.gen_cmp_zero(cond.ty(), buf);
 
                 // 等しければ一連のコードの終わりにjumpする
                 // つまり、以下の処理をスキップする
                 let end_label = format!("L_if_end_{}", get_unique_num());
                 *buf += arbitrary(format!("  je {}", end_label));
+                let join_len = self.stack_len;
 
                 // stmtを評価する
                 // `expr` の評価結果が0ならこのコードはスキップされる
-                self.gen_stmt(then_branch, buf);
+                self.gen_stmt(then_branch, buf)?;
 
                 // ジャンプ先
+                // どちらの経路から来てもスタックの長さは同じでなければならない
+                self.check_stack_len(join_len, "the end of \"if\"");
                 *buf += arbitrary(format!("{}:", end_label));
             }
 
@@ -139,22 +339,23 @@ impl SubroutineGen {
                 ..
             }) => {
                 // 式を評価する（ようなコードを生成する）
-                self.gen_expr(cond, buf);
+                self.gen_expr(cond, buf)?;
 
                 // 評価結果を取り出す
                 *buf += pop(RAX);
                 self.dec_stack_len();
 
                 // 評価結果が0と等しいかどうか
-                *buf += cmp(RAX, 0);
+                self.gen_cmp_zero(cond.ty(), buf);
 
                 // 等しければ `else_label` にjumpする
                 let label_num = get_unique_num();
                 let else_label = format!("L_if_else_{}", label_num);
                 *buf += arbitrary(format!("  je {}", else_label));
+                let join_len = self.stack_len;
 
                 // 評価結果がtrueのときに実行されるstmt
-                self.gen_stmt(then_branch, buf);
+                self.gen_stmt(then_branch, buf)?;
 
                 // 実行が終わったら `end_label` にjumpする
                 // つまりelseのstmtをスキップする
@@ -165,9 +366,11 @@ impl SubroutineGen {
                 *buf += arbitrary(format!("{}:", else_label));
 
                 // 評価結果がfalseのときに実行されるstmt
-                self.gen_stmt(else_branch, buf);
+                self.gen_stmt(else_branch, buf)?;
 
                 // end_labelのジャンプ先
+                // どちらの経路から来てもスタックの長さは同じでなければならない
+                self.check_stack_len(join_len, "the end of \"if\"");
                 *buf += arbitrary(format!("{}:", end_label));
             }
 
@@ -176,155 +379,791 @@ impl SubroutineGen {
                 let label_num = get_unique_num();
                 let begin_label = format!("L_loop_begin_{}", label_num);
                 *buf += arbitrary(format!("{}:", begin_label));
+                let loop_len = self.stack_len;
 
                 // ループ判定の式を評価するコード
-                self.gen_expr(cond, buf);
+                self.gen_expr(cond, buf)?;
 
                 // ループ判定の結果を取り出す
                 *buf += pop(RAX);
                 self.dec_stack_len();
 
                 // 判定の結果が0と等しければend_labelにジャンプ
-                *buf += cmp(RAX, 0);
+                self.gen_cmp_zero(cond.ty(), buf);
                 let end_label = format!("L_loop_end_{}", label_num);
                 *buf += arbitrary(format!("  je {}", end_label));
 
                 // stmtを実行するコード
-                self.gen_stmt(block, buf);
+                self.gen_stmt(block, buf)?;
 
                 // ループの先頭に戻る
+                // 1回の繰り返しでスタックが伸び縮みしてはならない
+                self.check_stack_len(loop_len, "the end of \"while\" body");
                 *buf += arbitrary(format!("  jmp {}", begin_label));
 
                 // ループを抜け出した場所
+                self.check_stack_len(loop_len, "the end of \"while\"");
                 *buf += arbitrary(format!("{}:", end_label));
             }
 
             Stmt::Block(StmtBlock { stmts, .. }) => {
                 for stmt in stmts {
-                    self.gen_stmt(stmt, buf);
+                    self.gen_stmt(stmt, buf)?;
                 }
             }
         }
+
+        Ok(())
     }
 
     // スタックトップにexprの結果の値を1つ載せるようなコードを生成する
-    pub fn gen_expr<'a>(&mut self, expr: &Expr<'a>, buf: &mut AsmBuf) {
+    pub fn gen_expr<'a>(&mut self, expr: &Expr<'a>, buf: &mut AsmBuf) -> GResult {
         match expr {
             // スタックトップに即値を載せる
             Expr::Num(n) => {
-                *buf += push(n.num as i64);
+                let num = n.num as i64;
+                if num == num as i32 as i64 {
+                    // 32bitの即値は符号拡張されてpushされる
+                    *buf += push(num);
+                } else {
+                    // 32bitに収まらない即値は直接pushできないので
+                    // 一度レジスタに載せる
+                    *buf += mov(RAX, num);
+                    *buf += push(RAX);
+                }
                 self.inc_stack_len();
             }
 
             // スタックトップに変数の値を載せる
-            Expr::Ident(ExprIdent { ident_offset, .. }) => {
-                *buf += mov(RAX, Addr(RBP) - *ident_offset as i64);
+            Expr::Ident(ExprIdent { var, .. }) => {
+                self.gen_var_addr(var, buf);
+                self.gen_load(var.ty, buf);
+                *buf += push(RAX);
+                self.inc_stack_len();
+            }
+
+            // スタックトップに配列の要素や構造体のメンバの値を載せる
+            Expr::Index(_) | Expr::Member(_) => {
+                self.gen_addr(expr, buf)?;
+                self.gen_load(expr.ty(), buf);
                 *buf += push(RAX);
                 self.inc_stack_len();
             }
 
+            // 関数のアドレスをスタックトップに載せる。
+            // 共有ライブラリの関数も指せるよう、アドレスはGOTから読む
+            Expr::Func(ExprFunc { ident, .. }) => {
+                *buf += mov(RAX, Addr(GotEntry(format!("_{}", ident.name))));
+                *buf += push(RAX);
+                self.inc_stack_len();
+            }
+
+            // 変数や要素のアドレスをスタックトップに載せる
+            Expr::Addr(addr) if matches!(addr.ty(), Type::Ptr(_)) => {
+                self.gen_addr(&addr.expr, buf)?;
+                *buf += push(RAX);
+                self.inc_stack_len();
+            }
+
+            // ポインタの指す先の値をスタックトップに載せる
+            Expr::Deref(deref) if matches!(deref.expr.ty(), Type::Ptr(_)) => {
+                self.gen_addr(expr, buf)?;
+                self.gen_load(expr.ty(), buf);
+                *buf += push(RAX);
+                self.inc_stack_len();
+            }
+
+            // 関数へのポインタはそのまま値になる
+            Expr::Addr(ExprAddr { expr, .. }) | Expr::Deref(ExprDeref { expr, .. }) => {
+                self.gen_expr(expr, buf)?
+            }
+
             // 関数を呼び出す
-            Expr::Call(ExprCall {
-                ident: func,
-                params,
-                ..
-            }) => {
-                if params.len() > 6 {
-                    eprintln!("6個より多い引数には対応していません");
-                    std::process::exit(1);
+            Expr::Call(expr_call @ ExprCall { callee, params, .. }) => {
+                let func_ty = expr_call.func_ty();
+                let direct_callee = expr_call.direct_callee();
+
+                let locs = classify_args(params.iter().map(|param| param.ty()));
+                let stack_params = locs.iter().filter(|loc| **loc == ArgLoc::Stack).count();
+
+                // call命令の時点でRSPが16 byteにalignされるよう、
+                // 引数を積む前にパディングを入れておく
+                let padding = !(self.stack_len + stack_params * 8).is_multiple_of(16);
+                if padding {
+                    *buf += sub(RSP, 8);
+                    self.inc_stack_len();
                 }
 
-                // 引数を評価する
-                for param in params.iter() {
-                    self.gen_expr(param, buf);
+                // スタックで渡す引数を右から順に評価して積み、
+                // その上にレジスタで渡す引数を右から順に評価して積む
+                let (stack_args, reg_args): (Vec<_>, Vec<_>) = params
+                    .iter()
+                    .zip(&locs)
+                    .partition(|(_, loc)| **loc == ArgLoc::Stack);
+                for (param, _) in stack_args.iter().rev().chain(reg_args.iter().rev()) {
+                    self.gen_expr(param, buf)?;
                 }
 
-                // 引数をレジスタに載せる
-                // スタックには逆順で評価結果が乗っている
-                for (i, _param) in params.iter().enumerate().rev() {
-                    let reg = match i {
-                        0 => RDI,
-                        1 => RSI,
-                        2 => RDX,
-                        3 => RCX,
-                        4 => R8,
-                        5 => R9,
-                        _ => unreachable!(),
-                    };
+                // 関数名以外を呼び出す場合は、呼び出す関数のアドレスを
+                // 引数を渡すのに使わないR10に載せておく
+                if direct_callee.is_none() {
+                    self.gen_expr(callee, buf)?;
+                    *buf += pop(R10);
+                    self.dec_stack_len();
+                }
 
-                    *buf += pop(reg);
+                // レジスタで渡す引数を先頭から順にレジスタに載せる。
+                // 残りの引数はスタックトップから順に並んでいる
+                for (_, loc) in &reg_args {
+                    match loc {
+                        ArgLoc::Reg(reg) => *buf += pop(*reg),
+                        ArgLoc::Xmm(reg) => {
+                            *buf += pop(RAX);
+                            *buf += movq(*reg, RAX);
+                        }
+                        ArgLoc::Stack => unreachable!(),
+                    }
                     self.dec_stack_len();
                 }
 
-                // RSP を16 byte にalignする
-                if self.stack_len % 16 != 0 {
-                    *buf += sub(RSP, 8);
+                // 可変長引数を取る関数では、ALにベクタレジスタで渡す引数の数をセットする。
+                // 宣言されていない関数も可変長引数を取る可能性があるのでセットしておく
+                if func_ty.is_variadic {
+                    let xmm_args = locs
+                        .iter()
+                        .filter(|loc| matches!(loc, ArgLoc::Xmm(_)))
+                        .count();
+                    *buf += mov(RAX, xmm_args as i64);
                 }
 
                 // 関数の呼び出し
-                *buf += arbitrary(format!("  call _{}", func.name));
+                match direct_callee {
+                    Some(ExprFunc { ident, .. }) => {
+                        *buf += arbitrary(format!("  call _{}", ident.name))
+                    }
+                    None => *buf += call(R10),
+                }
+
+                // スタックに積んだ引数とパディングを取り除く
+                let cleanup = stack_params + padding as usize;
+                if cleanup > 0 {
+                    *buf += add(RSP, 8 * cleanup as i64);
+                    for _ in 0..cleanup {
+                        self.dec_stack_len();
+                    }
+                }
+
+                // 返り値を型に応じて拡張し、スタックトップに載せる。
+                // 浮動小数点数はXMM0で返される。
+                // 返り値の無い関数でも、他のexprと同じく1つの値を積む
+                match expr.ty() {
+                    Type::Float(ty) => self.gen_from_xmm0(ty, buf),
+                    Type::Int(ty) => self.gen_int_cast(ty, buf),
+                    Type::Void | Type::FuncPtr(_) | Type::Ptr(_) => {}
+                    Type::VaList | Type::Array(_) | Type::Struct(_) => {
+                        unreachable!("functions cannot return {}", expr.ty())
+                    }
+                }
+                *buf += push(RAX);
+                self.inc_stack_len();
+            }
+
+            Expr::Paren(ExprParen { expr, .. }) => self.gen_expr(expr, buf)?,
+
+            // 左辺を評価して値を捨ててから、右辺の値をスタックトップに載せる
+            // 変数や要素に右辺の値を代入し、代入した値をスタックトップに残す
+            Expr::Assign(ExprAssign { lhs, rhs, .. }) => {
+                self.gen_expr(rhs, buf)?;
+                self.gen_addr(lhs, buf)?;
+                *buf += mov(RDI, Addr(RSP));
+                self.gen_store(lhs.ty(), buf);
+            }
+
+            Expr::Comma(ExprComma { lhs, rhs, .. }) => {
+                self.gen_expr(lhs, buf)?;
+                *buf += pop(RAX);
+                self.dec_stack_len();
+                self.gen_expr(rhs, buf)?;
+            }
+
+            Expr::Va(ExprVa { op, ap, .. }) => {
+                // `va_list` は配列なので、その先頭のアドレスがスタックトップに載る
+                self.gen_expr(ap, buf)?;
+                self.gen_va(op, buf);
+            }
+
+            // スタックトップの値を型変換する
+            Expr::Cast(ExprCast {
+                type_name: TypeName { ty, .. },
+                expr,
+                ..
+            })
+            | Expr::ImplicitCast(ExprImplicitCast { ty, expr }) => {
+                self.gen_expr(expr, buf)?;
+
+                *buf += pop(RAX);
+                self.gen_cast(expr.ty(), *ty, buf);
+                *buf += push(RAX);
             }
 
-            Expr::Paren(ExprParen { expr, .. }) => self.gen_expr(expr, buf),
+            // スタックトップの値の符号を反転する
+            Expr::Unary(unary) => {
+                self.gen_expr(&unary.expr, buf)?;
+
+                if let UnaryOp::Neg(_) = unary.op {
+                    *buf += pop(RAX);
+                    match unary.ty() {
+                        // 浮動小数点数は符号ビットだけを反転させる。
+                        // 0から引くと -0.0 にならないため
+                        Type::Float(FloatType::Float) => {
+                            *buf += mov(RDI, 0x8000_0000);
+                            *buf += xor(RAX, RDI);
+                        }
+                        Type::Float(FloatType::Double) => {
+                            *buf += mov(RDI, i64::MIN);
+                            *buf += xor(RAX, RDI);
+                        }
+                        Type::Int(ty) => {
+                            *buf += neg(RAX);
+                            self.gen_int_cast(ty, buf);
+                        }
+                        _ => unreachable!(),
+                    }
+                    *buf += push(RAX);
+                }
+            }
 
             // スタックトップに計算結果を載せる
-            Expr::BinOp(ExprBinOp { lhs, op, rhs }) => {
+            Expr::BinOp(binop @ ExprBinOp { lhs, op, rhs }) => {
                 // スタックトップに1つ値が残る（ようなコードを生成する）
-                self.gen_expr(lhs, buf);
+                self.gen_expr(lhs, buf)?;
                 // スタックトップに1つ値が残る（ようなコードを生成する）
-                self.gen_expr(rhs, buf);
+                self.gen_expr(rhs, buf)?;
 
-                // 左ブランチの計算結果をrdiレジスタに記録
+                // 右ブランチの計算結果をrdiレジスタに記録
                 *buf += pop(RDI);
                 self.dec_stack_len();
-                // 右ブランチの計算結果をraxレジスタに記録
+                // 左ブランチの計算結果をraxレジスタに記録
                 *buf += pop(RAX);
                 self.dec_stack_len();
 
-                match op {
-                    BinOp::Add(_) => *buf += add(RAX, RDI),
-                    BinOp::Sub(_) => *buf += sub(RAX, RDI),
-                    BinOp::Mul(_) => *buf += imul(RAX, RDI),
-                    BinOp::Div(_) => {
-                        *buf += cqo();
-                        *buf += idiv(RDI);
-                    }
-                    BinOp::Eq(_) => {
-                        // RAXとRDIが等しければZFを立てる
-                        *buf += cmp(RAX, RDI);
-                        // ZFが立っていればALに1をセットする
-                        *buf += sete(AL);
-                        // ALの値をゼロ拡張してRAXにコピーする
-                        *buf += movzx(RAX, AL);
-                    }
-                    BinOp::Neq(_) => {
-                        // RAXとRDIが等しければZFを立てる
-                        *buf += cmp(RAX, RDI);
-                        // ZFが立っていなければALに1をセットする
-                        *buf += setne(AL);
-                        // ALの値をゼロ拡張してRAXにコピーする
-                        *buf += movzx(RAX, AL);
-                    }
-                    BinOp::Lt(_) => {
-                        // RAX - RDIの結果をステータスフラグにセットする
-                        *buf += cmp(RAX, RDI);
-                        // SF <> OF のときにALに1をセットする
-                        *buf += setl(AL);
-                        // ALの値をゼロ拡張してRAXにコピーする
-                        *buf += movzx(RAX, AL);
-                    }
-                    BinOp::Lte(_) => {
-                        // RAXとRDIが等しければZFを立てる
-                        *buf += cmp(RAX, RDI);
-                        *buf += setle(AL);
-                        // ALの値をゼロ拡張してRAXにコピーする
-                        *buf += movzx(RAX, AL);
-                    }
+                match binop.operand_ty() {
+                    Type::Float(ty) => self.gen_float_binop(*op, ty, buf),
+                    _ => self.gen_int_binop(binop, buf),
+                }
+
+                *buf += push(RAX);
+                self.inc_stack_len();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// RAXの値とRDIの値の整数の演算を行い、結果をRAXにセットする
+    fn gen_int_binop(&mut self, binop: &ExprBinOp, buf: &mut AsmBuf) {
+        // 除算と比較は符号の有無で命令が異なる
+        let is_unsigned = binop
+            .operand_ty()
+            .as_int()
+            .is_some_and(|ty| !ty.is_signed());
+        let op = &binop.op;
+
+        match op {
+            BinOp::Add(_) => *buf += add(RAX, RDI),
+            BinOp::Sub(_) => *buf += sub(RAX, RDI),
+            BinOp::Mul(_) => *buf += imul(RAX, RDI),
+            BinOp::Div(_) if is_unsigned => {
+                // RDX:RAX をRDIで割るので、RDXを0にしておく
+                *buf += mov(RDX, 0);
+                *buf += div(RDI);
+            }
+            BinOp::Div(_) => {
+                // RAXを符号拡張してRDX:RAXにセットする
+                *buf += cqo();
+                *buf += idiv(RDI);
+            }
+            BinOp::Eq(_) => {
+                // RAXとRDIが等しければZFを立てる
+                *buf += cmp(RAX, RDI);
+                // ZFが立っていればALに1をセットする
+                *buf += sete(AL);
+                // ALの値をゼロ拡張してRAXにコピーする
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Neq(_) => {
+                // RAXとRDIが等しければZFを立てる
+                *buf += cmp(RAX, RDI);
+                // ZFが立っていなければALに1をセットする
+                *buf += setne(AL);
+                // ALの値をゼロ拡張してRAXにコピーする
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Lt(_) if is_unsigned => {
+                // RAX - RDIの結果をステータスフラグにセットする
+                *buf += cmp(RAX, RDI);
+                // CF == 1 のときにALに1をセットする
+                *buf += setb(AL);
+                // ALの値をゼロ拡張してRAXにコピーする
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Lt(_) => {
+                // RAX - RDIの結果をステータスフラグにセットする
+                *buf += cmp(RAX, RDI);
+                // SF <> OF のときにALに1をセットする
+                *buf += setl(AL);
+                // ALの値をゼロ拡張してRAXにコピーする
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Lte(_) if is_unsigned => {
+                // RAX - RDIの結果をステータスフラグにセットする
+                *buf += cmp(RAX, RDI);
+                // CF == 1 または ZF == 1 のときにALに1をセットする
+                *buf += setbe(AL);
+                // ALの値をゼロ拡張してRAXにコピーする
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Lte(_) => {
+                // RAXとRDIが等しければZFを立てる
+                *buf += cmp(RAX, RDI);
+                *buf += setle(AL);
+                // ALの値をゼロ拡張してRAXにコピーする
+                *buf += movzx(RAX, AL);
+            }
+        }
+
+        // 四則演算の結果は64bitで計算されているので、演算結果の型に揃える
+        if matches!(
+            op,
+            BinOp::Add(_) | BinOp::Sub(_) | BinOp::Mul(_) | BinOp::Div(_)
+        ) {
+            if let Type::Int(ty) = binop.ty() {
+                self.gen_int_cast(ty, buf);
+            }
+        }
+    }
+
+    /// RAXの値とRDIの値の浮動小数点数の演算を行い、結果をRAXにセットする
+    fn gen_float_binop(&mut self, op: BinOp, ty: FloatType, buf: &mut AsmBuf) {
+        let is_double = ty == FloatType::Double;
+
+        *buf += movq(XMM0, RAX);
+        *buf += movq(XMM1, RDI);
+
+        match op {
+            BinOp::Add(_) if is_double => *buf += addsd(XMM0, XMM1),
+            BinOp::Add(_) => *buf += addss(XMM0, XMM1),
+            BinOp::Sub(_) if is_double => *buf += subsd(XMM0, XMM1),
+            BinOp::Sub(_) => *buf += subss(XMM0, XMM1),
+            BinOp::Mul(_) if is_double => *buf += mulsd(XMM0, XMM1),
+            BinOp::Mul(_) => *buf += mulss(XMM0, XMM1),
+            BinOp::Div(_) if is_double => *buf += divsd(XMM0, XMM1),
+            BinOp::Div(_) => *buf += divss(XMM0, XMM1),
+            BinOp::Eq(_) => {
+                self.gen_ucomi(ty, XMM0, XMM1, buf);
+                // 等しく、かつどちらもNaNでなければALに1をセットする
+                *buf += sete(AL);
+                *buf += setnp(CL);
+                *buf += and(AL, CL);
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Neq(_) => {
+                self.gen_ucomi(ty, XMM0, XMM1, buf);
+                // 等しくないか、どちらかがNaNであればALに1をセットする
+                *buf += setne(AL);
+                *buf += setp(CL);
+                *buf += or(AL, CL);
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Lt(_) => {
+                // XMM1 > XMM0 のときにALに1をセットする。
+                // NaNとの比較ではCFが立つので0になる
+                self.gen_ucomi(ty, XMM1, XMM0, buf);
+                *buf += seta(AL);
+                *buf += movzx(RAX, AL);
+            }
+            BinOp::Lte(_) => {
+                // XMM1 >= XMM0 のときにALに1をセットする
+                self.gen_ucomi(ty, XMM1, XMM0, buf);
+                *buf += setae(AL);
+                *buf += movzx(RAX, AL);
+            }
+        }
+
+        if matches!(
+            op,
+            BinOp::Add(_) | BinOp::Sub(_) | BinOp::Mul(_) | BinOp::Div(_)
+        ) {
+            self.gen_from_xmm0(ty, buf);
+        }
+    }
+
+    /// `ty` 型の浮動小数点数として `lhs` と `rhs` を比較する
+    fn gen_ucomi(&mut self, ty: FloatType, lhs: RegXmm, rhs: RegXmm, buf: &mut AsmBuf) {
+        match ty {
+            FloatType::Float => *buf += ucomiss(lhs, rhs),
+            FloatType::Double => *buf += ucomisd(lhs, rhs),
+        }
+    }
+
+    /// RAXの `ty` 型の値を0と比較し、等しければZFをセットする。
+    /// 浮動小数点数のNaNは0と等しくないものとして扱う。
+    fn gen_cmp_zero(&mut self, ty: Type, buf: &mut AsmBuf) {
+        if let Type::Float(ty) = ty {
+            *buf += movq(XMM0, RAX);
+            *buf += xorps(XMM1, XMM1);
+            self.gen_ucomi(ty, XMM0, XMM1, buf);
+            *buf += setne(AL);
+            *buf += setp(CL);
+            *buf += or(AL, CL);
+            *buf += movzx(RAX, AL);
+        }
+        *buf += cmp(RAX, 0);
+    }
+
+    /// XMM0の `ty` 型の値をRAXにセットする。
+    /// float の場合は上位32bitをゼロクリアする。
+    fn gen_from_xmm0(&mut self, ty: FloatType, buf: &mut AsmBuf) {
+        match ty {
+            FloatType::Float => *buf += movd(EAX, XMM0),
+            FloatType::Double => *buf += movq(RAX, XMM0),
+        }
+    }
+
+    /// RAXの `from` 型の値を `to` 型に変換する
+    fn gen_cast(&mut self, from: Type, to: Type, buf: &mut AsmBuf) {
+        match (from, to) {
+            (Type::Int(from), Type::Float(to)) => self.gen_int_to_float(from, to, buf),
+            (Type::Float(from), Type::Int(to)) => {
+                self.gen_float_to_int(from, to, buf);
+                self.gen_int_cast(to, buf);
+            }
+            (Type::Float(from), Type::Float(to)) if from != to => {
+                *buf += movq(XMM0, RAX);
+                match to {
+                    FloatType::Float => *buf += cvtsd2ss(XMM0, XMM0),
+                    FloatType::Double => *buf += cvtss2sd(XMM0, XMM0),
                 }
+                self.gen_from_xmm0(to, buf);
+            }
+            (_, Type::Int(to)) => self.gen_int_cast(to, buf),
+            // ポインタは64bitの整数と同じ表現を持ち、
+            // 配列はアドレスとして扱うので変換しない。
+            // voidへの変換では値が使われない
+            (_, Type::Void)
+            | (_, Type::Float(_))
+            | (_, Type::FuncPtr(_))
+            | (_, Type::Ptr(_))
+            | (_, Type::VaList)
+            | (_, Type::Array(_))
+            | (_, Type::Struct(_)) => {}
+        }
+    }
+
+    /// RAXの整数を浮動小数点数に変換する
+    fn gen_int_to_float(&mut self, from: IntType, to: FloatType, buf: &mut AsmBuf) {
+        let cvtsi2 = |buf: &mut AsmBuf, src| match to {
+            FloatType::Float => *buf += cvtsi2ss(XMM0, src),
+            FloatType::Double => *buf += cvtsi2sd(XMM0, src),
+        };
+
+        if from == IntType::ULong {
+            // 最上位bitが立っている値は符号付き整数として変換できないので、
+            // 1bit右シフトした値を変換してから2倍する。
+            // 丸めの結果が変わらないよう、シフトで落ちたbitを最下位bitに残しておく
+            let label_num = get_unique_num();
+            let large_label = format!("L_cvt_large_{}", label_num);
+            let end_label = format!("L_cvt_end_{}", label_num);
+
+            *buf += cmp(RAX, 0);
+            *buf += arbitrary(format!("  jl {}", large_label));
+            cvtsi2(buf, RAX);
+            *buf += arbitrary(format!("  jmp {}", end_label));
+
+            *buf += arbitrary(format!("{}:", large_label));
+            *buf += mov(RDI, RAX);
+            *buf += shr(RDI, 1);
+            *buf += and(RAX, 1);
+            *buf += or(RDI, RAX);
+            cvtsi2(buf, RDI);
+            match to {
+                FloatType::Float => *buf += addss(XMM0, XMM0),
+                FloatType::Double => *buf += addsd(XMM0, XMM0),
+            }
+
+            *buf += arbitrary(format!("{}:", end_label));
+        } else {
+            // 符号なし整数もゼロ拡張されているので、64bitの符号付き整数として変換できる
+            cvtsi2(buf, RAX);
+        }
+
+        self.gen_from_xmm0(to, buf);
+    }
+
+    /// RAXの浮動小数点数を0方向に丸めて64bitの整数に変換する
+    fn gen_float_to_int(&mut self, from: FloatType, to: IntType, buf: &mut AsmBuf) {
+        *buf += movq(XMM0, RAX);
+
+        if to != IntType::ULong {
+            match from {
+                FloatType::Float => *buf += cvttss2si(RAX, XMM0),
+                FloatType::Double => *buf += cvttsd2si(RAX, XMM0),
+            }
+            return;
+        }
+
+        // 2^63以上の値は符号付き整数として変換できないので、
+        // 2^63を引いてから変換し、最上位bitを立てる
+        if from == FloatType::Float {
+            *buf += cvtss2sd(XMM0, XMM0);
+        }
+        let label_num = get_unique_num();
+        let large_label = format!("L_cvt_large_{}", label_num);
+        let end_label = format!("L_cvt_end_{}", label_num);
+
+        *buf += mov(RCX, (2.0f64.powi(63)).to_bits() as i64);
+        *buf += movq(XMM1, RCX);
+        *buf += ucomisd(XMM0, XMM1);
+        *buf += arbitrary(format!("  jae {}", large_label));
+        *buf += cvttsd2si(RAX, XMM0);
+        *buf += arbitrary(format!("  jmp {}", end_label));
+
+        *buf += arbitrary(format!("{}:", large_label));
+        *buf += subsd(XMM0, XMM1);
+        *buf += cvttsd2si(RAX, XMM0);
+        *buf += mov(RCX, i64::MIN);
+        *buf += xor(RAX, RCX);
+
+        *buf += arbitrary(format!("{}:", end_label));
+    }
+
+    /// スタックトップの `va_list` のアドレスをpopし、`op` を実行した結果をスタックトップに載せる。
+    ///
+    /// `va_list` はSystem V ABIの `__va_list_tag` と同じレイアウトを持つ。
+    ///
+    /// | offset | field               |
+    /// |--------|---------------------|
+    /// | 0      | `gp_offset`         |
+    /// | 4      | `fp_offset`         |
+    /// | 8      | `overflow_arg_area` |
+    /// | 16     | `reg_save_area`     |
+    fn gen_va(&mut self, op: &VaOp, buf: &mut AsmBuf) {
+        match op {
+            VaOp::Start { frame, .. } => {
+                *buf += pop(RAX);
+                self.dec_stack_len();
 
+                *buf += mov(RCX, frame.gp_offset as i64);
+                *buf += mov(Addr(RAX), ECX);
+                *buf += mov(RCX, frame.fp_offset as i64);
+                *buf += mov(Addr(RAX) + 4, ECX);
+                *buf += lea(RCX, Addr(RBP) + frame.overflow_arg_area as i64);
+                *buf += mov(Addr(RAX) + 8, RCX);
+                *buf += lea(RCX, Addr(RBP) - frame.reg_save_area as i64);
+                *buf += mov(Addr(RAX) + 16, RCX);
+
+                *buf += push(0);
+                self.inc_stack_len();
+            }
+
+            VaOp::Arg(TypeName { ty, .. }) => {
+                *buf += pop(RDI);
+                self.dec_stack_len();
+
+                let label_num = get_unique_num();
+                let stack_label = format!("L_va_arg_stack_{}", label_num);
+                let end_label = format!("L_va_arg_end_{}", label_num);
+
+                // 浮動小数点数はfp_offset、それ以外はgp_offsetを使って退避領域を読む
+                let (field, limit, step) = match ty {
+                    Type::Float(_) => (4, REG_SAVE_AREA_SIZE, 16),
+                    _ => (0, GP_SAVE_AREA_SIZE, 8),
+                };
+
+                // レジスタ退避領域を使い切っていれば、スタックで渡された引数を読む
+                *buf += mov(ECX, Addr(RDI) + field);
+                *buf += cmp(RCX, limit as i64);
+                *buf += arbitrary(format!("  jae {}", stack_label));
+
+                // reg_save_area + offset の位置の引数を読み、offsetを進める
+                *buf += mov(RAX, Addr(RDI) + 16);
+                *buf += add(RAX, RCX);
+                *buf += add(RCX, step);
+                *buf += mov(Addr(RDI) + field, ECX);
+                *buf += arbitrary(format!("  jmp {}", end_label));
+
+                // overflow_arg_area の位置の引数を読み、overflow_arg_areaを進める
+                *buf += arbitrary(format!("{}:", stack_label));
+                *buf += mov(RAX, Addr(RDI) + 8);
+                *buf += lea(RCX, Addr(RAX) + 8);
+                *buf += mov(Addr(RDI) + 8, RCX);
+
+                // RAXが指す引数を型に応じて読み込む
+                *buf += arbitrary(format!("{}:", end_label));
+                self.gen_load(*ty, buf);
                 *buf += push(RAX);
                 self.inc_stack_len();
             }
+
+            // 後始末が必要なものは無い
+            VaOp::End => {
+                *buf += pop(RAX);
+                *buf += push(0);
+            }
+        }
+    }
+
+    /// スタックトップの値をpopし、変数に代入する
+    fn gen_assign(&mut self, var: &Var, buf: &mut AsmBuf) {
+        *buf += pop(RDI);
+        self.dec_stack_len();
+
+        self.gen_var_addr(var, buf);
+        self.gen_store(var.ty, buf);
+    }
+
+    /// RAXの値を `int_ty` 型の値として表現できるよう切り詰め、
+    /// 符号付きなら符号拡張、符号なしならゼロ拡張する。
+    fn gen_int_cast(&mut self, int_ty: IntType, buf: &mut AsmBuf) {
+        match (int_ty.size(), int_ty.is_signed()) {
+            (1, true) => *buf += movsx(RAX, AL),
+            (1, false) => *buf += movzx(RAX, AL),
+            (2, true) => *buf += movsx(RAX, AX),
+            (2, false) => *buf += movzx(RAX, AX),
+            (4, true) => *buf += movsxd(RAX, EAX),
+            // 32bitレジスタへのmovは上位32bitをゼロクリアする
+            (4, false) => *buf += mov(EAX, EAX),
+            _ => {}
+        }
+    }
+
+    /// 変数のアドレスをRAXにセットする
+    fn gen_var_addr(&mut self, var: &Var, buf: &mut AsmBuf) {
+        match var.loc {
+            VarLoc::Local(offset) => {
+                self.check_in_frame(offset);
+                *buf += lea(RAX, Addr(RBP) - offset as i64)
+            }
+            VarLoc::Global(_) | VarLoc::Static(..) => {
+                *buf += lea(RAX, Addr(Label(static_label(var.loc))))
+            }
+        }
+    }
+
+    /// RBPから `offset` byte下にあるローカル変数が、プロローグで確保した領域に収まっているか検査する
+    fn check_in_frame(&self, offset: usize) {
+        assert!(
+            offset <= self.frame_size,
+            "local variable at offset {} overruns the frame of {} bytes",
+            offset,
+            self.frame_size
+        );
+    }
+
+    /// 変数か、配列の要素や構造体のメンバを表すexprのアドレスをRAXにセットする
+    fn gen_addr(&mut self, expr: &Expr, buf: &mut AsmBuf) -> GResult {
+        match expr {
+            Expr::Ident(ExprIdent { var, .. }) => self.gen_var_addr(var, buf),
+            Expr::Paren(ExprParen { expr, .. }) => self.gen_addr(expr, buf)?,
+            // "(f(), a)[0]" のように集成体型の値になる場合は、右辺のアドレスを使う
+            Expr::Comma(ExprComma { lhs, rhs, .. }) => {
+                self.gen_expr(lhs, buf)?;
+                *buf += pop(RAX);
+                self.dec_stack_len();
+                self.gen_addr(rhs, buf)?;
+            }
+            Expr::Index(index) => {
+                self.gen_addr(&index.base, buf)?;
+                *buf += push(RAX);
+                self.inc_stack_len();
+
+                self.gen_expr(&index.index, buf)?;
+                *buf += pop(RDI);
+                self.dec_stack_len();
+                *buf += pop(RAX);
+                self.dec_stack_len();
+
+                // 添字に要素のサイズを掛けて先頭のアドレスに足す
+                *buf += mov(RCX, index.elem_ty().size() as i64);
+                *buf += imul(RDI, RCX);
+                *buf += add(RAX, RDI);
+            }
+            Expr::Member(ExprMember { base, member, .. }) => {
+                self.gen_addr(base, buf)?;
+                *buf += add(RAX, member.offset as i64);
+            }
+            // ポインタの値がそのまま指す先のアドレスになる
+            Expr::Deref(ExprDeref { expr, .. }) => {
+                self.gen_expr(expr, buf)?;
+                *buf += pop(RAX);
+                self.dec_stack_len();
+            }
+            // 集成体型の値になるexprは変数とその要素だけ
+            _ => unreachable!("expression has no address: {:?}", expr),
+        }
+        Ok(())
+    }
+
+    /// 集成体型のローカル変数を初期化子で初期化する。
+    /// 変数全体を0で埋めてから、初期化子に含まれる要素を書き込む
+    fn gen_init_list(&mut self, var: &Var, list: &InitList, buf: &mut AsmBuf) -> GResult {
+        let offset = match var.loc {
+            VarLoc::Local(offset) => offset,
+            VarLoc::Global(_) | VarLoc::Static(..) => {
+                unreachable!("static variables are initialized statically")
+            }
+        };
+        self.check_in_frame(offset);
+
+        // ローカル変数の領域は8 byte単位で割り当てられている
+        *buf += xor(RDI, RDI);
+        for i in 0..var.ty.size().div_ceil(8) {
+            *buf += mov(Addr(RBP) - (offset - 8 * i) as i64, RDI);
+        }
+
+        for elem in &list.elems {
+            self.gen_expr(&elem.expr, buf)?;
+            *buf += pop(RDI);
+            self.dec_stack_len();
+
+            *buf += lea(RAX, Addr(RBP) - (offset - elem.offset) as i64);
+            self.gen_store(elem.ty, buf);
+        }
+        Ok(())
+    }
+
+    /// RAXが指すアドレスから `ty` 型の値を読み込み、RAXにセットする。
+    /// 8 byteより小さい値は型に応じて符号拡張またはゼロ拡張する。
+    fn gen_load(&mut self, ty: Type, buf: &mut AsmBuf) {
+        match ty {
+            Type::Int(int_ty) => match (int_ty.size(), int_ty.is_signed()) {
+                (1, true) => *buf += movsx(RAX, BytePtr(Addr(RAX))),
+                (1, false) => *buf += movzx(RAX, BytePtr(Addr(RAX))),
+                (2, true) => *buf += movsx(RAX, WordPtr(Addr(RAX))),
+                (2, false) => *buf += movzx(RAX, WordPtr(Addr(RAX))),
+                (4, true) => *buf += movsxd(RAX, DwordPtr(Addr(RAX))),
+                // 32bitレジスタへのmovは上位32bitをゼロクリアする
+                (4, false) => *buf += mov(EAX, Addr(RAX)),
+                _ => *buf += mov(RAX, Addr(RAX)),
+            },
+            Type::FuncPtr(_) | Type::Ptr(_) => *buf += mov(RAX, Addr(RAX)),
+            // 浮動小数点数はビット列をそのまま読み込む
+            Type::Float(FloatType::Float) => *buf += mov(EAX, Addr(RAX)),
+            Type::Float(FloatType::Double) => *buf += mov(RAX, Addr(RAX)),
+            // 配列や構造体は先頭のアドレスに変換されるので、RAXをそのまま値とする
+            Type::VaList | Type::Array(_) | Type::Struct(_) => {}
+            Type::Void => unreachable!("void values cannot be loaded"),
+        }
+    }
+
+    /// RDIの値を `ty` 型の値としてRAXが指すアドレスに書き込む。
+    /// 8 byteより小さい型の場合は下位のbitだけが書き込まれる。
+    fn gen_store(&mut self, ty: Type, buf: &mut AsmBuf) {
+        match ty.size() {
+            1 => *buf += mov(Addr(RAX), DIL),
+            2 => *buf += mov(Addr(RAX), DI),
+            4 => *buf += mov(Addr(RAX), EDI),
+            _ => *buf += mov(Addr(RAX), RDI),
         }
     }
 }
@@ -1,4 +1,7 @@
 pub mod asm;
+pub mod diagnostic;
 pub mod generator;
 pub mod parser;
+pub mod preprocessor;
 pub mod token;
+pub mod ty;
@@ -1,15 +1,273 @@
-use atomcc::{asm::AsmBuf, generator, parser, token::tokenize};
+use atomcc::{
+    asm::AsmBuf,
+    diagnostic::{CompileError, DEFAULT_MAX_ERRORS},
+    generator, parser,
+    preprocessor::Preprocessor,
+    token::{tokenize_with_max_errors, FileId, SourceMap, TokenStream},
+};
+use std::{
+    io::Read,
+    path::{Component, Path, PathBuf},
+    process,
+};
+
+/// コンパイルエラーが見つかった場合の終了コード
+const EXIT_COMPILE_ERROR: i32 = 1;
+
+/// コマンドライン引数が不正な場合の終了コード
+const EXIT_USAGE: i32 = 2;
+
+/// ファイルの読み書きに失敗した場合の終了コード
+const EXIT_IO_ERROR: i32 = 3;
+
+const USAGE: &str = "\
+usage: atomcc [options] <file>...
+
+Compile each <file> into x86-64 assembly. Use \"-\" as <file> to read from stdin.
+With a single <file>, the assembly is written to stdout.
+With several, the assembly of <dir>/<name>.c is written to <name>.s
+in the current directory, and that of \"-\" to stdout.
+
+options:
+  -o <file>   write the assembly to <file> instead (only with a single <file>)
+  -I <dir>    add <dir> to the #include search path
+  -ferror-limit=<n>
+              stop after <n> errors in each <file> (default: 20, 0: no limit)
+  -h, --help  print this help and exit
+
+exit status:
+  0  the assembly was written
+  1  the source has compile errors
+  2  the command line is invalid
+  3  a file cannot be read or written
+
+If several <file>s fail, the largest of their exit statuses is used.
+";
+
+/// コマンドライン引数で指定された設定
+#[derive(Debug, Default)]
+struct Options {
+    /// 入力ファイルのパス。"-" の場合は標準入力から読む
+    inputs: Vec<String>,
+    /// 出力ファイルのパス。入力ファイルが1つの場合だけ指定できる
+    output: Option<PathBuf>,
+    /// "#include" でファイルを探すディレクトリ
+    include_dirs: Vec<PathBuf>,
+    /// 字句解析とパースで報告するエラーの最大数
+    max_errors: usize,
+}
+
+/// コマンドライン引数が指示する動作
+enum Command {
+    Compile(Options),
+    Help,
+}
 
 fn main() {
-    let arg = std::env::args().nth(1).unwrap();
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Compile(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(msg) => {
+            eprintln!("atomcc: error: {}", msg);
+            eprintln!("Try \"atomcc --help\" for more information.");
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    // 失敗した入力ファイルがあっても、残りのファイルのコンパイルを続ける
+    let exit_code = options
+        .inputs
+        .iter()
+        .map(|input| match compile_file(input, &options) {
+            Ok(()) => 0,
+            Err(exit_code) => exit_code,
+        })
+        .max()
+        .unwrap_or(0);
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
+}
+
+/// `input` をコンパイルしてアセンブリを出力する。
+/// 失敗した場合はエラーを表示し、終了コードを `Err` で返す。
+fn compile_file(input: &str, options: &Options) -> Result<(), i32> {
+    let (name, src) = read_input(input).map_err(|e| {
+        eprintln!("atomcc: error: cannot read \"{}\": {}", input, e);
+        EXIT_IO_ERROR
+    })?;
+
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file(name, src);
+
+    let asm = compile(&mut source_map, file, options).map_err(|e| {
+        eprint!("{}", e.display(&source_map));
+        EXIT_COMPILE_ERROR
+    })?;
+
+    let (result, output_name) = match output_path(input, options) {
+        Some(path) => (asm.output_file(&path), path.display().to_string()),
+        None => (asm.output_stdout(), "<stdout>".to_string()),
+    };
+    result.map_err(|e| {
+        eprintln!("atomcc: error: cannot write \"{}\": {}", output_name, e);
+        EXIT_IO_ERROR
+    })
+}
+
+/// `input` のアセンブリを書き込むファイルのパスを返す。
+/// `None` の場合は標準出力に書く。
+/// 入力ファイルが複数ある場合は、拡張子を ".s" に変えたファイル名でカレントディレクトリに書く。
+fn output_path(input: &str, options: &Options) -> Option<PathBuf> {
+    if options.inputs.len() == 1 {
+        return options.output.clone();
+    }
+    let name = Path::new(input).file_name().filter(|_| input != "-")?;
+    Some(Path::new(name).with_extension("s"))
+}
+
+/// 出力ファイルが入力ファイルや他の入力ファイルの出力ファイルを上書きしないか検査する
+fn check_outputs(options: &Options) -> Result<(), String> {
+    let mut outputs: Vec<PathBuf> = Vec::new();
+    for input in &options.inputs {
+        let output = match output_path(input, options) {
+            Some(output) => output,
+            None => continue,
+        };
+        if options
+            .inputs
+            .iter()
+            .any(|input| input != "-" && is_same_file(Path::new(input), &output))
+        {
+            return Err(format!(
+                "output file \"{}\" would overwrite an input file",
+                output.display()
+            ));
+        }
+        if outputs.iter().any(|prev| is_same_file(prev, &output)) {
+            return Err(format!(
+                "multiple input files would be written to \"{}\"",
+                output.display()
+            ));
+        }
+        outputs.push(output);
+    }
+    Ok(())
+}
+
+/// 2つのパスが同じファイルを指すかどうか。
+/// "./" を除いて同じパスであるか、両方が存在して同じファイルに解決される場合に同じとみなす。
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    let normalize = |path: &Path| -> PathBuf {
+        path.components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect()
+    };
+    if normalize(a) == normalize(b) {
+        return true;
+    }
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// コマンドライン引数を解釈する。
+/// "-o" と "-I" の引数は、続けて書くことも次の引数にすることもできる。
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options {
+        max_errors: DEFAULT_MAX_ERRORS,
+        ..Options::default()
+    };
+    let mut inputs = Vec::new();
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut option_value = |name: &str| match arg.strip_prefix(name) {
+            Some("") => args
+                .next()
+                .ok_or_else(|| format!("argument to \"{}\" is missing", name)),
+            Some(value) => Ok(value.to_string()),
+            None => unreachable!(),
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            _ if arg.starts_with("-o") => options.output = Some(option_value("-o")?.into()),
+            _ if arg.starts_with("-I") => options.include_dirs.push(option_value("-I")?.into()),
+            // 0は上限なしを表す
+            _ if arg.starts_with("-ferror-limit=") => {
+                let value = &arg["-ferror-limit=".len()..];
+                options.max_errors = match value.parse::<usize>() {
+                    Ok(0) => usize::MAX,
+                    Ok(max_errors) => max_errors,
+                    Err(_) => return Err(format!("invalid error limit \"{}\"", value)),
+                };
+            }
+            "-" => inputs.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option \"{}\"", arg)),
+            _ => inputs.push(arg),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("no input file".to_string());
+    }
+    if inputs.len() > 1 && options.output.is_some() {
+        return Err("cannot specify \"-o\" with multiple input files".to_string());
+    }
+    options.inputs = inputs;
+    check_outputs(&options)?;
+    Ok(Command::Compile(options))
+}
+
+/// 入力ファイルを読み、診断メッセージに表示するファイル名とその内容を返す
+fn read_input(input: &str) -> std::io::Result<(String, String)> {
+    if input == "-" {
+        let mut src = String::new();
+        std::io::stdin().read_to_string(&mut src)?;
+        Ok(("<stdin>".to_string(), src))
+    } else {
+        Ok((input.to_string(), std::fs::read_to_string(input)?))
+    }
+}
+
+fn compile(
+    source_map: &mut SourceMap,
+    file: FileId,
+    options: &Options,
+) -> Result<AsmBuf, CompileError> {
+    let mut preprocessor = options
+        .include_dirs
+        .iter()
+        .fold(Preprocessor::new(), |preprocessor, dir| {
+            preprocessor.include_dir(dir)
+        });
+    let file = preprocessor.preprocess(source_map, file);
+    for warning in preprocessor.warnings() {
+        eprintln!("{}", warning.display(source_map));
+    }
+    let file = file?;
+
+    let tokens = tokenize_with_max_errors(source_map.file(file), options.max_errors)?;
 
-    let mut token_iter = tokenize(arg.as_str());
+    let program = parser::Parser::new()
+        .max_errors(options.max_errors)
+        .parse(&mut TokenStream::new(&tokens))?;
 
-    let nodes = parser::Parser::new().parse(&mut token_iter);
+    let mut converter = parser::Converter::new();
+    let program = converter.convert(program);
+    for warning in converter.warnings() {
+        eprintln!("{}", warning.display(source_map));
+    }
+    let program = program?;
 
     let mut asm = AsmBuf::new();
     let mut generator = generator::Generator::new();
-    generator.gen(&nodes, &mut asm);
+    generator.gen(&program, &mut asm)?;
 
-    asm.output_stdout().unwrap();
+    Ok(asm)
 }
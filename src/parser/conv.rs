@@ -0,0 +1,1055 @@
+use super::{
+    eval::{eval_const, eval_initializer},
+    node::*,
+    op::BinOp,
+};
+use crate::{
+    diagnostic::{CompileError, Diagnostic},
+    token::Span,
+    ty::{IntType, Type},
+};
+
+/// 暗黙の型変換を解決するフェーズ。
+///
+/// パース済みのASTを受け取り、整数拡張と通常の算術変換に従って
+/// `Expr::ImplicitCast` を挿入したASTを返す。
+/// 符号の異なる整数の比較や、値が変わりうる縮小変換は警告として記録する。
+/// 変換できない型の組み合わせはエラーになる。
+pub struct Converter {
+    warnings: Vec<Diagnostic>,
+    errors: Vec<Diagnostic>,
+    /// 変換中の関数の名前
+    func_name: String,
+    /// 変換中の関数の返り値の型
+    ret_ty: Type,
+}
+
+impl Converter {
+    pub fn new() -> Self {
+        Converter {
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            func_name: String::new(),
+            ret_ty: Type::Int(IntType::Int),
+        }
+    }
+
+    /// 変換中に見つかった警告
+    pub fn warnings(&self) -> &[Diagnostic] {
+        self.warnings.as_slice()
+    }
+
+    pub fn convert<'src>(&mut self, program: Program<'src>) -> Result<Program<'src>, CompileError> {
+        let globals = program
+            .globals
+            .into_iter()
+            .map(|global| self.convert_global_var(global))
+            .collect();
+        let func_defs = program
+            .func_defs
+            .into_iter()
+            .map(|func_def| self.convert_func_def(func_def))
+            .collect();
+
+        // トップレベルのstmtはmain関数の本体になる。
+        // main関数は終わりに達すると0を返すので警告しない
+        self.func_name = "main".to_string();
+        self.ret_ty = Type::Int(IntType::Int);
+        let stmts = program
+            .stmts
+            .into_iter()
+            .map(|stmt| self.convert_stmt(stmt))
+            .collect();
+
+        if !self.errors.is_empty() {
+            return Err(CompileError {
+                diagnostics: std::mem::take(&mut self.errors),
+            });
+        }
+
+        Ok(Program {
+            globals,
+            funcs: program.funcs,
+            func_defs,
+            stmts,
+            locals_len: program.locals_len,
+        })
+    }
+
+    fn convert_global_var<'src>(&mut self, global: GlobalVar<'src>) -> GlobalVar<'src> {
+        let decl = self.convert_init_declarator(global.decl);
+
+        // 初期値を変数の型に変換した値で置き換える
+        let init_values = match &decl.init {
+            Some((_, init)) => eval_initializer(init, decl.var.ty).unwrap_or(global.init_values),
+            None => global.init_values,
+        };
+
+        GlobalVar {
+            decl,
+            init_values,
+            ..global
+        }
+    }
+
+    fn convert_func_def<'src>(&mut self, func_def: FuncDef<'src>) -> FuncDef<'src> {
+        self.func_name = func_def.ident.name.to_string();
+        self.ret_ty = func_def.func.ty.ret_ty;
+
+        let body = func_def.body;
+        let stmts: Vec<_> = body
+            .stmts
+            .into_iter()
+            .map(|stmt| self.convert_stmt(stmt))
+            .collect();
+
+        // 返り値のある関数は、終わりに達する前にreturnしなければならない
+        if self.ret_ty != Type::Void && stmts.iter().all(can_fall_through) {
+            let msg = format!(
+                "non-void function \"{}\" does not return a value in all control paths",
+                self.func_name
+            );
+            self.warnings
+                .push(Diagnostic::warning(body.brace_right_token.span(), msg));
+        }
+
+        FuncDef {
+            body: StmtBlock { stmts, ..body },
+            ..func_def
+        }
+    }
+
+    fn convert_decl<'src>(&mut self, decl: StmtDecl<'src>) -> StmtDecl<'src> {
+        let declarators = decl
+            .declarators
+            .into_iter()
+            .map(|declarator| self.convert_init_declarator(declarator))
+            .collect();
+        StmtDecl {
+            declarators,
+            ..decl
+        }
+    }
+
+    fn convert_init_declarator<'src>(
+        &mut self,
+        decl: InitDeclarator<'src>,
+    ) -> InitDeclarator<'src> {
+        let ty = decl.var.ty;
+        let init = decl.init.map(|(assign_token, init)| {
+            let init = match init {
+                Initializer::Expr(expr) => Initializer::Expr(self.convert_to(expr, ty)),
+                // 要素ごとに、その要素の型に変換する
+                Initializer::List(list) => {
+                    let elems = list
+                        .elems
+                        .into_iter()
+                        .map(|elem| InitElem {
+                            expr: self.convert_to(elem.expr, elem.ty),
+                            ..elem
+                        })
+                        .collect();
+                    Initializer::List(InitList { elems, ..list })
+                }
+            };
+            (assign_token, init)
+        });
+
+        InitDeclarator { init, ..decl }
+    }
+
+    fn convert_stmt<'src>(&mut self, stmt: Stmt<'src>) -> Stmt<'src> {
+        match stmt {
+            Stmt::Decl(decl) => Stmt::Decl(self.convert_decl(decl)),
+            Stmt::TypeDecl(_) => stmt,
+            Stmt::Block(stmt) => {
+                let stmts = stmt
+                    .stmts
+                    .into_iter()
+                    .map(|stmt| self.convert_stmt(stmt))
+                    .collect();
+                Stmt::Block(StmtBlock { stmts, ..stmt })
+            }
+            Stmt::Expr(stmt) => {
+                let expr = self.convert_expr(stmt.expr);
+                Stmt::Expr(StmtExpr { expr, ..stmt })
+            }
+            Stmt::Return(stmt) => {
+                let expr = match (stmt.expr, self.ret_ty) {
+                    (None, Type::Void) => None,
+                    (None, _) => {
+                        let msg = format!(
+                            "non-void function \"{}\" should return a value",
+                            self.func_name
+                        );
+                        self.errors
+                            .push(Diagnostic::error(stmt.return_token.span(), msg));
+                        None
+                    }
+                    // void型の式はそのまま返せる
+                    (Some(expr), Type::Void) => {
+                        let expr = self.convert_expr(expr);
+                        if expr.ty() != Type::Void {
+                            let msg = format!(
+                                "void function \"{}\" should not return a value",
+                                self.func_name
+                            );
+                            self.errors.push(Diagnostic::error(expr.span(), msg));
+                        }
+                        Some(expr)
+                    }
+                    (Some(expr), ty) => Some(self.convert_to(expr, ty)),
+                };
+                Stmt::Return(StmtReturn { expr, ..stmt })
+            }
+            Stmt::Null(_) => stmt,
+            Stmt::If(stmt) => {
+                let cond = self.convert_cond(stmt.cond);
+                let then_branch = Box::new(self.convert_stmt(*stmt.then_branch));
+                let else_branch = stmt
+                    .else_branch
+                    .map(|(else_token, stmt)| (else_token, Box::new(self.convert_stmt(*stmt))));
+                Stmt::If(StmtIf {
+                    cond,
+                    then_branch,
+                    else_branch,
+                    ..stmt
+                })
+            }
+            Stmt::While(stmt) => {
+                let cond = self.convert_cond(stmt.cond);
+                let block = Box::new(self.convert_stmt(*stmt.block));
+                Stmt::While(StmtWhile {
+                    cond,
+                    block,
+                    ..stmt
+                })
+            }
+        }
+    }
+
+    /// if や while の条件式を変換する
+    fn convert_cond<'src>(&mut self, cond: Expr<'src>) -> Expr<'src> {
+        let cond = self.convert_expr(cond);
+        if !cond.ty().is_scalar() {
+            let msg = format!("expected scalar type but found \"{}\"", cond.ty());
+            self.errors.push(Diagnostic::error(cond.span(), msg));
+        }
+        cond
+    }
+
+    /// 代入やreturnなど、`ty` 型への変換が起こる箇所のexprを変換する
+    fn convert_to<'src>(&mut self, expr: Expr<'src>, ty: Type) -> Expr<'src> {
+        let expr = self.convert_expr(expr);
+
+        match (expr.ty(), ty) {
+            (Type::Int(from), Type::Int(to)) => {
+                self.check_narrowing(&expr, from, to);
+                implicit_cast(expr, ty)
+            }
+            (from, to) if from.is_arith() && to.is_arith() => implicit_cast(expr, ty),
+            (Type::FuncPtr(from), Type::FuncPtr(to)) if from.is_compatible(to) => expr,
+            (Type::Ptr(from), Type::Ptr(to)) if to.accepts(from) => implicit_cast(expr, ty),
+            (Type::Int(_), Type::FuncPtr(_) | Type::Ptr(_)) if is_null_pointer_constant(&expr) => {
+                implicit_cast(expr, ty)
+            }
+            (_, to) if to.is_aggregate() => {
+                let msg = format!("assignment to \"{}\" is not supported", to);
+                self.errors.push(Diagnostic::error(expr.span(), msg));
+                expr
+            }
+            (from, to) => {
+                let msg = format!("cannot convert \"{}\" to \"{}\"", from, to);
+                self.errors.push(Diagnostic::error(expr.span(), msg));
+                expr
+            }
+        }
+    }
+
+    /// 関数の引数を仮引数の型に変換する。
+    /// `va_list` は配列なので、先頭のアドレスがそのまま渡される。
+    fn convert_arg<'src>(&mut self, expr: Expr<'src>, ty: Type) -> Expr<'src> {
+        if ty == Type::VaList && expr.ty() == Type::VaList {
+            return self.convert_expr(expr);
+        }
+        self.convert_to(expr, ty)
+    }
+
+    /// 仮引数の型が分からない引数を、既定の実引数拡張に従って変換する
+    fn convert_promoted<'src>(&mut self, expr: Expr<'src>) -> Expr<'src> {
+        let expr = self.convert_expr(expr);
+        if expr.ty().is_aggregate() {
+            let msg = format!(
+                "passing \"{}\" as a variadic argument is not supported",
+                expr.ty()
+            );
+            self.errors.push(Diagnostic::error(expr.span(), msg));
+        }
+        if expr.ty() == Type::Void {
+            self.errors.push(Diagnostic::error(
+                expr.span(),
+                "argument type \"void\" is incomplete",
+            ));
+        }
+        let ty = expr.ty().promoted();
+        implicit_cast(expr, ty)
+    }
+
+    fn convert_expr<'src>(&mut self, expr: Expr<'src>) -> Expr<'src> {
+        match expr {
+            Expr::Num(_) | Expr::Ident(_) | Expr::Func(_) | Expr::ImplicitCast(_) => expr,
+            Expr::Call(call) => {
+                // 宣言されていない関数は、任意の引数を取るものとして呼び出す
+                if let Some(ExprFunc { func: None, ident }) = call.direct_callee() {
+                    let msg = format!("implicit declaration of function \"{}\"", ident.name);
+                    self.warnings.push(Diagnostic::warning(ident.span(), msg));
+                }
+
+                // 引数を仮引数の型に変換して渡す。
+                // 可変長引数の部分は既定の実引数拡張を行って渡す
+                let func_ty = call.func_ty();
+                let callee = Box::new(self.convert_expr(*call.callee));
+                let params = call
+                    .params
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, param)| match func_ty.param_tys.get(i) {
+                        Some(ty) => self.convert_arg(param, *ty),
+                        None => self.convert_promoted(param),
+                    })
+                    .collect();
+                Expr::Call(ExprCall {
+                    callee,
+                    params,
+                    ..call
+                })
+            }
+            Expr::Addr(addr) => {
+                let expr = Box::new(self.convert_expr(*addr.expr));
+                Expr::Addr(ExprAddr { expr, ..addr })
+            }
+            Expr::Deref(deref) => {
+                let expr = Box::new(self.convert_expr(*deref.expr));
+                Expr::Deref(ExprDeref { expr, ..deref })
+            }
+            Expr::Paren(paren) => {
+                let expr = Box::new(self.convert_expr(*paren.expr));
+                Expr::Paren(ExprParen { expr, ..paren })
+            }
+            // 右辺を左辺の型に変換して代入する
+            Expr::Assign(assign) => {
+                let lhs = self.convert_expr(*assign.lhs);
+                let rhs = Box::new(self.convert_to(*assign.rhs, lhs.ty()));
+                Expr::Assign(ExprAssign {
+                    lhs: Box::new(lhs),
+                    rhs,
+                    ..assign
+                })
+            }
+            Expr::Comma(comma) => {
+                let lhs = Box::new(self.convert_expr(*comma.lhs));
+                let rhs = Box::new(self.convert_expr(*comma.rhs));
+                Expr::Comma(ExprComma { lhs, rhs, ..comma })
+            }
+            // 添字は64bitに拡張された値のまま使う
+            Expr::Index(index) => {
+                let base = Box::new(self.convert_expr(*index.base));
+                let expr = Box::new(self.convert_expr(*index.index));
+                Expr::Index(ExprIndex {
+                    base,
+                    index: expr,
+                    ..index
+                })
+            }
+            Expr::Member(member) => {
+                let base = Box::new(self.convert_expr(*member.base));
+                Expr::Member(ExprMember { base, ..member })
+            }
+            // 明示的な型変換では警告しない
+            Expr::Cast(cast) => {
+                let expr = self.convert_expr(*cast.expr);
+                if !can_cast(expr.ty(), cast.type_name.ty) {
+                    let msg = format!("cannot cast \"{}\" to \"{}\"", expr.ty(), cast.type_name.ty);
+                    self.errors.push(Diagnostic::error(expr.span(), msg));
+                }
+                Expr::Cast(ExprCast {
+                    expr: Box::new(expr),
+                    ..cast
+                })
+            }
+            Expr::Va(va) => {
+                let ap = Box::new(self.convert_expr(*va.ap));
+                Expr::Va(ExprVa { ap, ..va })
+            }
+            // オペランドを演算結果の型に揃える
+            Expr::Unary(unary) => {
+                let ty = unary.ty();
+                let expr = self.convert_expr(*unary.expr);
+                let expr = match self.expect_arith(&expr) {
+                    Some(_) => implicit_cast(expr, ty),
+                    None => expr,
+                };
+                Expr::Unary(ExprUnary {
+                    expr: Box::new(expr),
+                    ..unary
+                })
+            }
+            Expr::BinOp(binop) => {
+                let span = binop.lhs.span().join(binop.rhs.span());
+                let lhs = self.convert_expr(*binop.lhs);
+                let rhs = self.convert_expr(*binop.rhs);
+
+                if matches!(binop.op, BinOp::Eq(_) | BinOp::Neq(_))
+                    && (lhs.ty().is_ptr() || rhs.ty().is_ptr())
+                {
+                    return self.convert_ptr_compare(span, binop.op, lhs, rhs);
+                }
+
+                let common = match (self.expect_arith(&lhs), self.expect_arith(&rhs)) {
+                    (Some(lhs_ty), Some(rhs_ty)) => lhs_ty.common(rhs_ty).unwrap(),
+                    _ => {
+                        return Expr::BinOp(ExprBinOp {
+                            lhs: Box::new(lhs),
+                            op: binop.op,
+                            rhs: Box::new(rhs),
+                        })
+                    }
+                };
+
+                let is_compare = matches!(
+                    binop.op,
+                    BinOp::Lt(_) | BinOp::Lte(_) | BinOp::Eq(_) | BinOp::Neq(_)
+                );
+                if let (true, Type::Int(lhs_ty), Type::Int(rhs_ty), Type::Int(common)) =
+                    (is_compare, lhs.ty(), rhs.ty(), common)
+                {
+                    self.check_sign_compare(span, (&lhs, lhs_ty), (&rhs, rhs_ty), common);
+                }
+
+                // 両辺を共通の型に揃える
+                Expr::BinOp(ExprBinOp {
+                    lhs: Box::new(implicit_cast(lhs, common)),
+                    op: binop.op,
+                    rhs: Box::new(implicit_cast(rhs, common)),
+                })
+            }
+        }
+    }
+
+    /// ポインタ同士か、ポインタとヌルポインタ定数の等値比較を変換する。
+    /// 関数以外を指すポインタ同士は、修飾子を除いて同じ型か `void` を指す場合に比較できる。
+    /// ポインタの比較は64bitの整数の比較として行われる。
+    fn convert_ptr_compare<'src>(
+        &mut self,
+        span: Span,
+        op: BinOp,
+        lhs: Expr<'src>,
+        rhs: Expr<'src>,
+    ) -> Expr<'src> {
+        let (lhs, rhs) = match (lhs.ty(), rhs.ty()) {
+            (Type::FuncPtr(lhs_ty), Type::FuncPtr(rhs_ty)) if lhs_ty.is_compatible(rhs_ty) => {
+                (lhs, rhs)
+            }
+            (Type::Ptr(lhs_ty), Type::Ptr(rhs_ty))
+                if lhs_ty.accepts(rhs_ty) || rhs_ty.accepts(lhs_ty) =>
+            {
+                (lhs, rhs)
+            }
+            (ty @ (Type::FuncPtr(_) | Type::Ptr(_)), Type::Int(_))
+                if is_null_pointer_constant(&rhs) =>
+            {
+                (lhs, implicit_cast(rhs, ty))
+            }
+            (Type::Int(_), ty @ (Type::FuncPtr(_) | Type::Ptr(_)))
+                if is_null_pointer_constant(&lhs) =>
+            {
+                (implicit_cast(lhs, ty), rhs)
+            }
+            (lhs_ty, rhs_ty) => {
+                let msg = format!(
+                    "comparison of incompatible types \"{}\" and \"{}\"",
+                    lhs_ty, rhs_ty
+                );
+                self.errors.push(Diagnostic::error(span, msg));
+                (lhs, rhs)
+            }
+        };
+
+        Expr::BinOp(ExprBinOp {
+            lhs: Box::new(lhs),
+            op,
+            rhs: Box::new(rhs),
+        })
+    }
+
+    /// 算術型のexprであればその型を返す。
+    /// そうでなければエラーを記録する。
+    fn expect_arith(&mut self, expr: &Expr) -> Option<Type> {
+        let ty = expr.ty();
+        if !ty.is_arith() {
+            let msg = format!("expected arithmetic type but found \"{}\"", ty);
+            self.errors.push(Diagnostic::error(expr.span(), msg));
+            return None;
+        }
+        Some(ty)
+    }
+
+    /// `expr` を `from` 型から `to` 型に変換すると値が変わりうる場合に警告する。
+    /// 定数の場合は、実際に値が変わるときだけ警告する。
+    fn check_narrowing(&mut self, expr: &Expr, from: IntType, to: IntType) {
+        if from.size() <= to.size() {
+            return;
+        }
+
+        let msg = match eval_const(expr) {
+            Some(value) => {
+                let before = from.to_i128(value);
+                let after = to.to_i128(to.wrap(value));
+                if before == after {
+                    return;
+                }
+                format!(
+                    "implicit conversion from \"{}\" to \"{}\" changes value from {} to {}",
+                    from, to, before, after
+                )
+            }
+            None => format!(
+                "implicit conversion loses integer precision: \"{}\" to \"{}\"",
+                from, to
+            ),
+        };
+        self.warnings.push(Diagnostic::warning(expr.span(), msg));
+    }
+
+    /// 符号付き整数が符号なし整数に変換されて比較される場合に警告する。
+    /// 符号付きの側が非負の定数であれば値は変わらないので警告しない。
+    fn check_sign_compare(
+        &mut self,
+        span: Span,
+        lhs: (&Expr, IntType),
+        rhs: (&Expr, IntType),
+        common: IntType,
+    ) {
+        if common.is_signed() {
+            return;
+        }
+
+        for ((signed, signed_ty), (_, unsigned_ty)) in [(lhs, rhs), (rhs, lhs)] {
+            if !signed_ty.is_signed() || unsigned_ty.promoted().is_signed() {
+                continue;
+            }
+
+            if matches!(eval_const(signed), Some(value) if signed_ty.to_i128(value) >= 0) {
+                return;
+            }
+
+            let msg = format!(
+                "comparison of integers of different signs: \"{}\" and \"{}\"",
+                lhs.1, rhs.1
+            );
+            self.warnings.push(Diagnostic::warning(span, msg));
+            return;
+        }
+    }
+}
+
+impl Default for Converter {
+    fn default() -> Self {
+        Converter::new()
+    }
+}
+
+/// 値が0の整数定数式かどうか。
+/// ヌルポインタ定数としてポインタに変換できる。
+fn is_null_pointer_constant(expr: &Expr) -> bool {
+    expr.ty().as_int().is_some() && eval_const(expr) == Some(0)
+}
+
+/// 明示的な型変換ができるかどうか。
+/// スカラ型同士であれば変換できるが、浮動小数点数とポインタは相互に変換できない。
+fn can_cast(from: Type, to: Type) -> bool {
+    match (from, to) {
+        // どの型の値もvoidにキャストして捨てられる
+        (_, Type::Void) => true,
+        (Type::Float(_), to) if to.is_ptr() => false,
+        (from, Type::Float(_)) if from.is_ptr() => false,
+        _ => from.is_scalar() && to.is_scalar(),
+    }
+}
+
+/// stmtの実行が終わった後に、次のstmtへ進む可能性があるかどうか。
+/// 条件が0でない定数のwhileは、returnしない限り終わらない
+fn can_fall_through(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) => false,
+        Stmt::Block(block) => block.stmts.iter().all(can_fall_through),
+        Stmt::If(StmtIf {
+            then_branch,
+            else_branch: Some((_, else_branch)),
+            ..
+        }) => can_fall_through(then_branch) || can_fall_through(else_branch),
+        Stmt::While(stmt) => !matches!(eval_const(&stmt.cond), Some(cond) if cond != 0),
+        Stmt::Decl(_) | Stmt::TypeDecl(_) | Stmt::Expr(_) | Stmt::Null(_) | Stmt::If(_) => true,
+    }
+}
+
+/// `expr` の型が `ty` と異なる場合は暗黙の型変換を挿入する
+fn implicit_cast(expr: Expr, ty: Type) -> Expr {
+    if expr.ty() == ty {
+        return expr;
+    }
+
+    Expr::ImplicitCast(ExprImplicitCast {
+        ty,
+        expr: Box::new(expr),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::Parser,
+        token::{tokenize, SourceMap, TokenStream},
+        ty::FloatType,
+    };
+
+    /// `input` をパースして変換し、その結果を `f` に渡す
+    fn convert<T>(input: &str, f: impl FnOnce(Program, Vec<String>) -> T) -> T {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        let program = Parser::new().parse(&mut TokenStream::new(&tokens)).unwrap();
+
+        let mut converter = Converter::new();
+        let program = converter.convert(program).unwrap();
+        let warnings = converter
+            .warnings()
+            .iter()
+            .map(|warning| warning.msg.clone())
+            .collect();
+        f(program, warnings)
+    }
+
+    /// 最後のstmtの式の型と、そのオペランドの型を返す
+    fn binop_types(input: &str) -> (Type, Type, Type) {
+        convert(input, |program, _| match program.stmts.last() {
+            Some(Stmt::Expr(StmtExpr {
+                expr: Expr::BinOp(binop),
+                ..
+            })) => (binop.ty(), binop.lhs.ty(), binop.rhs.ty()),
+            stmt => panic!("unexpected stmt: {:?}", stmt),
+        })
+    }
+
+    fn warnings(input: &str) -> Vec<String> {
+        convert(input, |_, warnings| warnings)
+    }
+
+    /// 変換時のエラーのメッセージを返す
+    fn errors(input: &str) -> Vec<String> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        let program = Parser::new().parse(&mut TokenStream::new(&tokens)).unwrap();
+
+        match Converter::new().convert(program) {
+            Ok(_) => Vec::new(),
+            Err(e) => e.diagnostics.into_iter().map(|e| e.msg).collect(),
+        }
+    }
+
+    #[test]
+    fn arithmetic_conversion_test() {
+        use IntType::*;
+
+        assert_eq!(
+            binop_types("char a; short b; a + b;"),
+            (Type::Int(Int), Type::Int(Int), Type::Int(Int))
+        );
+        assert_eq!(
+            binop_types("int a; unsigned b; a * b;"),
+            (Type::Int(UInt), Type::Int(UInt), Type::Int(UInt))
+        );
+        assert_eq!(
+            binop_types("long a; unsigned b; a - b;"),
+            (Type::Int(Long), Type::Int(Long), Type::Int(Long))
+        );
+        assert_eq!(
+            binop_types("unsigned char a; unsigned char b; a < b;"),
+            (Type::Int(Int), Type::Int(Int), Type::Int(Int))
+        );
+        assert_eq!(
+            binop_types("long a; unsigned long b; a == b;"),
+            (Type::Int(Int), Type::Int(ULong), Type::Int(ULong))
+        );
+    }
+
+    #[test]
+    fn float_conversion_test() {
+        use crate::ty::FloatType::*;
+
+        assert_eq!(
+            binop_types("float a; long b; a + b;"),
+            (Type::Float(Float), Type::Float(Float), Type::Float(Float))
+        );
+        assert_eq!(
+            binop_types("float a; double b; a * b;"),
+            (
+                Type::Float(Double),
+                Type::Float(Double),
+                Type::Float(Double)
+            )
+        );
+        assert_eq!(
+            binop_types("double a; a < 1;"),
+            (
+                Type::Int(IntType::Int),
+                Type::Float(Double),
+                Type::Float(Double)
+            )
+        );
+        assert!(errors("double d; int i; i = (int)d + (long)1.5f;").is_empty());
+    }
+
+    /// アドレスでない静的な初期値
+    fn num(value: StaticValue) -> u64 {
+        match value {
+            StaticValue::Num(num) => num,
+            value => panic!("unexpected address: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn global_init_test() {
+        let values = convert("char c = 300; unsigned u = -1;", |program, _| {
+            program
+                .globals
+                .iter()
+                .map(|global| num(global.init_values[0].value))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(values, vec![44, 0xffffffff]);
+
+        let values = convert(
+            "double d = 1 + 0.5; float f = 3; int i = 2.9;",
+            |program, _| {
+                program
+                    .globals
+                    .iter()
+                    .map(|global| num(global.init_values[0].value))
+                    .collect::<Vec<_>>()
+            },
+        );
+        assert_eq!(values, vec![1.5f64.to_bits(), 3.0f32.to_bits() as u64, 2]);
+
+        // 集成体の要素はそれぞれの型に変換され、オフセットの順に並ぶ
+        let values = convert(
+            "struct P { char c; long l; }; struct P p[2] = {[1] = {.l = 2.5, .c = 300}};",
+            |program, _| {
+                program.globals[0]
+                    .init_values
+                    .iter()
+                    .map(|init| (init.offset, num(init.value)))
+                    .collect::<Vec<_>>()
+            },
+        );
+        assert_eq!(values, vec![(16, 44), (24, 2)]);
+
+        // 関数のアドレスで初期化できる
+        let values = convert(
+            "int f(int); struct S { int (*p)(int); long n; }; struct S s = {&f, 2};",
+            |program, _| {
+                program.globals[0]
+                    .init_values
+                    .iter()
+                    .map(|init| format!("{:?}", init.value))
+                    .collect::<Vec<_>>()
+            },
+        );
+        assert_eq!(values, vec!["Func(\"f\")", "Num(2)"]);
+
+        // 静的な領域に配置された変数や、その要素・メンバのアドレスで初期化できる
+        let values = convert(
+            "struct S { int a; int b[3]; } s; int *p = &s.b[2], *q = (int *)&s, *r = 0;",
+            |program, _| {
+                program.globals[1..]
+                    .iter()
+                    .map(|global| format!("{:?}", global.init_values[0].value))
+                    .collect::<Vec<_>>()
+            },
+        );
+        assert_eq!(
+            values,
+            vec!["Var(Global(\"s\"), 12)", "Var(Global(\"s\"), 0)", "Num(0)"]
+        );
+    }
+
+    #[test]
+    fn comma_test() {
+        // コンマ演算子の値の型は右辺の型で、通常の算術変換は行われない
+        let ty = convert("char c; 1.5, c;", |program, _| match program.stmts.last() {
+            Some(Stmt::Expr(StmtExpr { expr, .. })) => expr.ty(),
+            stmt => panic!("unexpected stmt: {:?}", stmt),
+        });
+        assert_eq!(ty, Type::Int(IntType::Char));
+        assert_eq!(
+            errors("int f(int a) { return a; } f((1, 2, 3));"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn assign_test() {
+        // 代入式の値の型は左辺の型で、右辺は左辺の型に変換される
+        let types = convert("char c; c = 1.5;", |program, _| {
+            match program.stmts.last() {
+                Some(Stmt::Expr(StmtExpr {
+                    expr: Expr::Assign(assign),
+                    ..
+                })) => (assign.lhs.ty(), assign.rhs.ty()),
+                stmt => panic!("unexpected stmt: {:?}", stmt),
+            }
+        });
+        assert_eq!(types, (Type::Int(IntType::Char), Type::Int(IntType::Char)));
+        assert_eq!(
+            errors("struct P { int x; } p; int i; i = p;"),
+            vec!["cannot convert \"struct P\" to \"int\""]
+        );
+    }
+
+    #[test]
+    fn unary_test() {
+        // 整数のオペランドは整数拡張されるが、floatはdoubleに拡張されない
+        let unary_ty = |src| {
+            convert(src, |program, _| match program.stmts.last() {
+                Some(Stmt::Expr(StmtExpr {
+                    expr: Expr::Unary(unary),
+                    ..
+                })) => (unary.ty(), unary.expr.ty()),
+                stmt => panic!("unexpected stmt: {:?}", stmt),
+            })
+        };
+        assert_eq!(
+            unary_ty("char c; -c;"),
+            (Type::Int(IntType::Int), Type::Int(IntType::Int))
+        );
+        assert_eq!(
+            unary_ty("float f; -f;"),
+            (Type::Float(FloatType::Float), Type::Float(FloatType::Float))
+        );
+        assert_eq!(
+            errors("struct P { int x; } p; -p;"),
+            vec!["expected arithmetic type but found \"struct P\""]
+        );
+    }
+
+    #[test]
+    fn sign_compare_test() {
+        assert_eq!(
+            warnings("int a; unsigned b; a < b;"),
+            vec!["comparison of integers of different signs: \"int\" and \"unsigned int\""]
+        );
+        assert_eq!(warnings("int a; unsigned b; b == a;").len(), 1);
+        assert!(warnings("unsigned b; b < 10;").is_empty());
+        assert!(warnings("unsigned char a; unsigned b; a < b;").is_empty());
+        assert!(warnings("long a; unsigned b; a < b;").is_empty());
+        assert!(warnings("int a; unsigned b; a + b;").is_empty());
+        assert_eq!(warnings("unsigned b; b < -1;").len(), 1);
+    }
+
+    #[test]
+    fn narrowing_test() {
+        assert_eq!(
+            warnings("char c = 300;"),
+            vec!["implicit conversion from \"int\" to \"char\" changes value from 300 to 44"]
+        );
+        assert_eq!(
+            warnings("long l; int i; i = l;"),
+            vec!["implicit conversion loses integer precision: \"long\" to \"int\""]
+        );
+        assert!(warnings("char c = 100; short s = -1; int i = 'a';").is_empty());
+        assert!(warnings("int i; long l; l = i;").is_empty());
+        assert_eq!(warnings("long l; return l;").len(), 1);
+        assert!(warnings("char c = (char)300; long l; int i; i = (int)l;").is_empty());
+        assert_eq!(warnings("int f(char c); f(300);").len(), 1);
+        assert!(warnings("int f(char c); f(30);").is_empty());
+    }
+
+    #[test]
+    fn call_test() {
+        assert_eq!(
+            warnings("foo(1);"),
+            vec!["implicit declaration of function \"foo\""]
+        );
+        assert!(warnings("int foo(long a); foo(1);").is_empty());
+        assert!(warnings("int foo(char c, ...); long l; foo(1, l);").is_empty());
+        assert_eq!(
+            binop_types("unsigned long f(); f() + 1;"),
+            (
+                Type::Int(IntType::ULong),
+                Type::Int(IntType::ULong),
+                Type::Int(IntType::ULong)
+            )
+        );
+    }
+
+    #[test]
+    fn func_ptr_test() {
+        assert!(errors("int f(int); int (*p)(int); p = f; p = 0; if (p == f) p = 0;").is_empty());
+        assert_eq!(
+            errors("long f(int); int (*p)(int); p = f;"),
+            vec!["cannot convert \"long (*)(int)\" to \"int (*)(int)\""]
+        );
+        assert_eq!(
+            errors("int f(int); int (*p)(int); p = 1;"),
+            vec!["cannot convert \"int\" to \"int (*)(int)\""]
+        );
+        assert_eq!(
+            errors("int f(int); int g(int, ...); f == g;"),
+            vec!["comparison of incompatible types \"int (*)(int)\" and \"int (*)(int, ...)\""]
+        );
+        assert_eq!(
+            errors("int f(int); long x; x = f + 1;"),
+            vec!["expected arithmetic type but found \"int (*)(int)\""]
+        );
+        assert_eq!(
+            errors("int f(int); double d; d = (double)f;"),
+            vec!["cannot cast \"int (*)(int)\" to \"double\""]
+        );
+        assert!(errors("int f(int); long x; x = (long)f; x = (long)(int (*)(int))x;").is_empty());
+        assert!(warnings("int (*p)(int); p = 0; p(1);").is_empty());
+
+        // "()" で宣言された関数へのポインタとは、互換性のある型の間で代入や比較ができる
+        assert!(errors("int f(int); int (*p)(); p = f; if (p == f) p = 0;").is_empty());
+        assert_eq!(
+            errors("int f(char); int (*p)(); p = f;"),
+            vec!["cannot convert \"int (*)(char)\" to \"int (*)()\""]
+        );
+    }
+
+    #[test]
+    fn ptr_test() {
+        assert!(errors("int x; int *p; p = &x; p = 0; *p = 1; if (p == &x) p = 0;").is_empty());
+        // 修飾子を増やす変換と、void へのポインタとの変換は暗黙に行える
+        assert!(errors("int *p; const int *c; void *v; c = p; v = p; p = v; c == p;").is_empty());
+        assert_eq!(
+            errors("const int *c; int *p; p = c;"),
+            vec!["cannot convert \"const int *\" to \"int *\""]
+        );
+        assert_eq!(
+            errors("int *p; long *q; q = p;"),
+            vec!["cannot convert \"int *\" to \"long *\""]
+        );
+        assert_eq!(
+            errors("int *p; p = 1;"),
+            vec!["cannot convert \"int\" to \"int *\""]
+        );
+        assert_eq!(
+            errors("int *p; char *c; p == c;"),
+            vec!["comparison of incompatible types \"int *\" and \"char *\""]
+        );
+        assert_eq!(
+            errors("int *p; double d; d = (double)p;"),
+            vec!["cannot cast \"int *\" to \"double\""]
+        );
+        // 整数や他のポインタとは明示的に変換できる
+        assert!(
+            errors("int *p; long x; x = (long)p; p = (int *)x; p = (int *)(char *)p;").is_empty()
+        );
+        assert!(errors("int f(int); int *p; p = (int *)f;").is_empty());
+
+        // "&" はオブジェクトの修飾子を指す先の型に引き継ぐ
+        let ty = convert("const int x = 1; &x;", |program, _| {
+            match program.stmts.last() {
+                Some(Stmt::Expr(StmtExpr { expr, .. })) => expr.ty(),
+                stmt => panic!("unexpected stmt: {:?}", stmt),
+            }
+        });
+        assert_eq!(ty.to_string(), "const int *");
+    }
+
+    #[test]
+    fn va_list_test() {
+        let func = |body: &str| format!("int f(int n, ...) {{ va_list ap; {} return 0; }}", body);
+
+        assert!(errors(&func("va_start(ap, n); n = va_arg(ap, int);")).is_empty());
+        assert_eq!(
+            errors(&func("n = ap;")),
+            vec!["cannot convert \"va_list\" to \"int\""]
+        );
+        assert_eq!(
+            errors(&func("n = ap + 1;")),
+            vec!["expected arithmetic type but found \"va_list\""]
+        );
+        assert_eq!(
+            errors(&func("if (ap) n = 1;")),
+            vec!["expected scalar type but found \"va_list\""]
+        );
+        assert_eq!(
+            errors(&func("n = (int)ap;")),
+            vec!["cannot cast \"va_list\" to \"int\""]
+        );
+        assert!(
+            errors("int g(va_list); int f(int n, ...) { va_list ap; g(ap); return 0; }").is_empty()
+        );
+    }
+
+    #[test]
+    fn void_test() {
+        assert!(
+            errors("void f(void) { return; } void g() { return f(); } f(); (void)1;").is_empty()
+        );
+        assert_eq!(
+            errors("void f(void) { return 1; }"),
+            vec!["void function \"f\" should not return a value"]
+        );
+        assert_eq!(
+            errors("int f(void) { return; }"),
+            vec!["non-void function \"f\" should return a value"]
+        );
+        assert_eq!(
+            errors("return;"),
+            vec!["non-void function \"main\" should return a value"]
+        );
+        assert_eq!(
+            errors("void f(void) {} int x; x = f();"),
+            vec!["cannot convert \"void\" to \"int\""]
+        );
+        assert_eq!(
+            errors("void f(void) {} if (f()) 1;"),
+            vec!["expected scalar type but found \"void\""]
+        );
+        assert_eq!(
+            errors("void f(void) {} int x; x = (int)f();"),
+            vec!["cannot cast \"void\" to \"int\""]
+        );
+        assert_eq!(
+            errors("int sum(int n, ...); void f(void) {} sum(1, f());"),
+            vec!["argument type \"void\" is incomplete"]
+        );
+    }
+
+    #[test]
+    fn fall_off_test() {
+        let msg = |name: &str| {
+            vec![format!(
+                "non-void function \"{}\" does not return a value in all control paths",
+                name
+            )]
+        };
+
+        assert_eq!(warnings("int f(int x) { x = 1; }"), msg("f"));
+        assert_eq!(warnings("int f(int x) { if (x) return 1; }"), msg("f"));
+        assert_eq!(
+            warnings("int f(int x) { while (x) { return 1; } }"),
+            msg("f")
+        );
+        assert!(warnings("int f(int x) { if (x) return 1; else { return 2; } }").is_empty());
+        assert!(warnings("int f(int x) { while (1) { x = x + 1; } }").is_empty());
+        assert!(warnings("int f(int x) { { return x; } ; }").is_empty());
+        assert!(warnings("void f(void) { }").is_empty());
+        // main関数は終わりに達すると0を返す
+        assert!(warnings("1;").is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+use super::{
+    node::*,
+    op::{BinOp, UnaryOp},
+};
+use crate::ty::{FloatType, IntType, Type};
+
+/// 定数式を評価する。
+/// 評価結果はexprの型で表現できるよう切り詰められる。
+/// 浮動小数点数型の場合は値のビット列を返す。
+/// 変数や関数呼び出しを含むなど、コンパイル時に評価できない場合は `None` を返す。
+pub fn eval_const(expr: &Expr) -> Option<u64> {
+    match expr {
+        Expr::Num(num) => Some(num.num),
+        Expr::Paren(ExprParen { expr, .. }) => eval_const(expr),
+        Expr::Unary(unary) => {
+            let ty = unary.ty();
+            let value = convert_const(eval_const(&unary.expr)?, unary.expr.ty(), ty)?;
+            match (unary.op, ty) {
+                (UnaryOp::Plus(_), _) => Some(value),
+                (UnaryOp::Neg(_), Type::Int(ty)) => Some(ty.wrap(value.wrapping_neg())),
+                (UnaryOp::Neg(_), Type::Float(ty)) => Some(ty.from_f64(-ty.to_f64(value))),
+                _ => None,
+            }
+        }
+        Expr::BinOp(binop) => {
+            let operand_ty = binop.operand_ty();
+            let lhs = convert_const(eval_const(&binop.lhs)?, binop.lhs.ty(), operand_ty)?;
+            let rhs = convert_const(eval_const(&binop.rhs)?, binop.rhs.ty(), operand_ty)?;
+
+            let num = match operand_ty {
+                Type::Int(ty) => eval_int_binop(binop.op, lhs, rhs, ty)?,
+                Type::Float(ty) => eval_float_binop(binop.op, ty.to_f64(lhs), ty.to_f64(rhs), ty),
+                Type::Void
+                | Type::FuncPtr(_)
+                | Type::Ptr(_)
+                | Type::VaList
+                | Type::Array(_)
+                | Type::Struct(_) => return None,
+            };
+            convert_const(num, result_ty(binop, operand_ty), binop.ty())
+        }
+        Expr::Cast(ExprCast {
+            type_name: TypeName { ty, .. },
+            expr,
+            ..
+        })
+        | Expr::ImplicitCast(ExprImplicitCast { ty, expr }) => {
+            convert_const(eval_const(expr)?, expr.ty(), *ty)
+        }
+        // 関数のアドレスはリンク時まで決まらない。
+        // カンマ演算子は定数式に含められない
+        Expr::Ident(_)
+        | Expr::Assign(_)
+        | Expr::Comma(_)
+        | Expr::Func(_)
+        | Expr::Call(_)
+        | Expr::Addr(_)
+        | Expr::Deref(_)
+        | Expr::Index(_)
+        | Expr::Member(_)
+        | Expr::Va(_) => None,
+    }
+}
+
+/// 静的な領域に配置される `ty` 型の変数の初期化子を評価し、スカラの要素ごとの値を返す。
+/// 定数式のほか、関数や静的な領域に配置された変数のアドレスで初期化できる。
+/// コンパイル時に評価できない要素があれば、その初期化式を `Err` で返す。
+pub fn eval_initializer<'a, 'src>(
+    init: &'a Initializer<'src>,
+    ty: Type,
+) -> Result<Vec<StaticInit<'src>>, &'a Expr<'src>> {
+    let eval = |offset, ty, expr| {
+        let value = match eval_const(expr) {
+            Some(num) => StaticValue::Num(num),
+            None => eval_addr(expr).ok_or(expr)?,
+        };
+        Ok(StaticInit { offset, ty, value })
+    };
+
+    match init {
+        Initializer::Expr(expr) => Ok(vec![eval(0, ty, expr)?]),
+        Initializer::List(list) => list
+            .elems
+            .iter()
+            .map(|elem| eval(elem.offset, elem.ty, &elem.expr))
+            .collect(),
+    }
+}
+
+/// リンク時に決まるアドレスを表すexprであれば、そのアドレスを返す。
+/// "f", "&f", "*f" のような関数のアドレスと、
+/// "&g", "&a[1]", "&s.x" のような静的な領域に配置された変数のアドレスを評価できる。
+/// ポインタ同士の型変換を含められる。
+fn eval_addr<'src>(expr: &Expr<'src>) -> Option<StaticValue<'src>> {
+    match expr {
+        Expr::Func(ExprFunc { ident, .. }) => Some(StaticValue::Func(ident.name)),
+        Expr::Paren(ExprParen { expr, .. }) => eval_addr(expr),
+        Expr::Addr(ExprAddr { expr, .. }) if expr.is_func_designator() => eval_addr(expr),
+        Expr::Addr(ExprAddr { expr, .. }) => {
+            let (loc, offset) = eval_object_addr(expr)?;
+            Some(StaticValue::Var(loc, offset))
+        }
+        Expr::Deref(ExprDeref { expr, .. }) if matches!(expr.ty(), Type::FuncPtr(_)) => {
+            eval_addr(expr)
+        }
+        Expr::Cast(ExprCast {
+            type_name: TypeName { ty, .. },
+            expr,
+            ..
+        })
+        | Expr::ImplicitCast(ExprImplicitCast { ty, expr }) => {
+            if ty.is_ptr() && expr.ty().is_ptr() {
+                eval_addr(expr)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 静的な領域に配置された変数や、その要素・メンバを表すexprであれば、
+/// 変数の格納場所と、変数の先頭からのオフセットを返す。
+fn eval_object_addr<'src>(expr: &Expr<'src>) -> Option<(VarLoc<'src>, usize)> {
+    match expr {
+        Expr::Ident(ExprIdent { var, .. }) => match var.loc {
+            VarLoc::Global(_) | VarLoc::Static(..) => Some((var.loc, 0)),
+            VarLoc::Local(_) => None,
+        },
+        Expr::Paren(ExprParen { expr, .. }) => eval_object_addr(expr),
+        Expr::Member(ExprMember { base, member, .. }) => {
+            let (loc, offset) = eval_object_addr(base)?;
+            Some((loc, offset + member.offset))
+        }
+        Expr::Index(index) => {
+            let (loc, offset) = eval_object_addr(&index.base)?;
+            let elem = index
+                .index
+                .ty()
+                .as_int()?
+                .to_i128(eval_const(&index.index)?);
+            if elem < 0 {
+                return None;
+            }
+            let elem_offset = (elem as usize).checked_mul(index.elem_ty().size())?;
+            Some((loc, offset.checked_add(elem_offset)?))
+        }
+        Expr::Deref(ExprDeref { expr, .. }) => match eval_addr(expr)? {
+            StaticValue::Var(loc, offset) => Some((loc, offset)),
+            StaticValue::Num(_) | StaticValue::Func(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// 演算結果の値がどの型で表現されているか。
+/// 比較の結果は0か1の整数になる。
+fn result_ty(binop: &ExprBinOp, operand_ty: Type) -> Type {
+    match binop.op {
+        BinOp::Lt(_) | BinOp::Lte(_) | BinOp::Eq(_) | BinOp::Neq(_) => Type::Int(IntType::Int),
+        _ => operand_ty,
+    }
+}
+
+fn eval_int_binop(op: BinOp, lhs: u64, rhs: u64, ty: IntType) -> Option<u64> {
+    let signed = ty.is_signed();
+
+    let num = match op {
+        BinOp::Add(_) => lhs.wrapping_add(rhs),
+        BinOp::Sub(_) => lhs.wrapping_sub(rhs),
+        BinOp::Mul(_) => lhs.wrapping_mul(rhs),
+        BinOp::Div(_) if signed => (lhs as i64).checked_div(rhs as i64)? as u64,
+        BinOp::Div(_) => lhs.checked_div(rhs)?,
+        BinOp::Lt(_) if signed => ((lhs as i64) < (rhs as i64)) as u64,
+        BinOp::Lt(_) => (lhs < rhs) as u64,
+        BinOp::Lte(_) if signed => ((lhs as i64) <= (rhs as i64)) as u64,
+        BinOp::Lte(_) => (lhs <= rhs) as u64,
+        BinOp::Eq(_) => (lhs == rhs) as u64,
+        BinOp::Neq(_) => (lhs != rhs) as u64,
+    };
+    Some(ty.wrap(num))
+}
+
+fn eval_float_binop(op: BinOp, lhs: f64, rhs: f64, ty: FloatType) -> u64 {
+    match op {
+        BinOp::Add(_) => ty.from_f64(lhs + rhs),
+        BinOp::Sub(_) => ty.from_f64(lhs - rhs),
+        BinOp::Mul(_) => ty.from_f64(lhs * rhs),
+        BinOp::Div(_) => ty.from_f64(lhs / rhs),
+        BinOp::Lt(_) => (lhs < rhs) as u64,
+        BinOp::Lte(_) => (lhs <= rhs) as u64,
+        BinOp::Eq(_) => (lhs == rhs) as u64,
+        BinOp::Neq(_) => (lhs != rhs) as u64,
+    }
+}
+
+/// `from` 型の定数を `to` 型に変換する。
+/// 浮動小数点数から整数への変換で値が表現できない場合は `None` を返す。
+fn convert_const(value: u64, from: Type, to: Type) -> Option<u64> {
+    match (from, to) {
+        (Type::Int(_) | Type::FuncPtr(_) | Type::Ptr(_), Type::Int(to)) => Some(to.wrap(value)),
+        // ポインタは64bitの整数と同じ表現を持つ
+        (Type::Int(_) | Type::FuncPtr(_) | Type::Ptr(_), Type::FuncPtr(_) | Type::Ptr(_)) => {
+            Some(value)
+        }
+        (Type::Int(from), Type::Float(to)) => Some(to.from_f64(from.to_i128(value) as f64)),
+        (Type::Float(from), Type::Int(to)) => {
+            let value = from.to_f64(value).trunc();
+            let min = if to.is_signed() {
+                -(to.max_value() as f64) - 1.0
+            } else {
+                0.0
+            };
+            // 範囲外の値への変換は未定義動作になる
+            if !(min..to.max_value() as f64 + 1.0).contains(&value) {
+                return None;
+            }
+            Some(to.wrap(value as i128 as u64))
+        }
+        (Type::Float(from), Type::Float(to)) => Some(to.from_f64(from.to_f64(value))),
+        (Type::Float(_), Type::FuncPtr(_) | Type::Ptr(_))
+        | (Type::FuncPtr(_) | Type::Ptr(_), Type::Float(_))
+        | (Type::Void | Type::VaList | Type::Array(_) | Type::Struct(_), _)
+        | (_, Type::Void | Type::VaList | Type::Array(_) | Type::Struct(_)) => None,
+    }
+}
@@ -1,9 +1,15 @@
+mod conv;
+mod eval;
 mod node;
 mod op;
-mod parser;
+mod parse;
 
-pub use parser::Parser;
+pub use conv::Converter;
+pub use parse::Parser;
 
 pub mod ast {
-    pub use super::{node::*, op::BinOp};
+    pub use super::{
+        node::*,
+        op::{BinOp, UnaryOp},
+    };
 }
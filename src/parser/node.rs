@@ -1,100 +1,691 @@
-use super::op::BinOp;
-use crate::token::token::*;
+use super::op::{BinOp, UnaryOp};
+use crate::{
+    token::{tokens::*, Span},
+    ty::{FuncType, IntType, Member, PtrType, Qualifiers, Type},
+};
+
+/// プログラム全体
+#[derive(Debug, Clone)]
+pub struct Program<'src> {
+    pub globals: Vec<GlobalVar<'src>>,
+    pub funcs: Vec<FuncDecl<'src>>,
+    pub func_defs: Vec<FuncDef<'src>>,
+    /// main関数の本体となるstmt
+    pub stmts: Vec<Stmt<'src>>,
+    /// main関数で割り当て済みのローカル変数の領域の大きさ
+    pub locals_len: usize,
+}
+
+/// 静的な領域に配置される変数。
+/// トップレベルで宣言されたグローバル変数と、"static" の付いたローカル変数
+#[derive(Debug, Clone)]
+pub struct GlobalVar<'src> {
+    pub storage_class: Option<StorageClass>,
+    pub decl: InitDeclarator<'src>,
+    /// 初期化子を評価した値。オフセットの順に並ぶ。
+    /// 含まれない部分は0で初期化される
+    pub init_values: Vec<StaticInit<'src>>,
+    pub linkage: Linkage,
+}
+
+/// 静的な領域に配置される変数の初期値のうち、1つのスカラの値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticInit<'src> {
+    /// 変数の先頭からのオフセット
+    pub offset: usize,
+    pub ty: Type,
+    pub value: StaticValue<'src>,
+}
+
+/// 静的な領域に配置されるスカラの値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaticValue<'src> {
+    /// 整数の値か、浮動小数点数の値のビット列
+    Num(u64),
+    /// 関数のアドレス。リンク時に決まる
+    Func(&'src str),
+    /// 静的な領域に配置された変数の先頭から、オフセットだけ進んだアドレス。
+    /// 変数のアドレスもリンク時に決まる
+    Var(VarLoc<'src>, usize),
+}
+
+impl<'src> GlobalVar<'src> {
+    /// 他の翻訳単位で定義された変数を参照する宣言かどうか
+    pub fn is_extern(&self) -> bool {
+        matches!(self.storage_class, Some(StorageClass::Extern(_)))
+    }
+}
+
+/// 記憶域クラス指定子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    /// 他の翻訳単位で定義されたシンボルを参照する
+    Extern(Extern),
+    /// シンボルを内部結合にする。
+    /// ローカル変数に付けた場合は、関数の呼び出しをまたいで値を保持する
+    Static(Static),
+}
+
+impl StorageClass {
+    pub fn span(&self) -> Span {
+        match self {
+            StorageClass::Extern(token) => token.span(),
+            StorageClass::Static(token) => token.span(),
+        }
+    }
+}
+
+/// シンボルの結合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    /// 他の翻訳単位から参照できる
+    External,
+    /// 翻訳単位の中でだけ参照できる
+    Internal,
+}
+
+/// 関数の宣言
+/// "extern int foo(int a, long);"
+#[derive(Debug, Clone)]
+pub struct FuncDecl<'src> {
+    pub storage_class: Option<StorageClass>,
+    pub ret_type_name: TypeName,
+    pub ident: Ident<'src>,
+    pub paren_left_token: ParenLeft,
+    pub params: Vec<FuncParam<'src>>,
+    /// 可変長引数を取る場合の "..."
+    pub ellipsis_token: Option<Ellipsis>,
+    pub paren_right_token: ParenRight,
+    pub semi_token: Semi,
+    pub func: Func<'src>,
+}
+
+/// 関数の定義
+/// "int add(int a, int b) { return a + b; }"
+#[derive(Debug, Clone)]
+pub struct FuncDef<'src> {
+    pub storage_class: Option<StorageClass>,
+    pub ret_type_name: TypeName,
+    pub ident: Ident<'src>,
+    pub paren_left_token: ParenLeft,
+    pub params: Vec<FuncParam<'src>>,
+    pub ellipsis_token: Option<Ellipsis>,
+    pub paren_right_token: ParenRight,
+    pub body: StmtBlock<'src>,
+    pub func: Func<'src>,
+    /// 仮引数を格納するローカル変数
+    pub param_vars: Vec<Var<'src>>,
+    /// 可変長引数を取る場合、引数レジスタを退避する領域のRBPからのオフセット
+    pub reg_save_area: Option<usize>,
+    /// 割り当て済みのローカル変数の領域の大きさ
+    pub locals_len: usize,
+    pub linkage: Linkage,
+}
+
+/// 関数の宣言の仮引数。
+/// 仮引数の名前は省略できる。
+#[derive(Debug, Clone)]
+pub struct FuncParam<'src> {
+    pub type_name: TypeName,
+    pub ident: Option<Ident<'src>>,
+}
+
+/// 宣言済みの関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Func<'src> {
+    pub name: &'src str,
+    pub ty: &'static FuncType,
+}
+
+/// 変数の格納場所
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarLoc<'src> {
+    /// ベースポインタからのオフセット
+    Local(usize),
+    /// グローバル変数の名前
+    Global(&'src str),
+    /// "static" の付いたローカル変数の名前と、
+    /// 同じ名前の変数とラベルが衝突しないようにするための番号
+    Static(&'src str, usize),
+}
+
+/// 宣言済みの変数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Var<'src> {
+    pub loc: VarLoc<'src>,
+    pub ty: Type,
+    pub quals: Qualifiers,
+}
+
+/// "unsigned long"
+#[derive(Debug, Clone, Copy)]
+pub struct TypeName {
+    pub ty: Type,
+    pub quals: Qualifiers,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone)]
 pub enum Stmt<'src> {
-    Assign(StmtAssign<'src>),
+    Decl(StmtDecl<'src>),
+    TypeDecl(StmtTypeDecl),
     Block(StmtBlock<'src>),
-    Expr(Expr<'src>),
+    Expr(StmtExpr<'src>),
     Return(StmtReturn<'src>),
+    Null(StmtNull),
     If(StmtIf<'src>),
     While(StmtWhile<'src>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Expr<'src> {
-    Num(Num<'src>),
+    Num(Num),
     Ident(ExprIdent<'src>),
+    Func(ExprFunc<'src>),
     Call(ExprCall<'src>),
+    Unary(ExprUnary<'src>),
     BinOp(ExprBinOp<'src>),
     Paren(ExprParen<'src>),
+    Assign(ExprAssign<'src>),
+    Comma(ExprComma<'src>),
+    Cast(ExprCast<'src>),
+    Addr(ExprAddr<'src>),
+    Deref(ExprDeref<'src>),
+    Index(ExprIndex<'src>),
+    Member(ExprMember<'src>),
+    Va(ExprVa<'src>),
+    ImplicitCast(ExprImplicitCast<'src>),
+}
+
+impl<'src> Stmt<'src> {
+    /// ソースコード上でこのstmtが占める範囲を返す
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Decl(stmt) => stmt.type_name.span.join(stmt.semi_token.span()),
+            Stmt::TypeDecl(stmt) => stmt.type_name.span.join(stmt.semi_token.span()),
+            Stmt::Block(stmt) => stmt
+                .brace_left_token
+                .span()
+                .join(stmt.brace_right_token.span()),
+            Stmt::Expr(stmt) => stmt.expr.span().join(stmt.semi_token.span()),
+            Stmt::Return(stmt) => stmt.return_token.span().join(stmt.semi_token.span()),
+            Stmt::Null(stmt) => stmt.semi_token.span(),
+            Stmt::If(stmt) => {
+                let last = match &stmt.else_branch {
+                    Some((_, else_branch)) => else_branch,
+                    None => &stmt.then_branch,
+                };
+                stmt.if_token.span().join(last.span())
+            }
+            Stmt::While(stmt) => stmt.while_token.span().join(stmt.block.span()),
+        }
+    }
+}
+
+impl<'src> Expr<'src> {
+    /// ソースコード上でこのexprが占める範囲を返す
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Num(num) => num.span(),
+            Expr::Ident(expr) => expr.ident.span(),
+            Expr::Func(expr) => expr.ident.span(),
+            Expr::Call(expr) => expr.callee.span().join(expr.paren_right_token.span()),
+            Expr::Unary(expr) => expr.op.span().join(expr.expr.span()),
+            Expr::BinOp(expr) => expr.lhs.span().join(expr.rhs.span()),
+            Expr::Assign(expr) => expr.lhs.span().join(expr.rhs.span()),
+            Expr::Comma(expr) => expr.lhs.span().join(expr.rhs.span()),
+            Expr::Paren(expr) => expr
+                .paren_left_token
+                .span()
+                .join(expr.paren_right_token.span()),
+            Expr::Cast(expr) => expr.paren_left_token.span().join(expr.expr.span()),
+            Expr::Addr(expr) => expr.amp_token.span().join(expr.expr.span()),
+            Expr::Deref(expr) => expr.star_token.span().join(expr.expr.span()),
+            Expr::Index(expr) => expr.base.span().join(expr.bracket_right_token.span()),
+            Expr::Member(expr) => expr.base.span().join(expr.ident.span()),
+            Expr::Va(expr) => expr.ident.span().join(expr.paren_right_token.span()),
+            Expr::ImplicitCast(expr) => expr.expr.span(),
+        }
+    }
+
+    /// このexprの評価結果の型を返す
+    pub fn ty(&self) -> Type {
+        match self {
+            Expr::Num(num) => num.ty,
+            Expr::Ident(expr) => expr.var.ty,
+            // 関数名は関数へのポインタに変換される
+            Expr::Func(expr) => Type::FuncPtr(expr.func_ty()),
+            Expr::Call(expr) => expr.func_ty().ret_ty,
+            Expr::Unary(expr) => expr.ty(),
+            Expr::BinOp(expr) => expr.ty(),
+            Expr::Paren(expr) => expr.expr.ty(),
+            // 代入された後の左辺の値が結果になる
+            Expr::Assign(expr) => expr.lhs.ty(),
+            // 左辺の値は捨てられ、右辺の値が結果になる
+            Expr::Comma(expr) => expr.rhs.ty(),
+            Expr::Cast(expr) => expr.type_name.ty,
+            Expr::Addr(expr) => expr.ty(),
+            Expr::Deref(expr) => expr.ty(),
+            Expr::Index(expr) => expr.elem_ty(),
+            Expr::Member(expr) => expr.member.ty,
+            Expr::Va(expr) => match &expr.op {
+                VaOp::Arg(type_name) => type_name.ty,
+                // va_start と va_end は値を持たないので int とみなす
+                VaOp::Start { .. } | VaOp::End => Type::Int(IntType::Int),
+            },
+            Expr::ImplicitCast(expr) => expr.ty,
+        }
+    }
+
+    /// 関数名を表すexprかどうか。
+    /// 括弧で囲まれていてもよい。
+    pub fn is_func_designator(&self) -> bool {
+        match self {
+            Expr::Func(_) => true,
+            Expr::Paren(ExprParen { expr, .. }) => expr.is_func_designator(),
+            _ => false,
+        }
+    }
+
+    /// lvalueを表すexprの型修飾子。
+    /// 配列の要素や構造体のメンバは、配列や構造体の修飾子も引き継ぐ。
+    pub fn quals(&self) -> Qualifiers {
+        match self {
+            Expr::Ident(expr) => expr.var.quals,
+            Expr::Paren(ExprParen { expr, .. }) | Expr::Index(ExprIndex { base: expr, .. }) => {
+                expr.quals()
+            }
+            Expr::Member(expr) => expr.member.quals.merge(expr.base.quals()),
+            Expr::Deref(expr) => match expr.expr.ty() {
+                Type::Ptr(ty) => ty.quals,
+                _ => Qualifiers::default(),
+            },
+            _ => Qualifiers::default(),
+        }
+    }
 }
 
-/// "hoge = 42;"
+/// "int hoge = 42;"
+/// "int a, b[2] = {1, 2};"
 #[derive(Debug, Clone)]
-pub struct StmtAssign<'src> {
-    pub lhs_offset: usize,
-    pub lhs: Ident<'src>,
-    pub assign_token: Assign<'src>,
-    pub rhs: Expr<'src>,
-    pub semi_token: Semi<'src>,
+pub struct StmtDecl<'src> {
+    pub storage_class: Option<StorageClass>,
+    /// 宣言子を適用する前の型名
+    pub type_name: TypeName,
+    /// "," で区切られた宣言子
+    pub declarators: Vec<InitDeclarator<'src>>,
+    pub semi_token: Semi,
+}
+
+/// 宣言子と初期化子の組。1つの変数を宣言する。
+/// "hoge = 42"
+#[derive(Debug, Clone)]
+pub struct InitDeclarator<'src> {
+    pub ident: Ident<'src>,
+    pub var: Var<'src>,
+    pub init: Option<(Assign, Initializer<'src>)>,
+}
+
+/// 変数を宣言せず、構造体の型だけを定義する宣言
+/// "struct P { int x; int y; };"
+#[derive(Debug, Clone)]
+pub struct StmtTypeDecl {
+    pub type_name: TypeName,
+    pub semi_token: Semi,
+}
+
+/// 変数の初期化子
+#[derive(Debug, Clone)]
+pub enum Initializer<'src> {
+    /// スカラ型の変数の初期化式
+    /// "42"
+    Expr(Expr<'src>),
+    /// 集成体型の変数の初期化子
+    /// "{1, 2, [5] = 3}", "{.x = 1, .y = 2}"
+    List(InitList<'src>),
+}
+
+impl<'src> Initializer<'src> {
+    pub fn span(&self) -> Span {
+        match self {
+            Initializer::Expr(expr) => expr.span(),
+            Initializer::List(list) => list
+                .brace_left_token
+                .span()
+                .join(list.brace_right_token.span()),
+        }
+    }
+}
+
+/// 波括弧で囲まれた初期化子。
+/// 入れ子になった初期化子と指示子は `Parser` が解決し、
+/// 初期化されるスカラの要素の並びにする。
+#[derive(Debug, Clone)]
+pub struct InitList<'src> {
+    pub brace_left_token: BraceLeft,
+    /// 初期化される要素。オフセットの順に並ぶ。
+    /// 含まれない要素は0で初期化される
+    pub elems: Vec<InitElem<'src>>,
+    pub brace_right_token: BraceRight,
+    /// 初期化される変数の型。
+    /// 要素数を省略した配列の場合は、初期化子から推論した要素数を持つ
+    pub ty: Type,
+}
+
+/// 初期化子のうち、1つのスカラの要素の初期化式
+#[derive(Debug, Clone)]
+pub struct InitElem<'src> {
+    /// 変数の先頭からのオフセット
+    pub offset: usize,
+    pub ty: Type,
+    pub expr: Expr<'src>,
+}
+
+/// "hoge(42);"
+#[derive(Debug, Clone)]
+pub struct StmtExpr<'src> {
+    pub expr: Expr<'src>,
+    pub semi_token: Semi,
 }
 
 /// "{ hoge = 42; return hoge; }"
 #[derive(Debug, Clone)]
 pub struct StmtBlock<'src> {
-    pub brace_left_token: BraceLeft<'src>,
+    pub brace_left_token: BraceLeft,
     pub stmts: Vec<Stmt<'src>>,
-    pub brace_right_token: BraceRight<'src>,
+    pub brace_right_token: BraceRight,
 }
 
 /// "return 42;"
+/// "return;"
 #[derive(Debug, Clone)]
 pub struct StmtReturn<'src> {
-    pub return_token: Return<'src>,
-    pub expr: Expr<'src>,
-    pub semi_token: Semi<'src>,
+    pub return_token: Return,
+    pub expr: Option<Expr<'src>>,
+    pub semi_token: Semi,
+}
+
+/// ";"
+#[derive(Debug, Clone)]
+pub struct StmtNull {
+    pub semi_token: Semi,
 }
 
 /// "if (true) { 42 }"
 /// "if (i = 0) { 42 } else { 24 }"
 #[derive(Debug, Clone)]
 pub struct StmtIf<'src> {
-    pub if_token: If<'src>,
-    pub paren_left_token: ParenLeft<'src>,
+    pub if_token: If,
+    pub paren_left_token: ParenLeft,
     pub cond: Expr<'src>,
-    pub paren_right_token: ParenRight<'src>,
+    pub paren_right_token: ParenRight,
     pub then_branch: Box<Stmt<'src>>,
-    pub else_branch: Option<(Else<'src>, Box<Stmt<'src>>)>,
+    pub else_branch: Option<(Else, Box<Stmt<'src>>)>,
 }
 
 /// "while (i < 10) { i = i + 1 }"
 #[derive(Debug, Clone)]
 pub struct StmtWhile<'src> {
-    pub while_token: While<'src>,
-    pub paren_left_token: ParenLeft<'src>,
+    pub while_token: While,
+    pub paren_left_token: ParenLeft,
     pub cond: Expr<'src>,
-    pub paren_right_token: ParenRight<'src>,
+    pub paren_right_token: ParenRight,
     pub block: Box<Stmt<'src>>,
 }
 
 /// "hoge"
 #[derive(Debug, Clone)]
 pub struct ExprIdent<'src> {
-    pub ident_offset: usize,
+    pub var: Var<'src>,
     pub ident: Ident<'src>,
 }
 
-/// "func()"
+/// 式として使われた関数名
+/// "func"
 #[derive(Debug, Clone)]
-pub struct ExprCall<'src> {
+pub struct ExprFunc<'src> {
+    /// 関数の宣言。
+    /// 宣言されていない関数を呼び出す場合は `None`
+    pub func: Option<Func<'src>>,
     pub ident: Ident<'src>,
-    pub paren_left_token: ParenLeft<'src>,
+}
+
+impl<'src> ExprFunc<'src> {
+    /// 関数の型。
+    /// 宣言されていない関数は `int` を返し、任意の引数を取るものとみなす。
+    pub fn func_ty(&self) -> &'static FuncType {
+        match &self.func {
+            Some(func) => func.ty,
+            None => FuncType::implicit(),
+        }
+    }
+}
+
+/// "func()", "fp(1, 2)", "(*fp)(1, 2)"
+#[derive(Debug, Clone)]
+pub struct ExprCall<'src> {
+    /// 呼び出す関数を表す式。
+    /// 関数名か、関数へのポインタ型の式
+    pub callee: Box<Expr<'src>>,
+    pub paren_left_token: ParenLeft,
     pub params: Vec<Expr<'src>>,
-    pub paren_right_token: ParenRight<'src>,
+    pub paren_right_token: ParenRight,
+}
+
+impl<'src> ExprCall<'src> {
+    /// 呼び出す関数の型
+    pub fn func_ty(&self) -> &'static FuncType {
+        match self.callee.ty() {
+            Type::FuncPtr(ty) => ty,
+            // 関数へのポインタ以外の呼び出しは `Parser` がエラーにする
+            _ => FuncType::implicit(),
+        }
+    }
+
+    /// 関数名を直接呼び出す場合は、その関数名のexprを返す。
+    /// "(&func)()" や "(*func)()" も直接の呼び出しとみなす。
+    pub fn direct_callee(&self) -> Option<&ExprFunc<'src>> {
+        let mut callee = &*self.callee;
+        loop {
+            match callee {
+                Expr::Func(func) => return Some(func),
+                Expr::Paren(ExprParen { expr, .. })
+                | Expr::Addr(ExprAddr { expr, .. })
+                | Expr::Deref(ExprDeref { expr, .. }) => callee = expr,
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// "-42", "+x"
+#[derive(Debug, Clone)]
+pub struct ExprUnary<'src> {
+    pub op: UnaryOp,
+    pub expr: Box<Expr<'src>>,
+}
+
+impl<'src> ExprUnary<'src> {
+    /// 演算結果の型。
+    /// 整数のオペランドは整数拡張され、浮動小数点数はそのままの型で演算する。
+    pub fn ty(&self) -> Type {
+        match self.expr.ty() {
+            Type::Int(ty) => Type::Int(ty.promoted()),
+            ty => ty,
+        }
+    }
 }
 
 /// "4 * 2"
 #[derive(Debug, Clone)]
 pub struct ExprBinOp<'src> {
     pub lhs: Box<Expr<'src>>,
-    pub op: BinOp<'src>,
+    pub op: BinOp,
     pub rhs: Box<Expr<'src>>,
 }
 
+impl<'src> ExprBinOp<'src> {
+    /// 演算結果の型
+    pub fn ty(&self) -> Type {
+        match self.op {
+            BinOp::Lt(_) | BinOp::Lte(_) | BinOp::Eq(_) | BinOp::Neq(_) => Type::Int(IntType::Int),
+            _ => self.operand_ty(),
+        }
+    }
+
+    /// 演算を行う際の型。
+    /// 整数の符号の有無や浮動小数点数かどうかで演算の命令が変わる。
+    pub fn operand_ty(&self) -> Type {
+        self.lhs
+            .ty()
+            .common(self.rhs.ty())
+            // ポインタは64bitの整数として比較する。
+            // それ以外の算術型でないオペランドは `Converter` がエラーにする
+            .unwrap_or(Type::Int(IntType::Long))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExprParen<'src> {
-    pub paren_left_token: ParenLeft<'src>,
+    pub paren_left_token: ParenLeft,
+    pub expr: Box<Expr<'src>>,
+    pub paren_right_token: ParenRight,
+}
+
+/// "hoge = 42", "a[0] = 42", "p.x = 42"
+#[derive(Debug, Clone)]
+pub struct ExprAssign<'src> {
+    /// 変数か、配列の要素や構造体のメンバを表すexpr
+    pub lhs: Box<Expr<'src>>,
+    pub assign_token: Assign,
+    pub rhs: Box<Expr<'src>>,
+}
+
+/// "f(), 42"
+#[derive(Debug, Clone)]
+pub struct ExprComma<'src> {
+    pub lhs: Box<Expr<'src>>,
+    pub comma_token: Comma,
+    pub rhs: Box<Expr<'src>>,
+}
+
+/// "(unsigned char)hoge"
+#[derive(Debug, Clone)]
+pub struct ExprCast<'src> {
+    pub paren_left_token: ParenLeft,
+    pub type_name: TypeName,
+    pub paren_right_token: ParenRight,
+    pub expr: Box<Expr<'src>>,
+}
+
+/// "&func", "&x"
+#[derive(Debug, Clone)]
+pub struct ExprAddr<'src> {
+    pub amp_token: Amp,
+    /// 関数名か、変数やその要素・メンバを表すlvalue
+    pub expr: Box<Expr<'src>>,
+}
+
+impl<'src> ExprAddr<'src> {
+    /// "&func" は関数へのポインタに、"&x" は `x` の型へのポインタになる
+    pub fn ty(&self) -> Type {
+        if self.expr.is_func_designator() {
+            return self.expr.ty();
+        }
+        let ty = PtrType {
+            pointee: self.expr.ty(),
+            quals: self.expr.quals(),
+        };
+        Type::Ptr(ty.intern())
+    }
+}
+
+/// "*fp", "*p"
+#[derive(Debug, Clone)]
+pub struct ExprDeref<'src> {
+    pub star_token: Mul,
+    /// ポインタ型の式
+    pub expr: Box<Expr<'src>>,
+}
+
+impl<'src> ExprDeref<'src> {
+    /// "*p" は `p` の指す先の型になる。
+    /// 関数へのポインタは関数に戻ってから再び関数へのポインタになるので、型は変わらない
+    pub fn ty(&self) -> Type {
+        match self.expr.ty() {
+            Type::Ptr(ty) => ty.pointee,
+            ty => ty,
+        }
+    }
+}
+
+/// "a[i]"
+#[derive(Debug, Clone)]
+pub struct ExprIndex<'src> {
+    /// 配列型の式
+    pub base: Box<Expr<'src>>,
+    pub bracket_left_token: BracketLeft,
+    pub index: Box<Expr<'src>>,
+    pub bracket_right_token: BracketRight,
+}
+
+impl<'src> ExprIndex<'src> {
+    /// 配列の要素の型
+    pub fn elem_ty(&self) -> Type {
+        match self.base.ty() {
+            Type::Array(ty) => ty.elem,
+            // 配列以外の添字アクセスは `Parser` がエラーにする
+            ty => unreachable!("subscripted value of type {}", ty),
+        }
+    }
+}
+
+/// "p.x"
+#[derive(Debug, Clone)]
+pub struct ExprMember<'src> {
+    /// 構造体型の式
+    pub base: Box<Expr<'src>>,
+    pub dot_token: Dot,
+    pub ident: Ident<'src>,
+    pub member: &'static Member,
+}
+
+/// "va_start(ap, n)", "va_arg(ap, int)", "va_end(ap)"
+#[derive(Debug, Clone)]
+pub struct ExprVa<'src> {
+    pub op: VaOp<'src>,
+    pub ident: Ident<'src>,
+    pub paren_left_token: ParenLeft,
+    /// `va_list` 型の式
+    pub ap: Box<Expr<'src>>,
+    pub paren_right_token: ParenRight,
+}
+
+#[derive(Debug, Clone)]
+pub enum VaOp<'src> {
+    Start { last: Ident<'src>, frame: VaFrame },
+    Arg(TypeName),
+    End,
+}
+
+/// `va_start` が `va_list` を初期化するのに使う、可変長引数の関数のスタックフレームの情報
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaFrame {
+    /// 名前付き引数が使った汎用レジスタの退避領域のバイト数
+    pub gp_offset: usize,
+    /// 名前付き引数が使ったXMMレジスタの退避領域の終わりの、
+    /// レジスタ退避領域の先頭からのバイト数
+    pub fp_offset: usize,
+    /// スタックで渡された最初の可変長引数のRBPからのオフセット
+    pub overflow_arg_area: usize,
+    /// レジスタ退避領域のRBPからのオフセット
+    pub reg_save_area: usize,
+}
+
+/// 暗黙の型変換。
+/// ソースコード上には現れず、`Converter` によって挿入される。
+#[derive(Debug, Clone)]
+pub struct ExprImplicitCast<'src> {
+    pub ty: Type,
     pub expr: Box<Expr<'src>>,
-    pub paren_right_token: ParenRight<'src>,
 }
@@ -1,14 +1,30 @@
-use crate::token::token::*;
+use crate::token::{tokens::*, Span};
 
 /// A binary operator: `+`, `+=`, `<`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum BinOp<'src> {
-    Add(Add<'src>),
-    Sub(Sub<'src>),
-    Mul(Mul<'src>),
-    Div(Div<'src>),
-    Lt(Lt<'src>),
-    Lte(Lte<'src>),
-    Eq(Eq<'src>),
-    Neq(Neq<'src>),
+pub enum BinOp {
+    Add(Add),
+    Sub(Sub),
+    Mul(Mul),
+    Div(Div),
+    Lt(Lt),
+    Lte(Lte),
+    Eq(Eq),
+    Neq(Neq),
+}
+
+/// A unary arithmetic operator: `+`, `-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Plus(Add),
+    Neg(Sub),
+}
+
+impl UnaryOp {
+    pub fn span(&self) -> Span {
+        match self {
+            UnaryOp::Plus(token) => token.span(),
+            UnaryOp::Neg(token) => token.span(),
+        }
+    }
 }
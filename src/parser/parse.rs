@@ -0,0 +1,3339 @@
+use super::{
+    eval::{eval_const, eval_initializer},
+    node::*,
+    op::{BinOp, UnaryOp},
+};
+use crate::{
+    diagnostic::{CompileError, Diagnostic, DEFAULT_MAX_ERRORS},
+    generator::get_unique_num,
+    token::{tokens::*, Span, TokenStream},
+    ty::{ArrayType, FloatType, FuncType, IntType, PtrType, Qualifiers, StructType, Type},
+};
+use std::collections::{HashMap, HashSet};
+
+type PResult<T> = Result<T, Diagnostic>;
+
+/// 型名として扱う識別子
+const VA_LIST: &str = "va_list";
+
+/// System V ABI のレジスタ退避領域の大きさ。
+/// 6個の汎用レジスタと8個のXMMレジスタを退避する。
+const REG_SAVE_AREA_SIZE: usize = 6 * 8 + 8 * 16;
+
+/// 引数を渡すのに使う汎用レジスタの数
+const ARG_REGS_LEN: usize = 6;
+
+/// 引数を渡すのに使うXMMレジスタの数
+const FLOAT_ARG_REGS_LEN: usize = 8;
+
+/// 1つの関数のローカル変数の領域の大きさの上限。
+/// 16 byte単位に切り上げたフレームの大きさとRBPからのオフセットが、
+/// 32bitの即値やディスプレースメントで表せる範囲に収まるようにする。
+const MAX_LOCAL_VARS_LEN: usize = i32::MAX as usize & !15;
+
+pub struct Parser<'src> {
+    globals: HashMap<&'src str, Var<'src>>,
+    /// "extern" を付けずに宣言された、定義済みのグローバル変数
+    defined_globals: HashSet<&'src str>,
+    /// 宣言済みの関数
+    funcs: HashMap<&'src str, Func<'src>>,
+    /// 定義済みの関数
+    defined_funcs: HashSet<&'src str>,
+    /// 内部結合で宣言されたグローバル変数と関数
+    internal_symbols: HashSet<&'src str>,
+    /// 定義済みの構造体のタグ。
+    /// タグのスコープは区別せず、翻訳単位全体で共有する
+    struct_tags: HashMap<&'src str, &'static StructType>,
+    /// "static" の付いたローカル変数。
+    /// グローバル変数と同じように静的な領域に配置される
+    static_locals: Vec<GlobalVar<'src>>,
+    /// パース中の関数定義。
+    /// トップレベルのstmtをパースしている間は `None`
+    func_ctx: Option<FuncContext<'src>>,
+    /// ローカル変数のスコープ。
+    /// 先頭は関数全体のスコープで、ブロックに入るたびに積まれる。
+    local_scopes: Vec<HashMap<&'src str, Var<'src>>>,
+    /// 割り当て済みのローカル変数の領域の大きさ
+    local_vars_len: usize,
+    /// パース中の初期化子で、閉じられていない "{" の数。
+    /// 初期化子の中でエラーが起きた場合に、初期化子の終わりまで読み飛ばすために使う
+    init_depth: usize,
+    /// パース中に見つかったエラー
+    errors: Vec<Diagnostic>,
+    /// 報告するエラーの最大数。
+    /// これに達した時点でパースを打ち切る。
+    max_errors: usize,
+}
+
+/// 次のトークンが `$token` であれば読み進めてそれを返す。
+/// そうでなければエラーを返す。
+/// エラーから復帰できるよう、期待と異なるトークンは読み進めない。
+macro_rules! parse_exact {
+    ($tokens:expr, $token:tt) => {
+        match $tokens.peek() {
+            Some(Token::$token(token)) => {
+                let _ = $tokens.next();
+                token
+            }
+            Some(token) => {
+                let msg = format!("expected {} but found {}", $token::display(), token);
+                return Err(Diagnostic::error(token.span(), msg));
+            }
+            None => {
+                let msg = format!("expected {} but found EOF", $token::display());
+                return Err(Diagnostic::error($tokens.pos(), msg));
+            }
+        }
+    };
+}
+
+impl<'src> Parser<'src> {
+    pub fn new() -> Self {
+        Parser {
+            globals: HashMap::new(),
+            defined_globals: HashSet::new(),
+            funcs: HashMap::new(),
+            defined_funcs: HashSet::new(),
+            internal_symbols: HashSet::new(),
+            struct_tags: HashMap::new(),
+            static_locals: Vec::new(),
+            func_ctx: None,
+            local_scopes: vec![HashMap::new()],
+            local_vars_len: 0,
+            init_depth: 0,
+            errors: Vec::new(),
+            max_errors: DEFAULT_MAX_ERRORS,
+        }
+    }
+
+    /// 報告するエラーの最大数を設定する
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors.max(1);
+        self
+    }
+
+    /// 宣言済みの変数を探す
+    fn find_var(&self, ident: &'src str) -> Option<Var<'src>> {
+        self.local_scopes
+            .iter()
+            .rev()
+            .chain(std::iter::once(&self.globals))
+            .find_map(|scope| scope.get(ident))
+            .copied()
+    }
+
+    /// 変数を探す。
+    /// 宣言されていない変数は `long` 型のローカル変数として暗黙的に宣言する。
+    fn lookup_var(&mut self, ident: Ident<'src>) -> PResult<Var<'src>> {
+        if let Some(var) = self.find_var(ident.name) {
+            return Ok(var);
+        }
+
+        let var = self.alloc_local_var(ident, Type::Int(IntType::Long), Qualifiers::default())?;
+        self.local_scopes[0].insert(ident.name, var);
+        Ok(var)
+    }
+
+    /// `ident` という名前のローカル変数の領域を割り当てる
+    fn alloc_local_var(
+        &mut self,
+        ident: Ident<'src>,
+        ty: Type,
+        quals: Qualifiers,
+    ) -> PResult<Var<'src>> {
+        match self.alloc_local_area(ty.size(), ty.align()) {
+            Some(offset) => Ok(Var {
+                loc: VarLoc::Local(offset),
+                ty,
+                quals,
+            }),
+            None => {
+                let msg = format!(
+                    "local variable \"{}\" exceeds the stack frame limit of {} bytes",
+                    ident.name, MAX_LOCAL_VARS_LEN
+                );
+                Err(Diagnostic::error(ident.span(), msg))
+            }
+        }
+    }
+
+    /// `size` バイトのローカルな領域を割り当て、そのRBPからのオフセットを返す。
+    /// RBPは16 byteにalignされているので、オフセットを `align` の倍数にすれば
+    /// 領域の先頭のアドレスもalignされる。
+    /// 引数や初期化子は8 byte単位で書き込まれるため、領域は8 byteの倍数の大きさにする。
+    /// 領域の合計が `MAX_LOCAL_VARS_LEN` を超える場合は割り当てずに `None` を返す。
+    fn alloc_local_area(&mut self, size: usize, align: usize) -> Option<usize> {
+        let align = align.max(8);
+        let len = size
+            .checked_next_multiple_of(8)
+            .and_then(|size| self.local_vars_len.checked_add(size))
+            .and_then(|len| len.checked_next_multiple_of(align))
+            .filter(|len| *len <= MAX_LOCAL_VARS_LEN)?;
+        self.local_vars_len = len;
+        Some(len)
+    }
+
+    /// 現在のスコープにローカル変数を宣言する
+    fn declare_local_var(
+        &mut self,
+        ident: Ident<'src>,
+        ty: Type,
+        quals: Qualifiers,
+    ) -> PResult<Var<'src>> {
+        if self.local_scopes.last().unwrap().contains_key(ident.name) {
+            let msg = format!("redefinition of \"{}\"", ident.name);
+            return Err(Diagnostic::error(ident.span(), msg));
+        }
+
+        let var = self.alloc_local_var(ident, ty, quals)?;
+        self.local_scopes
+            .last_mut()
+            .unwrap()
+            .insert(ident.name, var);
+        Ok(var)
+    }
+
+    /// 現在のスコープに "static" の付いたローカル変数を宣言する。
+    /// 変数は関数ごとに別の領域を持つよう、一意な番号を付けたラベルで配置される。
+    fn declare_static_local_var(
+        &mut self,
+        ident: Ident<'src>,
+        ty: Type,
+        quals: Qualifiers,
+    ) -> PResult<Var<'src>> {
+        if self.local_scopes.last().unwrap().contains_key(ident.name) {
+            let msg = format!("redefinition of \"{}\"", ident.name);
+            return Err(Diagnostic::error(ident.span(), msg));
+        }
+
+        let var = Var {
+            loc: VarLoc::Static(ident.name, get_unique_num()),
+            ty,
+            quals,
+        };
+        self.local_scopes
+            .last_mut()
+            .unwrap()
+            .insert(ident.name, var);
+        Ok(var)
+    }
+
+    /// グローバル変数を宣言する。
+    /// 型が一致していれば、"extern" による宣言は何度でも繰り返せる。
+    fn declare_global_var(
+        &mut self,
+        ident: Ident<'src>,
+        ty: Type,
+        quals: Qualifiers,
+        storage_class: Option<StorageClass>,
+    ) -> PResult<(Var<'src>, Linkage)> {
+        let is_extern = matches!(storage_class, Some(StorageClass::Extern(_)));
+
+        if self.funcs.contains_key(ident.name) {
+            let msg = format!(
+                "redefinition of \"{}\" as different kind of symbol",
+                ident.name
+            );
+            return Err(Diagnostic::error(ident.span(), msg));
+        }
+
+        if let Some(prev) = self.globals.get(ident.name) {
+            if prev.ty != ty || prev.quals != quals {
+                let msg = format!("conflicting types for \"{}\"", ident.name);
+                return Err(Diagnostic::error(ident.span(), msg));
+            }
+            if !is_extern && self.defined_globals.contains(ident.name) {
+                let msg = format!("redefinition of \"{}\"", ident.name);
+                return Err(Diagnostic::error(ident.span(), msg));
+            }
+        }
+
+        let is_declared = self.globals.contains_key(ident.name);
+        let linkage = self.resolve_linkage(ident, storage_class, is_declared)?;
+
+        let var = Var {
+            loc: VarLoc::Global(ident.name),
+            ty,
+            quals,
+        };
+        self.globals.insert(ident.name, var);
+        if !is_extern {
+            self.defined_globals.insert(ident.name);
+        }
+        Ok((var, linkage))
+    }
+
+    /// 関数を宣言する。
+    /// 同じ関数を複数回宣言する場合は、型に互換性がなければならない。
+    /// 仮引数の型を宣言した宣言があれば、その型を関数の型とする。
+    fn declare_func(
+        &mut self,
+        ident: Ident<'src>,
+        func: &Func<'src>,
+        storage_class: Option<StorageClass>,
+    ) -> PResult<Linkage> {
+        if self.globals.contains_key(ident.name) {
+            let msg = format!(
+                "redefinition of \"{}\" as different kind of symbol",
+                ident.name
+            );
+            return Err(Diagnostic::error(ident.span(), msg));
+        }
+
+        match self.funcs.get(ident.name).copied() {
+            Some(prev) if !prev.ty.is_compatible(func.ty) => {
+                let msg = format!("conflicting types for \"{}\"", ident.name);
+                Err(Diagnostic::error(ident.span(), msg))
+            }
+            prev => {
+                let linkage = self.resolve_linkage(ident, storage_class, prev.is_some())?;
+                let func = match prev {
+                    Some(prev) if !func.ty.is_prototyped() => prev,
+                    _ => *func,
+                };
+                self.funcs.insert(ident.name, func);
+                Ok(linkage)
+            }
+        }
+    }
+
+    /// グローバル変数や関数の宣言の結合を決める。
+    /// "static" の付いた宣言は内部結合になり、先に外部結合で宣言されていてはならない。
+    /// "static" の無い宣言は先に宣言された結合を引き継ぐ。
+    fn resolve_linkage(
+        &mut self,
+        ident: Ident<'src>,
+        storage_class: Option<StorageClass>,
+        is_declared: bool,
+    ) -> PResult<Linkage> {
+        let is_internal = self.internal_symbols.contains(ident.name);
+
+        if let Some(StorageClass::Static(_)) = storage_class {
+            if is_declared && !is_internal {
+                let msg = format!(
+                    "static declaration of \"{}\" follows non-static declaration",
+                    ident.name
+                );
+                return Err(Diagnostic::error(ident.span(), msg));
+            }
+            self.internal_symbols.insert(ident.name);
+            return Ok(Linkage::Internal);
+        }
+
+        if is_internal {
+            Ok(Linkage::Internal)
+        } else {
+            Ok(Linkage::External)
+        }
+    }
+
+    /// > program       = (external_decl | stmt)*
+    /// > external_decl = storage_class? type_name
+    /// >     (";" | ident func_decl_rest | init_declarators ";")
+    /// > storage_class = "extern" | "static"
+    /// > func_decl_rest = "(" params ")" (";" | func_body)
+    /// > params        = "void" | (param ("," param)* ("," "...")?)?
+    /// > param         = type_name declarator
+    /// > func_body     = "{" stmt* "}"
+    /// > decl          = "static"? type_name (";" | init_declarators ";")
+    /// > init_declarators = init_declarator ("," init_declarator)*
+    /// > init_declarator = declarator ("=" initializer)?
+    /// > initializer   = assign | "{" (init_elem ("," init_elem)* ","?)? "}"
+    /// > init_elem     = designation? initializer
+    /// > designation   = designator+ "="
+    /// > designator    = "[" equality "]" | "." ident
+    /// > declarator    = "*" type_qual* declarator
+    /// >     | ident? array_suffix
+    /// >     | "(" "*" ident? ")" "(" params ")"
+    /// > array_suffix  = ("[" equality? "]")*
+    /// > type_name     = type_qual* type_spec type_qual*
+    /// > type_spec     = ("char" | "short" | "int" | "long" | "signed" | "unsigned")+
+    /// >     | "void" | "float" | "double" | "va_list" | struct_spec
+    /// > type_qual     = "const" | "volatile"
+    /// > struct_spec   = "struct" ident? ("{" (type_name declarator ";")* "}")?
+    /// > stmt          = decl
+    /// >     | expr ";"
+    /// >     | "return" expr? ";"
+    /// >     | ";"
+    /// >     | "if" "(" expr ")" stmt ("else" stmt)?
+    /// >     | "while" "(" expr ")" stmt
+    /// >     | "{" stmt* "}"
+    /// > expr          = assign ("," assign)*
+    /// > assign        = equality ("=" assign)?
+    /// > equality      = relational ("==" relational | "!=" relational)*
+    /// > relational    = add ("<" add | "<=" add | ">" add | ">=" add)*
+    /// > add           = mul ("+" mul | "-" mul)*
+    /// > mul           = cast ("*" cast | "/" cast)*
+    /// > cast          = "(" type_name declarator ")" cast | unary
+    /// > unary         = ("+" | "-" | "&" | "*") cast | postfix
+    /// > postfix       = primary ( "(" call_params? ")" | "[" expr "]" | "." ident )*
+    /// > primary       = num
+    /// >     | va
+    /// >     | ident
+    /// >     | "(" expr ")"
+    /// > va            = "va_start" "(" assign "," ident ")"
+    /// >     | "va_arg" "(" assign "," type_name ")"
+    /// >     | "va_end" "(" assign ")"
+    ///
+    /// で表現される文法をパースする関数。
+    ///
+    /// 構文エラーが見つかってもパースを続け、
+    /// 見つかった全てのエラーをまとめて返す。
+    pub fn parse(&mut self, tokens: &mut TokenStream<'src>) -> Result<Program<'src>, CompileError> {
+        let program = self.parse_program(tokens);
+
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(CompileError {
+                diagnostics: std::mem::take(&mut self.errors),
+            })
+        }
+    }
+
+    /// > program       = (external_decl | stmt)*
+    ///
+    /// で表現される非終端記号programをパースする関数。
+    /// トップレベルの宣言はグローバル変数または関数の宣言とみなし、
+    /// それ以外のstmtはmain関数の本体とみなす。
+    /// 見つかったエラーは `self.errors` に記録される。
+    pub fn parse_program(&mut self, tokens: &mut TokenStream<'src>) -> Program<'src> {
+        let mut globals = Vec::new();
+        let mut funcs = Vec::new();
+        let mut func_defs = Vec::new();
+        let mut stmts = Vec::new();
+        while let Some(token) = tokens.peek() {
+            let result = if is_type_name_start(token) || is_storage_class(token) {
+                self.recovering(tokens, Self::parse_external_decl)
+                    .map(|decl| {
+                        decl.map(|decl| match decl {
+                            ExternalDecl::Vars(vars) => globals.extend(vars),
+                            ExternalDecl::Func(func) => funcs.push(func),
+                            ExternalDecl::FuncDef(func_def) => func_defs.push(func_def),
+                            // 型の定義はパース中にだけ使う
+                            ExternalDecl::Type => {}
+                        })
+                    })
+            } else {
+                self.recovering(tokens, Self::parse_stmt)
+                    .map(|stmt| stmt.map(|stmt| stmts.push(stmt)))
+            };
+
+            match result {
+                Ok(Some(())) => {}
+                Ok(None) => {
+                    // 対応する "{" の無い "}" は読み飛ばす
+                    if let Some(Token::BraceRight(_)) = tokens.peek() {
+                        let _ = tokens.next();
+                    }
+                }
+                Err(fatal) => {
+                    self.errors.push(fatal);
+                    break;
+                }
+            }
+        }
+
+        // "static" の付いたローカル変数はグローバル変数と一緒に配置する
+        globals.append(&mut self.static_locals);
+
+        Program {
+            globals,
+            funcs,
+            func_defs,
+            stmts,
+            locals_len: self.local_vars_len,
+        }
+    }
+
+    /// `parse` でstmtなどをパースし、エラーが起きた場合はそれを記録して
+    /// 次のstmtの先頭まで読み飛ばす。
+    /// エラーから復帰した場合は `Ok(None)` を返す。
+    ///
+    /// 記録したエラーの数が上限に達した場合は、パースを打ち切るための
+    /// エラーを `Err` で返す。
+    fn recovering<T>(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        parse: impl FnOnce(&mut Self, &mut TokenStream<'src>) -> PResult<T>,
+    ) -> PResult<Option<T>> {
+        match parse(self, tokens) {
+            Ok(node) => Ok(Some(node)),
+            Err(e) => {
+                // 内側のブロックでパースが打ち切られた場合
+                if self.errors.len() >= self.max_errors {
+                    return Err(e);
+                }
+
+                self.errors.push(e);
+                if self.errors.len() >= self.max_errors {
+                    return Err(Diagnostic::error(
+                        tokens.pos(),
+                        "too many errors emitted, stopping now",
+                    ));
+                }
+
+                synchronize(tokens, std::mem::take(&mut self.init_depth));
+                Ok(None)
+            }
+        }
+    }
+
+    /// > stmt          = assign
+    /// >     | "return" expr? ";"
+    /// >     | ";"
+    /// >     | "if" "(" expr ")" stmt ("else" stmt)?
+    /// >     | "while" "(" expr ")" stmt
+    /// >     | "{" stmt* "}"
+    ///
+    /// で表現される非終端記号stmtをパースする関数。
+    pub fn parse_stmt(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Stmt<'src>> {
+        let stmt = match tokens.peek() {
+            // "return" から始まるとき
+            Some(Token::Return(return_token)) => {
+                let _ = tokens.next();
+                // 返り値の無い "return" は式を省略できる
+                let expr = match tokens.peek() {
+                    Some(Token::Semi(_)) => None,
+                    _ => Some(self.parse_expr(tokens)?),
+                };
+                let semi_token = parse_exact!(tokens, Semi);
+
+                Stmt::Return(StmtReturn {
+                    return_token,
+                    expr,
+                    semi_token,
+                })
+            }
+
+            // "if" から始まるとき
+            Some(Token::If(if_token)) => {
+                let _ = tokens.next();
+
+                // 次のTokenが "(" であることを確認
+                let paren_left_token = parse_exact!(tokens, ParenLeft);
+
+                // cond をパース
+                let cond = self.parse_expr(tokens)?;
+
+                // 次のTokenが ")" であることを確認
+                let paren_right_token = parse_exact!(tokens, ParenRight);
+
+                // stmt をパース
+                let stmt = self.parse_stmt(tokens)?;
+
+                // 次のTokenが "else" かどうか確認
+                match tokens.peek() {
+                    Some(Token::Else(else_token)) => {
+                        let _ = tokens.next();
+                        let else_stmt = self.parse_stmt(tokens)?;
+                        Stmt::If(StmtIf {
+                            if_token,
+                            paren_left_token,
+                            cond,
+                            paren_right_token,
+                            then_branch: Box::new(stmt),
+                            else_branch: Some((else_token, Box::new(else_stmt))),
+                        })
+                    }
+                    _ => Stmt::If(StmtIf {
+                        if_token,
+                        paren_left_token,
+                        cond,
+                        paren_right_token,
+                        then_branch: Box::new(stmt),
+                        else_branch: None,
+                    }),
+                }
+            }
+
+            // "while" から始まるとき
+            Some(Token::While(while_token)) => {
+                let _ = tokens.next();
+
+                // 次のトークンが "(" であることを確認する
+                let paren_left_token = parse_exact!(tokens, ParenLeft);
+
+                let cond = self.parse_expr(tokens)?;
+
+                // 次のトークンが ")" であることを確認する
+                let paren_right_token = parse_exact!(tokens, ParenRight);
+
+                let stmt = self.parse_stmt(tokens)?;
+
+                Stmt::While(StmtWhile {
+                    while_token,
+                    paren_left_token,
+                    cond,
+                    paren_right_token,
+                    block: Box::new(stmt),
+                })
+            }
+
+            // ";" だけのときは何もしない
+            Some(Token::Semi(semi_token)) => {
+                let _ = tokens.next();
+                Stmt::Null(StmtNull { semi_token })
+            }
+
+            // "{" から始まるとき
+            Some(Token::BraceLeft(brace_left_token)) => {
+                let _ = tokens.next();
+
+                // ブロックの中で宣言された変数はブロックの外からは見えない
+                self.local_scopes.push(HashMap::new());
+                let mut stmts = Vec::new();
+                let brace_right_token = self.parse_block_stmts(tokens, &mut stmts);
+                self.local_scopes.pop();
+
+                Stmt::Block(StmtBlock {
+                    brace_left_token,
+                    stmts,
+                    brace_right_token: brace_right_token?,
+                })
+            }
+
+            // 型名か "static" から始まるとき
+            Some(token) if is_type_name_start(token) || matches!(token, Token::Static(_)) => {
+                self.parse_decl(tokens)?
+            }
+
+            // その他の時は式として処理する
+            _ => {
+                let expr = self.parse_expr(tokens)?;
+                let semi_token = parse_exact!(tokens, Semi);
+                Stmt::Expr(StmtExpr { expr, semi_token })
+            }
+        };
+
+        Ok(stmt)
+    }
+
+    /// "}" が現れるまでstmtをパースし、"}" を返す
+    fn parse_block_stmts(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        stmts: &mut Vec<Stmt<'src>>,
+    ) -> PResult<BraceRight> {
+        loop {
+            match tokens.peek() {
+                Some(Token::BraceRight(token)) => {
+                    let _ = tokens.next();
+                    return Ok(token);
+                }
+                Some(_) => {
+                    if let Some(stmt) = self.recovering(tokens, Self::parse_stmt)? {
+                        stmts.push(stmt);
+                    }
+                }
+                None => {
+                    return Err(Diagnostic::error(
+                        tokens.pos(),
+                        "expected \"}\" but found EOF",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// > decl          = "static"? type_name (";" | init_declarators ";")
+    ///
+    /// で表現される記号declをパースし、ローカル変数を宣言する関数。
+    /// "static" の付いた変数は静的な領域に配置され、初期化子はコンパイル時に評価される。
+    /// 宣言子の無い宣言は構造体の型だけを定義する。
+    pub fn parse_decl(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Stmt<'src>> {
+        let storage_class = match tokens.peek() {
+            Some(Token::Static(token)) => {
+                let _ = tokens.next();
+                Some(StorageClass::Static(token))
+            }
+            _ => None,
+        };
+        let type_name = self.parse_type_name(tokens)?;
+        if let Some(decl) = parse_type_decl(tokens, type_name) {
+            return Ok(Stmt::TypeDecl(decl));
+        }
+        let first = self.parse_named_declarator(tokens, type_name)?;
+
+        let (declarators, semi_token) = self.parse_init_declarators(
+            tokens,
+            type_name,
+            first,
+            |this, tokens, type_name, ident| {
+                this.parse_local_var_rest(tokens, storage_class, type_name, ident)
+            },
+        )?;
+
+        Ok(Stmt::Decl(StmtDecl {
+            storage_class,
+            type_name,
+            declarators,
+            semi_token,
+        }))
+    }
+
+    /// ローカル変数の宣言のうち、宣言子より後ろの部分をパースし、変数を宣言する関数。
+    /// "static" の付いた変数は静的な領域に配置される変数としても記録する。
+    fn parse_local_var_rest(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        storage_class: Option<StorageClass>,
+        type_name: TypeName,
+        ident: Ident<'src>,
+    ) -> PResult<InitDeclarator<'src>> {
+        if storage_class.is_none() {
+            return self.parse_init_declarator_rest(
+                tokens,
+                storage_class,
+                type_name,
+                ident,
+                |this, ty| this.declare_local_var(ident, ty, type_name.quals),
+            );
+        }
+
+        if type_name.ty == Type::VaList {
+            return Err(Diagnostic::error(
+                type_name.span,
+                "\"va_list\" cannot be used for static variables",
+            ));
+        }
+        let decl = self.parse_init_declarator_rest(
+            tokens,
+            storage_class,
+            type_name,
+            ident,
+            |this, ty| this.declare_static_local_var(ident, ty, type_name.quals),
+        )?;
+
+        let init_values = eval_static_initializer(&decl)?;
+        self.static_locals.push(GlobalVar {
+            storage_class,
+            decl: decl.clone(),
+            init_values,
+            // 関数の外からは参照できない
+            linkage: Linkage::Internal,
+        });
+
+        Ok(decl)
+    }
+
+    /// > init_declarators = init_declarator ("," init_declarator)*
+    ///
+    /// で表現される記号init_declaratorsをパースする関数。
+    /// 最初の宣言子 `first` はパース済みで、残りの宣言子は `type_name` に適用される。
+    /// 宣言子ごとに `parse_rest` で初期化子をパースして変数を宣言し、最後の ";" も読み進める。
+    fn parse_init_declarators<T>(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        type_name: TypeName,
+        first: (TypeName, Ident<'src>),
+        mut parse_rest: impl FnMut(
+            &mut Self,
+            &mut TokenStream<'src>,
+            TypeName,
+            Ident<'src>,
+        ) -> PResult<T>,
+    ) -> PResult<(Vec<T>, Semi)> {
+        let mut declarators = vec![parse_rest(self, tokens, first.0, first.1)?];
+
+        while let Some(Token::Comma(_)) = tokens.peek() {
+            let _ = tokens.next();
+            let (type_name, ident) = self.parse_named_declarator(tokens, type_name)?;
+            declarators.push(parse_rest(self, tokens, type_name, ident)?);
+        }
+        let semi_token = parse_exact!(tokens, Semi);
+
+        Ok((declarators, semi_token))
+    }
+
+    /// > external_decl = storage_class? type_name
+    /// >     (";" | ident func_decl_rest | init_declarators ";")
+    /// > storage_class = "extern" | "static"
+    ///
+    /// で表現される記号external_declをパースする関数。
+    /// 識別子の次が "(" であれば関数の宣言、そうでなければグローバル変数の宣言とみなす。
+    /// 宣言子の無い宣言は構造体の型だけを定義する。
+    fn parse_external_decl(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+    ) -> PResult<ExternalDecl<'src>> {
+        let storage_class = match tokens.peek() {
+            Some(Token::Extern(token)) => {
+                let _ = tokens.next();
+                Some(StorageClass::Extern(token))
+            }
+            Some(Token::Static(token)) => {
+                let _ = tokens.next();
+                Some(StorageClass::Static(token))
+            }
+            _ => None,
+        };
+        let type_name = self.parse_type_name(tokens)?;
+        if parse_type_decl(tokens, type_name).is_some() {
+            return Ok(ExternalDecl::Type);
+        }
+        let (decl_type_name, ident) = self.parse_named_declarator(tokens, type_name)?;
+
+        match tokens.peek() {
+            // 関数へのポインタ型の変数の宣言は ")" で終わっている
+            Some(Token::ParenLeft(_)) if !matches!(decl_type_name.ty, Type::FuncPtr(_)) => {
+                self.parse_func_decl_rest(tokens, storage_class, decl_type_name, ident)
+            }
+            _ => {
+                let (globals, _) = self.parse_init_declarators(
+                    tokens,
+                    type_name,
+                    (decl_type_name, ident),
+                    |this, tokens, type_name, ident| {
+                        this.parse_global_var_rest(tokens, storage_class, type_name, ident)
+                    },
+                )?;
+                Ok(ExternalDecl::Vars(globals))
+            }
+        }
+    }
+
+    /// > func_decl_rest = "(" params ")" (";" | func_body)
+    ///
+    /// で表現される記号func_decl_restをパースし、関数を宣言する関数。
+    /// ")" の次が "{" であれば関数の定義とみなす。
+    fn parse_func_decl_rest(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        storage_class: Option<StorageClass>,
+        ret_type_name: TypeName,
+        ident: Ident<'src>,
+    ) -> PResult<ExternalDecl<'src>> {
+        if ret_type_name.ty == Type::VaList || ret_type_name.ty.is_aggregate() {
+            let msg = format!("function cannot return \"{}\"", ret_type_name.ty);
+            return Err(Diagnostic::error(ret_type_name.span, msg));
+        }
+
+        let paren_left_token = parse_exact!(tokens, ParenLeft);
+        let is_prototyped = !matches!(tokens.peek(), Some(Token::ParenRight(_)));
+        let (params, ellipsis_token) = self.parse_params(tokens)?;
+        let paren_right_token = parse_exact!(tokens, ParenRight);
+
+        let func = Func {
+            name: ident.name,
+            ty: func_type(ret_type_name.ty, &params, ellipsis_token, is_prototyped),
+        };
+        let linkage = self.declare_func(ident, &func, storage_class)?;
+
+        // 関数の宣言のみの場合
+        if !matches!(tokens.peek(), Some(Token::BraceLeft(_))) {
+            let semi_token = parse_exact!(tokens, Semi);
+            return Ok(ExternalDecl::Func(FuncDecl {
+                storage_class,
+                ret_type_name,
+                ident,
+                paren_left_token,
+                params,
+                ellipsis_token,
+                paren_right_token,
+                semi_token,
+                func,
+            }));
+        }
+
+        // main関数はトップレベルのstmtから生成する
+        if ident.name == "main" {
+            return Err(Diagnostic::error(
+                ident.span(),
+                "\"main\" is defined implicitly by top-level statements",
+            ));
+        }
+        if !self.defined_funcs.insert(ident.name) {
+            let msg = format!("redefinition of \"{}\"", ident.name);
+            return Err(Diagnostic::error(ident.span(), msg));
+        }
+
+        // 関数ごとに新しいローカル変数の領域を使う
+        let outer_scopes = std::mem::replace(&mut self.local_scopes, vec![HashMap::new()]);
+        let outer_vars_len = std::mem::replace(&mut self.local_vars_len, 0);
+
+        // 可変長引数を取る場合は、引数レジスタを退避する領域を確保する
+        // 関数の先頭なので、必ず割り当てられる
+        let reg_save_area = ellipsis_token.map(|_| {
+            self.alloc_local_area(REG_SAVE_AREA_SIZE, 16)
+                .expect("register save area fits in an empty frame")
+        });
+        let result = self.parse_func_body(tokens, &params, reg_save_area);
+        let locals_len = self.local_vars_len;
+
+        self.local_scopes = outer_scopes;
+        self.local_vars_len = outer_vars_len;
+        self.func_ctx = None;
+
+        let (param_vars, body) = result?;
+
+        Ok(ExternalDecl::FuncDef(FuncDef {
+            storage_class,
+            ret_type_name,
+            ident,
+            paren_left_token,
+            params,
+            ellipsis_token,
+            paren_right_token,
+            body,
+            func,
+            param_vars,
+            reg_save_area,
+            locals_len,
+            linkage,
+        }))
+    }
+
+    /// > params        = "void" | (param ("," param)* ("," "...")?)?
+    /// > param         = type_name declarator
+    ///
+    /// で表現される記号paramsをパースし、仮引数と "..." を返す関数。
+    /// "(void)" は引数を取らないことを表す。
+    /// "()" は仮引数の型を宣言しないことを表し、呼び出し側で区別する。
+    fn parse_params(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+    ) -> PResult<(Vec<FuncParam<'src>>, Option<Ellipsis>)> {
+        let mut params = Vec::new();
+        if matches!(tokens.peek(), Some(Token::ParenRight(_))) {
+            return Ok((params, None));
+        }
+        let mut tokens2 = *tokens;
+        if let (Some(Token::Void(_)), Some(Token::ParenRight(_))) = (tokens2.next(), tokens2.next())
+        {
+            let _ = tokens.next();
+            return Ok((params, None));
+        }
+
+        loop {
+            // "..." は最後の仮引数の後にだけ書ける
+            if let Some(Token::Ellipsis(token)) = tokens.peek() {
+                if params.is_empty() {
+                    return Err(Diagnostic::error(
+                        token.span(),
+                        "ISO C requires a named parameter before \"...\"",
+                    ));
+                }
+                let _ = tokens.next();
+                return Ok((params, Some(token)));
+            }
+
+            let type_name = self.parse_type_name(tokens)?;
+            let (type_name, ident) = self.parse_declarator(tokens, type_name)?;
+            if type_name.ty.is_aggregate() {
+                let msg = format!("parameter of type \"{}\" is not supported", type_name.ty);
+                return Err(Diagnostic::error(type_name.span, msg));
+            }
+            if type_name.ty == Type::Void {
+                return Err(Diagnostic::error(
+                    type_name.span,
+                    "parameter cannot have type \"void\"",
+                ));
+            }
+            params.push(FuncParam { type_name, ident });
+
+            match tokens.peek() {
+                Some(Token::Comma(_)) => {
+                    let _ = tokens.next();
+                }
+                _ => return Ok((params, None)),
+            }
+        }
+    }
+
+    /// > declarator    = "*" type_qual* declarator
+    /// >     | ident? array_suffix
+    /// >     | "(" "*" ident? ")" "(" params ")"
+    ///
+    /// で表現される記号declaratorをパースし、宣言される型と名前を返す関数。
+    /// "*" は `type_name` へのポインタを表し、後ろの型修飾子はポインタ自体に付く。
+    /// "(" "*" で始まる場合は、`type_name` を返す関数へのポインタの宣言とみなす。
+    /// 名前は省略できる。
+    fn parse_declarator(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        mut type_name: TypeName,
+    ) -> PResult<(TypeName, Option<Ident<'src>>)> {
+        while let Some(Token::Mul(mul_token)) = tokens.peek() {
+            let _ = tokens.next();
+            let mut quals = Qualifiers::default();
+            let mut span = type_name.span.join(mul_token.span());
+            while let Some(token @ (Token::Const(_) | Token::Volatile(_))) = tokens.peek() {
+                let _ = tokens.next();
+                match token {
+                    Token::Const(_) => quals.is_const = true,
+                    _ => quals.is_volatile = true,
+                }
+                span = span.join(token.span());
+            }
+            if type_name.ty == Type::VaList {
+                return Err(Diagnostic::error(
+                    type_name.span,
+                    "pointer to \"va_list\" is not supported",
+                ));
+            }
+            let ptr_ty = PtrType {
+                pointee: type_name.ty,
+                quals: type_name.quals,
+            };
+            type_name = TypeName {
+                ty: Type::Ptr(ptr_ty.intern()),
+                quals,
+                span,
+            };
+        }
+
+        let mut tokens2 = *tokens;
+        match (tokens2.next(), tokens2.next()) {
+            (Some(Token::Ident(ident)), _) => {
+                let _ = tokens.next();
+                return Ok((self.parse_array_suffix(tokens, type_name)?, Some(ident)));
+            }
+            (Some(Token::ParenLeft(_)), Some(Token::Mul(_))) => {
+                let _ = tokens.next();
+                let _ = tokens.next();
+            }
+            _ => return Ok((self.parse_array_suffix(tokens, type_name)?, None)),
+        }
+
+        let ident = match tokens.peek() {
+            Some(Token::Ident(ident)) => {
+                let _ = tokens.next();
+                Some(ident)
+            }
+            _ => None,
+        };
+        let _ = parse_exact!(tokens, ParenRight);
+
+        let _ = parse_exact!(tokens, ParenLeft);
+        let is_prototyped = !matches!(tokens.peek(), Some(Token::ParenRight(_)));
+        let (params, ellipsis_token) = self.parse_params(tokens)?;
+        let paren_right_token = parse_exact!(tokens, ParenRight);
+
+        if type_name.ty == Type::VaList || type_name.ty.is_aggregate() {
+            let msg = format!("function cannot return \"{}\"", type_name.ty);
+            return Err(Diagnostic::error(type_name.span, msg));
+        }
+
+        // 修飾子は戻り値の型に付くもので、関数ポインタ自体には付かない
+        let type_name = TypeName {
+            ty: Type::FuncPtr(func_type(
+                type_name.ty,
+                &params,
+                ellipsis_token,
+                is_prototyped,
+            )),
+            quals: Qualifiers::default(),
+            span: type_name.span.join(paren_right_token.span()),
+        };
+        Ok((type_name, ident))
+    }
+
+    /// > array_suffix  = ("[" equality? "]")*
+    ///
+    /// で表現される記号array_suffixをパースし、`type_name` を要素とする配列の型を返す関数。
+    /// "int a[2][3]" は、要素数3の配列を要素とする要素数2の配列になる。
+    /// 要素数を省略できるのは一番外側の配列だけで、初期化子から推論される。
+    fn parse_array_suffix(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        type_name: TypeName,
+    ) -> PResult<TypeName> {
+        let mut lens = Vec::new();
+        let mut span = type_name.span;
+
+        while let Some(Token::BracketLeft(bracket_left_token)) = tokens.peek() {
+            let _ = tokens.next();
+            let (len, len_span) = match tokens.peek() {
+                Some(Token::BracketRight(token)) if lens.is_empty() => (None, token.span()),
+                Some(Token::BracketRight(_)) => {
+                    return Err(Diagnostic::error(
+                        bracket_left_token.span(),
+                        "array has incomplete element type",
+                    ))
+                }
+                _ => {
+                    let expr = self.parse_equality(tokens)?;
+                    match eval_int_const(&expr)? {
+                        len if len > 0 => (Some(len as usize), expr.span()),
+                        _ => {
+                            return Err(Diagnostic::error(
+                                expr.span(),
+                                "array size must be greater than zero",
+                            ))
+                        }
+                    }
+                }
+            };
+            let bracket_right_token = parse_exact!(tokens, BracketRight);
+            lens.push((len, len_span));
+            span = span.join(bracket_right_token.span());
+        }
+        if !lens.is_empty() && type_name.ty == Type::Void {
+            return Err(Diagnostic::error(
+                type_name.span,
+                "array has incomplete element type \"void\"",
+            ));
+        }
+
+        // 内側の配列から順に組み立てる。
+        // サイズが `isize::MAX` を超える配列は、アドレスの計算で扱えないのでエラーにする
+        let mut ty = type_name.ty;
+        for (len, len_span) in lens.into_iter().rev() {
+            let size = ty.size().checked_mul(len.unwrap_or(0));
+            if size.is_none_or(|size| size > isize::MAX as usize) {
+                return Err(Diagnostic::error(len_span, "array is too large"));
+            }
+            ty = Type::Array(ArrayType { elem: ty, len }.intern());
+        }
+        Ok(TypeName {
+            ty,
+            quals: type_name.quals,
+            span,
+        })
+    }
+
+    /// 名前を持たないdeclaratorをパースし、型名を返す
+    fn parse_abstract_declarator(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        type_name: TypeName,
+    ) -> PResult<TypeName> {
+        match self.parse_declarator(tokens, type_name)? {
+            (type_name, None) => Ok(type_name),
+            (_, Some(ident)) => {
+                let msg = format!("unexpected identifier \"{}\" in type name", ident.name);
+                Err(Diagnostic::error(ident.span(), msg))
+            }
+        }
+    }
+
+    /// 名前の省略できないdeclaratorをパースする
+    fn parse_named_declarator(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        type_name: TypeName,
+    ) -> PResult<(TypeName, Ident<'src>)> {
+        match self.parse_declarator(tokens, type_name)? {
+            (type_name, Some(ident)) => Ok((type_name, ident)),
+            (_, None) => {
+                let found = match tokens.peek() {
+                    Some(token) => token.display(),
+                    None => "EOF",
+                };
+                let msg = format!("expected {} but found {}", Ident::display(), found);
+                Err(tokens.error(msg))
+            }
+        }
+    }
+
+    /// > func_body     = "{" stmt* "}"
+    ///
+    /// で表現される記号func_bodyをパースする関数。
+    /// 仮引数は関数本体の一番外側のスコープで宣言される。
+    fn parse_func_body(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        params: &[FuncParam<'src>],
+        reg_save_area: Option<usize>,
+    ) -> PResult<(Vec<Var<'src>>, StmtBlock<'src>)> {
+        let mut param_vars = Vec::new();
+        for param in params {
+            let ident = param
+                .ident
+                .ok_or_else(|| Diagnostic::error(param.type_name.span, "parameter name omitted"))?;
+            if param.type_name.ty == Type::VaList {
+                return Err(Diagnostic::error(
+                    param.type_name.span,
+                    "parameter of type \"va_list\" is not supported",
+                ));
+            }
+            param_vars.push(self.declare_local_var(
+                ident,
+                param.type_name.ty,
+                param.type_name.quals,
+            )?);
+        }
+
+        // 名前付き引数が使うレジスタの数を数える
+        let float_params = params
+            .iter()
+            .filter(|param| param.type_name.ty.as_float().is_some())
+            .count();
+        let int_params = params.len() - float_params;
+        let stack_params = int_params.saturating_sub(ARG_REGS_LEN)
+            + float_params.saturating_sub(FLOAT_ARG_REGS_LEN);
+
+        self.func_ctx = Some(FuncContext {
+            last_param: params
+                .last()
+                .and_then(|param| param.ident)
+                .map(|ident| ident.name),
+            va_frame: reg_save_area.map(|reg_save_area| VaFrame {
+                gp_offset: 8 * int_params.min(ARG_REGS_LEN),
+                // XMMレジスタは汎用レジスタの後に16 byteずつ退避される
+                fp_offset: 8 * ARG_REGS_LEN + 16 * float_params.min(FLOAT_ARG_REGS_LEN),
+                // return addressと退避したRBPの上に、レジスタに載らなかった引数が積まれている
+                overflow_arg_area: 16 + 8 * stack_params,
+                reg_save_area,
+            }),
+        });
+
+        let brace_left_token = parse_exact!(tokens, BraceLeft);
+        let mut stmts = Vec::new();
+        let brace_right_token = self.parse_block_stmts(tokens, &mut stmts)?;
+
+        Ok((
+            param_vars,
+            StmtBlock {
+                brace_left_token,
+                stmts,
+                brace_right_token,
+            },
+        ))
+    }
+
+    /// > decl_rest     = ("=" initializer)? ";"
+    ///
+    /// で表現される記号decl_restをパースし、グローバル変数を宣言する関数。
+    /// 初期化子はコンパイル時に評価できなければならない。
+    fn parse_global_var_rest(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        storage_class: Option<StorageClass>,
+        type_name: TypeName,
+        ident: Ident<'src>,
+    ) -> PResult<GlobalVar<'src>> {
+        if type_name.ty == Type::VaList {
+            return Err(Diagnostic::error(
+                type_name.span,
+                "\"va_list\" cannot be used for global variables",
+            ));
+        }
+
+        let mut linkage = Linkage::External;
+        let decl = self.parse_init_declarator_rest(
+            tokens,
+            storage_class,
+            type_name,
+            ident,
+            |this, ty| {
+                let (var, var_linkage) =
+                    this.declare_global_var(ident, ty, type_name.quals, storage_class)?;
+                linkage = var_linkage;
+                Ok(var)
+            },
+        )?;
+
+        // "extern" 宣言は定義ではないので初期化できない
+        if let (Some(StorageClass::Extern(_)), Some((assign_token, _))) =
+            (storage_class, &decl.init)
+        {
+            return Err(Diagnostic::error(
+                assign_token.span(),
+                "\"extern\" variable cannot have an initializer",
+            ));
+        }
+        let init_values = eval_static_initializer(&decl)?;
+
+        Ok(GlobalVar {
+            storage_class,
+            decl,
+            init_values,
+            linkage,
+        })
+    }
+
+    /// > init_declarator = declarator ("=" initializer)?
+    ///
+    /// で表現される記号init_declaratorのうち、宣言子より後ろの部分をパースし、
+    /// `declare` で変数を宣言する関数。
+    /// 変数のスコープは初期化子の前から始まるが、要素数を省略した配列は
+    /// 初期化子から型が決まった後に宣言する。
+    /// 初期化子にエラーがあった場合も変数は宣言される。
+    fn parse_init_declarator_rest(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        storage_class: Option<StorageClass>,
+        type_name: TypeName,
+        ident: Ident<'src>,
+        declare: impl FnOnce(&mut Self, Type) -> PResult<Var<'src>>,
+    ) -> PResult<InitDeclarator<'src>> {
+        if type_name.ty == Type::Void {
+            let msg = format!("variable \"{}\" has incomplete type \"void\"", ident.name);
+            return Err(Diagnostic::error(ident.span(), msg));
+        }
+        let is_incomplete = matches!(type_name.ty, Type::Array(ArrayType { len: None, .. }));
+
+        let (var, init) = match tokens.peek() {
+            Some(Token::Assign(assign_token)) if is_incomplete => {
+                let _ = tokens.next();
+                let init = match self.parse_initializer(tokens, type_name.ty) {
+                    Ok(init) => init,
+                    Err(e) => {
+                        // 以降の変数の参照で余計なエラーを出さないように、
+                        // 初期化子が誤っていても変数は宣言しておく
+                        declare(self, type_name.ty)?;
+                        return Err(e);
+                    }
+                };
+                let ty = match &init {
+                    Initializer::List(list) => list.ty,
+                    Initializer::Expr(_) => unreachable!("arrays are initialized by a list"),
+                };
+                (declare(self, ty)?, Some((assign_token, init)))
+            }
+            Some(Token::Assign(assign_token)) => {
+                let _ = tokens.next();
+                let var = declare(self, type_name.ty)?;
+                let init = self.parse_initializer(tokens, type_name.ty)?;
+                (var, Some((assign_token, init)))
+            }
+            // 他の翻訳単位で定義された配列は要素数を省略して宣言できる
+            _ if is_incomplete && !matches!(storage_class, Some(StorageClass::Extern(_))) => {
+                let msg = format!(
+                    "definition of variable \"{}\" with array type needs an explicit size or an initializer",
+                    ident.name
+                );
+                return Err(Diagnostic::error(ident.span(), msg));
+            }
+            _ => (declare(self, type_name.ty)?, None),
+        };
+
+        Ok(InitDeclarator { ident, var, init })
+    }
+
+    /// > initializer   = assign | "{" (init_elem ("," init_elem)* ","?)? "}"
+    ///
+    /// で表現される記号initializerをパースし、`ty` 型の変数の初期化子を返す関数。
+    /// 集成体型の変数は波括弧で囲まれた初期化子で初期化しなければならない。
+    /// スカラ型の変数の初期化式は、1つだけであれば波括弧で囲める。
+    fn parse_initializer(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        ty: Type,
+    ) -> PResult<Initializer<'src>> {
+        if !ty.is_aggregate() {
+            if !matches!(tokens.peek(), Some(Token::BraceLeft(_))) {
+                return Ok(Initializer::Expr(self.parse_assign(tokens)?));
+            }
+            let _ = tokens.next();
+            self.init_depth += 1;
+            // スカラに続く指示子はエラーにする
+            self.parse_designator(tokens, ty)?;
+            let init = self.parse_initializer(tokens, ty)?;
+            if let Some(Token::Comma(_)) = tokens.peek() {
+                let _ = tokens.next();
+            }
+            let _ = parse_exact!(tokens, BraceRight);
+            self.init_depth -= 1;
+            return Ok(init);
+        }
+
+        let brace_left_token = match tokens.peek() {
+            Some(Token::BraceLeft(token)) => token,
+            _ => {
+                let msg = format!("initializer for \"{}\" must be an initializer list", ty);
+                return Err(tokens.error(msg));
+            }
+        };
+        let _ = tokens.next();
+        self.init_depth += 1;
+        let mut elems = Vec::new();
+        let len = self.parse_init_elems(tokens, ty, 0, true, &mut elems)?;
+        let brace_right_token = parse_exact!(tokens, BraceRight);
+        self.init_depth -= 1;
+
+        // 要素数を省略した配列は、初期化された要素の数を要素数とする
+        let ty = match ty {
+            Type::Array(ArrayType { len: None, .. }) if len == 0 => {
+                return Err(Diagnostic::error(
+                    brace_left_token.span().join(brace_right_token.span()),
+                    "zero size arrays are not supported",
+                ))
+            }
+            Type::Array(ArrayType { elem, len: None }) => Type::Array(
+                ArrayType {
+                    elem: *elem,
+                    len: Some(len),
+                }
+                .intern(),
+            ),
+            ty => ty,
+        };
+        elems.sort_by_key(|elem: &InitElem| elem.offset);
+
+        Ok(Initializer::List(InitList {
+            brace_left_token,
+            elems,
+            brace_right_token,
+            ty,
+        }))
+    }
+
+    /// > init_elem     = designation? initializer
+    /// > designation   = designator+ "="
+    ///
+    /// で表現される記号init_elemの並びをパースし、集成体型 `ty` の `offset` の位置にある
+    /// 変数の要素の初期化式を `elems` に追加する関数。
+    /// `braced` が `false` の場合は波括弧の省略された入れ子の集成体とみなし、
+    /// "}" や指示子が現れるか、要素が埋まるまで読む。
+    /// "[1].x = 2" のように連なった指示子は、波括弧の省略された入れ子の集成体の
+    /// 先頭の指示子として読む。
+    /// 初期化された要素の数を返す。
+    fn parse_init_elems(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        ty: Type,
+        offset: usize,
+        braced: bool,
+        elems: &mut Vec<InitElem<'src>>,
+    ) -> PResult<usize> {
+        let len = match ty {
+            Type::Array(ty) => ty.len,
+            Type::Struct(ty) => Some(ty.members.len()),
+            _ => unreachable!("{} is not an aggregate type", ty),
+        };
+        let is_full = |index: usize| len.is_some_and(|len| index >= len);
+        let ends_elems = |token: Option<Token>| {
+            matches!(
+                token,
+                Some(Token::BraceRight(_) | Token::BracketLeft(_) | Token::Dot(_))
+            )
+        };
+
+        let mut index = 0;
+        let mut count = 0;
+        let mut is_first = true;
+        loop {
+            if braced && matches!(tokens.peek(), Some(Token::BraceRight(_))) {
+                break;
+            }
+            // 波括弧が省略されている場合、先頭以外の指示子は外側の初期化子のもの
+            let designated = if braced || is_first {
+                self.parse_designator(tokens, ty)?
+            } else {
+                None
+            };
+            match designated {
+                Some(designated) => index = designated,
+                None if !braced && (is_full(index) || ends_elems(tokens.peek())) => break,
+                None => {}
+            }
+            is_first = false;
+
+            if is_full(index) {
+                let msg = format!("excess elements in initializer for \"{}\"", ty);
+                return Err(tokens.error(msg));
+            }
+            let (elem_ty, elem_offset) = match ty {
+                Type::Array(ty) => (ty.elem, index * ty.elem.size()),
+                Type::Struct(ty) => (ty.members[index].ty, ty.members[index].offset),
+                _ => unreachable!(),
+            };
+            self.parse_init_value(tokens, elem_ty, offset + elem_offset, elems)?;
+            index += 1;
+            count = count.max(index);
+
+            if !matches!(tokens.peek(), Some(Token::Comma(_))) {
+                break;
+            }
+            // 波括弧が省略されている場合、要素が埋まった後の "," は外側の初期化子のもの
+            let mut tokens2 = *tokens;
+            let _ = tokens2.next();
+            if !braced && (is_full(index) || ends_elems(tokens2.peek())) {
+                break;
+            }
+            let _ = tokens.next();
+        }
+
+        Ok(count)
+    }
+
+    /// 集成体型の変数の1つの要素を初期化する初期化子をパースする
+    fn parse_init_value(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        ty: Type,
+        offset: usize,
+        elems: &mut Vec<InitElem<'src>>,
+    ) -> PResult<()> {
+        match tokens.peek() {
+            Some(Token::BraceLeft(_)) => {
+                let _ = tokens.next();
+                self.init_depth += 1;
+                if ty.is_aggregate() {
+                    self.parse_init_elems(tokens, ty, offset, true, elems)?;
+                } else {
+                    // スカラの初期化式も波括弧で囲める
+                    self.parse_init_value(tokens, ty, offset, elems)?;
+                    if let Some(Token::Comma(_)) = tokens.peek() {
+                        let _ = tokens.next();
+                    }
+                }
+                let _ = parse_exact!(tokens, BraceRight);
+                self.init_depth -= 1;
+            }
+            _ if ty.is_aggregate() => {
+                self.parse_init_elems(tokens, ty, offset, false, elems)?;
+            }
+            _ => {
+                // スカラに続く指示子はエラーにする
+                self.parse_designator(tokens, ty)?;
+                let expr = self.parse_assign(tokens)?;
+                // 指示子で同じ要素をもう一度初期化した場合は、後の初期化式で上書きする
+                elems.retain(|elem| elem.offset != offset);
+                elems.push(InitElem { offset, ty, expr });
+            }
+        }
+        Ok(())
+    }
+
+    /// > designator    = "[" equality "]" | "." ident
+    ///
+    /// で表現される記号designatorをパースし、指示された要素の添字を返す関数。
+    /// 指示子が無ければ何も読まずに `None` を返す。
+    /// 指示子が連なっていなければ、続く "=" も読み進める。
+    fn parse_designator(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        ty: Type,
+    ) -> PResult<Option<usize>> {
+        let index = match (tokens.peek(), ty) {
+            (Some(Token::BracketLeft(_)), Type::Array(array_ty)) => {
+                let _ = tokens.next();
+                let expr = self.parse_equality(tokens)?;
+                let index = eval_int_const(&expr)?;
+                let _ = parse_exact!(tokens, BracketRight);
+                if index < 0 || array_ty.len.is_some_and(|len| index >= len as i128) {
+                    let msg = format!("array designator index ({}) exceeds array bounds", index);
+                    return Err(Diagnostic::error(expr.span(), msg));
+                }
+                index as usize
+            }
+            (Some(Token::Dot(_)), Type::Struct(struct_ty)) => {
+                let _ = tokens.next();
+                let ident = parse_exact!(tokens, Ident);
+                match struct_ty
+                    .members
+                    .iter()
+                    .position(|member| member.name == ident.name)
+                {
+                    Some(index) => index,
+                    None => {
+                        let msg = format!("no member named \"{}\" in \"{}\"", ident.name, ty);
+                        return Err(Diagnostic::error(ident.span(), msg));
+                    }
+                }
+            }
+            (Some(token @ (Token::BracketLeft(_) | Token::Dot(_))), _) => {
+                let msg = format!("designator \"{}\" cannot initialize \"{}\"", token, ty);
+                return Err(Diagnostic::error(token.span(), msg));
+            }
+            _ => return Ok(None),
+        };
+        if !matches!(tokens.peek(), Some(Token::BracketLeft(_) | Token::Dot(_))) {
+            let _ = parse_exact!(tokens, Assign);
+        }
+        Ok(Some(index))
+    }
+
+    /// > type_name     = type_qual* type_spec type_qual*
+    /// > type_spec     = ("char" | "short" | "int" | "long" | "signed" | "unsigned")+
+    /// >     | "void" | "float" | "double" | "va_list" | struct_spec
+    /// > type_qual     = "const" | "volatile"
+    ///
+    /// で表現される記号type_nameをパースする関数。
+    /// "unsigned long int" のように複数のキーワードを組み合わせた型名を受け付ける。
+    /// 型修飾子は "int const" のように型指定子の間や後にも書ける。
+    pub fn parse_type_name(&mut self, tokens: &mut TokenStream<'src>) -> PResult<TypeName> {
+        let mut spec = TypeSpec::default();
+        let mut struct_ty: Option<Type> = None;
+        let mut quals = Qualifiers::default();
+        let mut span: Option<Span> = None;
+
+        while let Some(token) = tokens.peek() {
+            if !is_type_name_start(token) {
+                break;
+            }
+
+            let token_span = match token {
+                Token::Const(_) => {
+                    let _ = tokens.next();
+                    quals.is_const = true;
+                    token.span()
+                }
+                Token::Volatile(_) => {
+                    let _ = tokens.next();
+                    quals.is_volatile = true;
+                    token.span()
+                }
+                // 構造体の型は他のキーワードと組み合わせられない
+                Token::Struct(_) if struct_ty.is_none() && spec.is_empty() => {
+                    let type_name = self.parse_struct_spec(tokens)?;
+                    struct_ty = Some(type_name.ty);
+                    type_name.span
+                }
+                _ => {
+                    if struct_ty.is_some() || !spec.add(token) {
+                        let msg =
+                            format!("cannot combine \"{}\" with previous type specifiers", token);
+                        return Err(Diagnostic::error(token.span(), msg));
+                    }
+                    let _ = tokens.next();
+                    token.span()
+                }
+            };
+            span = Some(match span {
+                Some(span) => span.join(token_span),
+                None => token_span,
+            });
+        }
+
+        let Some(span) = span else {
+            return Err(tokens.error("expected type name"));
+        };
+        let ty = match struct_ty {
+            Some(ty) => ty,
+            None if spec.is_empty() => {
+                return Err(Diagnostic::error(span, "type specifier missing"));
+            }
+            None => spec.ty(),
+        };
+        Ok(TypeName { ty, quals, span })
+    }
+
+    /// > struct_spec   = "struct" ident? ("{" (type_name declarator ";")* "}")?
+    ///
+    /// で表現される記号struct_specをパースする関数。
+    /// "{" が続く場合は構造体を定義し、そうでなければ定義済みの構造体をタグで参照する。
+    fn parse_struct_spec(&mut self, tokens: &mut TokenStream<'src>) -> PResult<TypeName> {
+        let struct_token = parse_exact!(tokens, Struct);
+        let tag = match tokens.peek() {
+            Some(Token::Ident(ident)) => {
+                let _ = tokens.next();
+                Some(ident)
+            }
+            _ => None,
+        };
+
+        if !matches!(tokens.peek(), Some(Token::BraceLeft(_))) {
+            let tag = tag.ok_or_else(|| tokens.error("expected identifier or \"{\""))?;
+            return match self.struct_tags.get(tag.name) {
+                Some(ty) => Ok(TypeName {
+                    ty: Type::Struct(ty),
+                    quals: Qualifiers::default(),
+                    span: struct_token.span().join(tag.span()),
+                }),
+                None => {
+                    let msg = format!("use of undefined \"struct {}\"", tag.name);
+                    Err(Diagnostic::error(tag.span(), msg))
+                }
+            };
+        }
+        let _ = tokens.next();
+
+        let mut members: Vec<(String, Type, Qualifiers)> = Vec::new();
+        let brace_right_token = loop {
+            if let Some(Token::BraceRight(token)) = tokens.peek() {
+                let _ = tokens.next();
+                break token;
+            }
+
+            let type_name = self.parse_type_name(tokens)?;
+            let (type_name, ident) = self.parse_named_declarator(tokens, type_name)?;
+            if let Type::Array(ArrayType { len: None, .. }) = type_name.ty {
+                return Err(Diagnostic::error(
+                    type_name.span,
+                    "flexible array member is not supported",
+                ));
+            }
+            if type_name.ty == Type::Void {
+                let msg = format!("field \"{}\" has incomplete type \"void\"", ident.name);
+                return Err(Diagnostic::error(ident.span(), msg));
+            }
+            if members.iter().any(|(name, _, _)| name == ident.name) {
+                let msg = format!("duplicate member \"{}\"", ident.name);
+                return Err(Diagnostic::error(ident.span(), msg));
+            }
+            members.push((ident.name.to_string(), type_name.ty, type_name.quals));
+            let _ = parse_exact!(tokens, Semi);
+        };
+        let span = struct_token.span().join(brace_right_token.span());
+
+        if members.is_empty() {
+            return Err(Diagnostic::error(
+                span,
+                "struct without members is not supported",
+            ));
+        }
+        if let Some(tag) = tag {
+            if self.struct_tags.contains_key(tag.name) {
+                let msg = format!("redefinition of \"struct {}\"", tag.name);
+                return Err(Diagnostic::error(tag.span(), msg));
+            }
+        }
+
+        let ty = StructType::new(tag.map(|tag| tag.name.to_string()), members);
+        if let Some(tag) = tag {
+            self.struct_tags.insert(tag.name, ty);
+        }
+        Ok(TypeName {
+            ty: Type::Struct(ty),
+            quals: Qualifiers::default(),
+            span,
+        })
+    }
+
+    /// > expr          = assign ("," assign)*
+    ///
+    /// で表現される記号exprをパースする関数。
+    /// カンマ演算子は左から順に評価し、右端のオペランドの値を結果とする。
+    /// "i = 0, j = n" のように、代入を並べて1つの式にできる。
+    /// 関数呼び出しの引数や初期化子の区切りの "," と区別するため、
+    /// それらの要素はassignとしてパースする。
+    pub fn parse_expr(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut expr = self.parse_assign(tokens)?;
+
+        while let Some(Token::Comma(comma_token)) = tokens.peek() {
+            let _ = tokens.next();
+            let rhs = self.parse_assign(tokens)?;
+            expr = Expr::Comma(ExprComma {
+                lhs: Box::new(expr),
+                comma_token,
+                rhs: Box::new(rhs),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// > assign        = equality ("=" assign)?
+    ///
+    /// で表現される記号assignをパースする関数。
+    /// 代入は右結合で、代入された後の左辺の値を結果とする。
+    /// 代入の左辺は、変数か配列の要素、構造体のメンバでなければならない。
+    pub fn parse_assign(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let lhs = self.parse_equality(tokens)?;
+
+        match tokens.peek() {
+            Some(Token::Assign(assign_token)) => {
+                let _ = tokens.next();
+                check_assignable(&lhs)?;
+                let rhs = self.parse_assign(tokens)?;
+
+                Ok(Expr::Assign(ExprAssign {
+                    lhs: Box::new(lhs),
+                    assign_token,
+                    rhs: Box::new(rhs),
+                }))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    /// > equality      = relational ("==" relational | "!=" relational)*
+    ///
+    /// で表現される記号equalityをパースする関数。
+    pub fn parse_equality(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut expr = self.parse_relational(tokens)?;
+
+        while let Some(token) = tokens.peek() {
+            let op = match token {
+                Token::Eq(eq_token) => BinOp::Eq(eq_token),
+                Token::Neq(neq_token) => BinOp::Neq(neq_token),
+                _ => break,
+            };
+
+            // このルートに入ることが確定したのでイテレータを進める
+            let _ = tokens.next();
+
+            let rhs = self.parse_relational(tokens)?;
+
+            expr = Expr::BinOp(ExprBinOp {
+                lhs: Box::new(expr),
+                op,
+                rhs: Box::new(rhs),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// > relational    = ("<" add | "<=" add | ">" add | ">=" add)*
+    ///
+    /// で表現される記号relationalをパースする関数。
+    pub fn parse_relational(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut expr = self.parse_add(tokens)?;
+
+        while let Some(token) = tokens.peek() {
+            let (op, reverse) = match token {
+                Token::Lt(token) => (BinOp::Lt(token), false),
+                Token::Lte(token) => (BinOp::Lte(token), false),
+                Token::Gt(token) => (BinOp::Lt(Lt::new(token.pos)), true),
+                Token::Gte(token) => (BinOp::Lte(Lte::new(token.pos)), true),
+                _ => break,
+            };
+
+            // このルートに入ることが確定したのでイテレータを進める
+            let _ = tokens.next();
+
+            let another_expr = self.parse_add(tokens)?;
+
+            let (lhs, rhs) = if reverse {
+                (another_expr, expr)
+            } else {
+                (expr, another_expr)
+            };
+
+            expr = Expr::BinOp(ExprBinOp {
+                lhs: Box::new(lhs),
+                op,
+                rhs: Box::new(rhs),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// > add           = mul ("+" mul | "-" mul)*
+    ///
+    /// で表現される記号addをパースする関数。
+    pub fn parse_add(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut expr = self.parse_mul(tokens)?;
+
+        while let Some(token) = tokens.peek() {
+            let op = match token {
+                Token::Add(token) => BinOp::Add(token),
+                Token::Sub(token) => BinOp::Sub(token),
+                _ => break,
+            };
+
+            // このルートに入ることが確定したのでイテレータを進める
+            let _ = tokens.next();
+
+            let rhs = self.parse_mul(tokens)?;
+
+            expr = Expr::BinOp(ExprBinOp {
+                lhs: Box::new(expr),
+                op,
+                rhs: Box::new(rhs),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// > mul       = cast ("*" cast | "/" cast)*
+    ///
+    /// で表現される記号mulをパースする関数。
+    pub fn parse_mul(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut expr = self.parse_cast(tokens)?;
+
+        while let Some(token) = tokens.peek() {
+            let op = match token {
+                Token::Mul(token) => BinOp::Mul(token),
+                Token::Div(token) => BinOp::Div(token),
+                _ => break,
+            };
+
+            // このルートに入ることが確定したのでイテレータを進める
+            let _ = tokens.next();
+
+            let rhs = self.parse_cast(tokens)?;
+
+            expr = Expr::BinOp(ExprBinOp {
+                lhs: Box::new(expr),
+                op,
+                rhs: Box::new(rhs),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// > cast      = "(" type_name declarator ")" cast | unary
+    ///
+    /// で表現される記号castをパースする関数。
+    /// "(" の次が型名であれば型変換、そうでなければ括弧で囲まれたexprとみなす。
+    pub fn parse_cast(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut tokens2 = *tokens;
+        let paren_left_token = match (tokens2.next(), tokens2.peek()) {
+            (Some(Token::ParenLeft(token)), Some(next)) if is_type_name_start(next) => token,
+            _ => return self.parse_unary(tokens),
+        };
+        let _ = tokens.next();
+
+        let type_name = self.parse_type_name(tokens)?;
+        let type_name = self.parse_abstract_declarator(tokens, type_name)?;
+        let paren_right_token = parse_exact!(tokens, ParenRight);
+        let expr = self.parse_cast(tokens)?;
+
+        Ok(Expr::Cast(ExprCast {
+            paren_left_token,
+            type_name,
+            paren_right_token,
+            expr: Box::new(expr),
+        }))
+    }
+
+    /// > unary     = ("+" | "-" | "&" | "*") cast | postfix
+    ///
+    /// で表現される記号unaryをパースする関数。
+    /// "&" は関数名か、変数やその要素・メンバに使える。
+    /// "*" は関数へのポインタか、void以外を指すポインタに使える。
+    pub fn parse_unary(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let expr = match tokens.peek() {
+            Some(Token::Add(token)) => {
+                let _ = tokens.next();
+                Expr::Unary(ExprUnary {
+                    op: UnaryOp::Plus(token),
+                    expr: Box::new(self.parse_cast(tokens)?),
+                })
+            }
+            Some(Token::Sub(token)) => {
+                let _ = tokens.next();
+                Expr::Unary(ExprUnary {
+                    op: UnaryOp::Neg(token),
+                    expr: Box::new(self.parse_cast(tokens)?),
+                })
+            }
+            Some(Token::Amp(amp_token)) => {
+                let _ = tokens.next();
+                let expr = self.parse_cast(tokens)?;
+                if expr.ty() == Type::VaList {
+                    return Err(Diagnostic::error(
+                        expr.span(),
+                        "taking the address of \"va_list\" is not supported",
+                    ));
+                }
+                if !is_object(&expr) && !expr.is_func_designator() {
+                    let msg = format!(
+                        "cannot take the address of an rvalue of type \"{}\"",
+                        expr.ty()
+                    );
+                    return Err(Diagnostic::error(expr.span(), msg));
+                }
+                Expr::Addr(ExprAddr {
+                    amp_token,
+                    expr: Box::new(expr),
+                })
+            }
+            Some(Token::Mul(star_token)) => {
+                let _ = tokens.next();
+                let expr = self.parse_cast(tokens)?;
+                match expr.ty() {
+                    Type::FuncPtr(_) => {}
+                    Type::Ptr(ty) if ty.pointee != Type::Void => {}
+                    Type::Ptr(_) => {
+                        let msg = format!("cannot dereference \"{}\"", expr.ty());
+                        return Err(Diagnostic::error(expr.span(), msg));
+                    }
+                    _ => {
+                        let msg = format!(
+                            "indirection requires pointer operand (\"{}\" invalid)",
+                            expr.ty()
+                        );
+                        return Err(Diagnostic::error(expr.span(), msg));
+                    }
+                }
+                Expr::Deref(ExprDeref {
+                    star_token,
+                    expr: Box::new(expr),
+                })
+            }
+            _ => self.parse_postfix(tokens)?,
+        };
+
+        Ok(expr)
+    }
+
+    /// > postfix   = primary ( "(" call_params? ")" | "[" expr "]" | "." ident )*
+    ///
+    /// で表現される記号postfixをパースする関数。
+    /// 関数へのポインタ型の式であれば呼び出せる。
+    pub fn parse_postfix(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        let mut expr = self.parse_primary(tokens)?;
+
+        loop {
+            expr = match tokens.peek() {
+                Some(Token::ParenLeft(paren_left_token)) => {
+                    let _ = tokens.next();
+                    self.parse_call_rest(tokens, expr, paren_left_token)?
+                }
+                Some(Token::BracketLeft(bracket_left_token)) => {
+                    let _ = tokens.next();
+                    let index = self.parse_expr(tokens)?;
+                    let bracket_right_token = parse_exact!(tokens, BracketRight);
+
+                    if !matches!(expr.ty(), Type::Array(_)) {
+                        return Err(Diagnostic::error(
+                            bracket_left_token.span(),
+                            "subscripted value is not an array",
+                        ));
+                    }
+                    if index.ty().as_int().is_none() {
+                        return Err(Diagnostic::error(
+                            index.span(),
+                            "array subscript is not an integer",
+                        ));
+                    }
+
+                    Expr::Index(ExprIndex {
+                        base: Box::new(expr),
+                        bracket_left_token,
+                        index: Box::new(index),
+                        bracket_right_token,
+                    })
+                }
+                Some(Token::Dot(dot_token)) => {
+                    let _ = tokens.next();
+                    let ident = parse_exact!(tokens, Ident);
+
+                    let struct_ty = match expr.ty() {
+                        Type::Struct(ty) => ty,
+                        ty => {
+                            let msg =
+                                format!("member reference base type \"{}\" is not a structure", ty);
+                            return Err(Diagnostic::error(dot_token.span(), msg));
+                        }
+                    };
+                    let member = struct_ty.member(ident.name).ok_or_else(|| {
+                        let msg =
+                            format!("no member named \"{}\" in \"{}\"", ident.name, expr.ty());
+                        Diagnostic::error(ident.span(), msg)
+                    })?;
+
+                    Expr::Member(ExprMember {
+                        base: Box::new(expr),
+                        dot_token,
+                        ident,
+                        member,
+                    })
+                }
+                _ => return Ok(expr),
+            };
+        }
+    }
+
+    /// 関数呼び出しの "(" より後ろの部分をパースする。
+    /// `callee` が関数へのポインタ型でなかったり、引数の数が合わない場合はエラーになる。
+    fn parse_call_rest(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        callee: Expr<'src>,
+        paren_left_token: ParenLeft,
+    ) -> PResult<Expr<'src>> {
+        let (paren_right_token, params) = match tokens.peek() {
+            Some(Token::ParenRight(token)) => {
+                let _ = tokens.next();
+                (token, Vec::new())
+            }
+            Some(_) => {
+                let params = self.parse_call_params(tokens)?;
+                let paren_right_token = parse_exact!(tokens, ParenRight);
+                (paren_right_token, params)
+            }
+            None => {
+                return Err(Diagnostic::error(
+                    tokens.pos(),
+                    "expected params or \")\" but found EOF",
+                ))
+            }
+        };
+
+        let func_ty = match callee.ty() {
+            Type::FuncPtr(func_ty) => func_ty,
+            ty => {
+                let msg = match &callee {
+                    Expr::Ident(ExprIdent { ident, .. }) => {
+                        format!("called object \"{}\" is not a function", ident.name)
+                    }
+                    _ => format!(
+                        "called object type \"{}\" is not a function or function pointer",
+                        ty
+                    ),
+                };
+                return Err(Diagnostic::error(paren_left_token.span(), msg));
+            }
+        };
+        if params.len() < func_ty.param_tys.len()
+            || (!func_ty.is_variadic && params.len() > func_ty.param_tys.len())
+        {
+            let msg = format!(
+                "too {} arguments to function call, expected {}{}, have {}",
+                if params.len() > func_ty.param_tys.len() {
+                    "many"
+                } else {
+                    "few"
+                },
+                if func_ty.is_variadic { "at least " } else { "" },
+                func_ty.param_tys.len(),
+                params.len()
+            );
+            return Err(Diagnostic::error(paren_left_token.span(), msg));
+        }
+
+        Ok(Expr::Call(ExprCall {
+            callee: Box::new(callee),
+            paren_left_token,
+            params,
+            paren_right_token,
+        }))
+    }
+
+    /// > primary   = num
+    /// >     | va
+    /// >     | ident
+    /// >     | "(" expr ")"
+    ///
+    /// で表現される記号primaryをパースする関数。
+    /// 宣言されていない識別子は、直後が "(" であれば関数、
+    /// そうでなければ変数として暗黙的に宣言する。
+    pub fn parse_primary(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Expr<'src>> {
+        // エラーから復帰できるよう、不正なトークンは読み進めずに残しておく
+        let before = *tokens;
+
+        match tokens.next() {
+            Some(Token::Num(token)) => Ok(Expr::Num(token)),
+            Some(Token::Ident(ident)) => {
+                let is_call = matches!(tokens.peek(), Some(Token::ParenLeft(_)));
+
+                // 可変長引数を扱う組み込み関数の場合
+                if is_call && matches!(ident.name, "va_start" | "va_arg" | "va_end") {
+                    return self.parse_va(tokens, ident);
+                }
+
+                if let Some(var) = self.find_var(ident.name) {
+                    return Ok(Expr::Ident(ExprIdent { var, ident }));
+                }
+                let func = self.funcs.get(ident.name).copied();
+                if func.is_some() || is_call {
+                    return Ok(Expr::Func(ExprFunc { func, ident }));
+                }
+
+                let var = self.lookup_var(ident)?;
+                Ok(Expr::Ident(ExprIdent { var, ident }))
+            }
+            Some(Token::ParenLeft(paren_left_token)) => {
+                let expr = self.parse_expr(tokens)?;
+                let paren_right_token = parse_exact!(tokens, ParenRight);
+
+                Ok(Expr::Paren(ExprParen {
+                    paren_left_token,
+                    expr: Box::new(expr),
+                    paren_right_token,
+                }))
+            }
+            Some(token) => {
+                *tokens = before;
+                Err(Diagnostic::error(
+                    token.span(),
+                    "expected number, ident or \"(\"",
+                ))
+            }
+            None => Err(Diagnostic::error(
+                tokens.pos(),
+                "expected number, ident or \"(\"",
+            )),
+        }
+    }
+
+    /// > va            = "va_start" "(" assign "," ident ")"
+    /// >     | "va_arg" "(" assign "," type_name ")"
+    /// >     | "va_end" "(" assign ")"
+    ///
+    /// で表現される記号vaをパースする関数。
+    /// `ident` は読み進めた後の "va_start" などの識別子。
+    fn parse_va(
+        &mut self,
+        tokens: &mut TokenStream<'src>,
+        ident: Ident<'src>,
+    ) -> PResult<Expr<'src>> {
+        let paren_left_token = parse_exact!(tokens, ParenLeft);
+
+        let ap = self.parse_assign(tokens)?;
+        if ap.ty() != Type::VaList {
+            let msg = format!(
+                "first argument to \"{}\" must be \"va_list\", found \"{}\"",
+                ident.name,
+                ap.ty()
+            );
+            return Err(Diagnostic::error(ap.span(), msg));
+        }
+
+        let op = match ident.name {
+            "va_start" => {
+                let _ = parse_exact!(tokens, Comma);
+                let last = parse_exact!(tokens, Ident);
+
+                let (last_param, frame) = match &self.func_ctx {
+                    Some(FuncContext {
+                        last_param,
+                        va_frame: Some(frame),
+                    }) => (*last_param, *frame),
+                    _ => {
+                        return Err(Diagnostic::error(
+                            ident.span(),
+                            "\"va_start\" used in function with fixed arguments",
+                        ))
+                    }
+                };
+                if last_param != Some(last.name) {
+                    return Err(Diagnostic::error(
+                        last.span(),
+                        "second argument to \"va_start\" is not the last named parameter",
+                    ));
+                }
+
+                VaOp::Start { last, frame }
+            }
+            "va_arg" => {
+                let _ = parse_exact!(tokens, Comma);
+                let type_name = self.parse_type_name(tokens)?;
+                let type_name = self.parse_abstract_declarator(tokens, type_name)?;
+                if matches!(type_name.ty, Type::Void | Type::VaList) || type_name.ty.is_aggregate()
+                {
+                    let msg = format!("\"va_arg\" cannot read a \"{}\"", type_name.ty);
+                    return Err(Diagnostic::error(type_name.span, msg));
+                }
+                // float の可変長引数は double に拡張されて渡される
+                if type_name.ty == Type::Float(FloatType::Float) {
+                    return Err(Diagnostic::error(
+                        type_name.span,
+                        "\"va_arg\" cannot read a \"float\" promoted to \"double\"",
+                    ));
+                }
+
+                VaOp::Arg(type_name)
+            }
+            _ => VaOp::End,
+        };
+
+        let paren_right_token = parse_exact!(tokens, ParenRight);
+
+        Ok(Expr::Va(ExprVa {
+            op,
+            ident,
+            paren_left_token,
+            ap: Box::new(ap),
+            paren_right_token,
+        }))
+    }
+
+    // > call_params = assign ( "," assign )*
+    fn parse_call_params(&mut self, tokens: &mut TokenStream<'src>) -> PResult<Vec<Expr<'src>>> {
+        let mut params = Vec::new();
+        params.push(self.parse_assign(tokens)?);
+
+        while let Some(Token::Comma(_)) = tokens.peek() {
+            let _ = tokens.next();
+            params.push(self.parse_assign(tokens)?);
+        }
+
+        Ok(params)
+    }
+}
+
+/// トップレベルの宣言
+enum ExternalDecl<'src> {
+    /// "," で区切られた宣言子ごとのグローバル変数
+    Vars(Vec<GlobalVar<'src>>),
+    Func(FuncDecl<'src>),
+    FuncDef(FuncDef<'src>),
+    /// 構造体の型だけを定義する宣言
+    Type,
+}
+
+/// パース中の関数定義の情報
+struct FuncContext<'src> {
+    /// 最後の名前付き仮引数の名前
+    last_param: Option<&'src str>,
+    /// 可変長引数を取る場合の `va_start` のための情報
+    va_frame: Option<VaFrame>,
+}
+
+/// 静的な領域に配置される変数の初期化子を評価する。
+/// 初期化子が無い場合や、初期化子に含まれない部分は0で初期化される。
+fn eval_static_initializer<'src>(decl: &InitDeclarator<'src>) -> PResult<Vec<StaticInit<'src>>> {
+    match &decl.init {
+        Some((_, init)) => eval_initializer(init, decl.var.ty).map_err(|expr| {
+            Diagnostic::error(
+                expr.span(),
+                "initializer element is not a compile-time constant",
+            )
+        }),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 配列の要素数や指示子の添字に使う整数定数式を評価する
+fn eval_int_const(expr: &Expr) -> PResult<i128> {
+    match (expr.ty(), eval_const(expr)) {
+        (Type::Int(ty), Some(value)) => Ok(ty.to_i128(value)),
+        (Type::Int(_), None) => Err(Diagnostic::error(
+            expr.span(),
+            "expression is not an integer constant expression",
+        )),
+        (ty, _) => {
+            let msg = format!(
+                "integer constant expression must have integer type, not \"{}\"",
+                ty
+            );
+            Err(Diagnostic::error(expr.span(), msg))
+        }
+    }
+}
+
+/// 代入の左辺が書き換え可能な変数や要素を表すかどうかを検査する。
+/// "const" の付いた変数やメンバ、"const" の付いた変数の要素への代入はエラーになる。
+fn check_assignable(lhs: &Expr) -> PResult<()> {
+    match lhs {
+        Expr::Ident(ExprIdent { var, ident }) if var.quals.is_const => {
+            let msg = format!(
+                "cannot assign to variable \"{}\" with const-qualified type \"{}\"",
+                ident.name,
+                var.ty.qualified(var.quals)
+            );
+            Err(Diagnostic::error(ident.span(), msg))
+        }
+        Expr::Ident(_) => Ok(()),
+        Expr::Paren(ExprParen { expr, .. }) => check_assignable(expr),
+        // 配列の要素は、配列の変数が const であれば代入できない
+        Expr::Index(ExprIndex { base, .. }) => check_assignable(base),
+        Expr::Member(ExprMember { ident, member, .. }) if member.quals.is_const => {
+            let msg = format!(
+                "cannot assign to member \"{}\" with const-qualified type \"{}\"",
+                ident.name,
+                member.ty.qualified(member.quals)
+            );
+            Err(Diagnostic::error(ident.span(), msg))
+        }
+        Expr::Member(ExprMember { base, .. }) => check_assignable(base),
+        Expr::Deref(ExprDeref { expr, .. }) => match expr.ty() {
+            Type::Ptr(ty) if ty.quals.is_const => {
+                let msg = format!(
+                    "cannot assign through pointer to const-qualified type \"{}\"",
+                    expr.ty()
+                );
+                Err(Diagnostic::error(lhs.span(), msg))
+            }
+            Type::Ptr(_) => Ok(()),
+            _ => Err(Diagnostic::error(
+                lhs.span(),
+                "expression is not assignable",
+            )),
+        },
+        _ => Err(Diagnostic::error(
+            lhs.span(),
+            "expression is not assignable",
+        )),
+    }
+}
+
+/// 宣言子の無い、構造体の型だけを定義する宣言であれば ";" を読み進めて返す
+fn parse_type_decl(tokens: &mut TokenStream<'_>, type_name: TypeName) -> Option<StmtTypeDecl> {
+    match (tokens.peek(), type_name.ty) {
+        (Some(Token::Semi(semi_token)), Type::Struct(_)) => {
+            let _ = tokens.next();
+            Some(StmtTypeDecl {
+                type_name,
+                semi_token,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 仮引数と返り値の型から関数の型を作る。
+/// `is_prototyped` が `false` の場合は、仮引数の型を宣言しない関数の型になる。
+fn func_type(
+    ret_ty: Type,
+    params: &[FuncParam],
+    ellipsis_token: Option<Ellipsis>,
+    is_prototyped: bool,
+) -> &'static FuncType {
+    if !is_prototyped {
+        return FuncType::unprototyped(ret_ty);
+    }
+    FuncType {
+        ret_ty,
+        param_tys: params.iter().map(|param| param.type_name.ty).collect(),
+        is_variadic: ellipsis_token.is_some(),
+    }
+    .intern()
+}
+
+/// 変数や、その要素・メンバ、ポインタの指す先を表すlvalueかどうか
+fn is_object(expr: &Expr) -> bool {
+    match expr {
+        Expr::Ident(_) | Expr::Index(_) | Expr::Member(_) => true,
+        Expr::Deref(ExprDeref { expr, .. }) => matches!(expr.ty(), Type::Ptr(_)),
+        Expr::Paren(ExprParen { expr, .. }) => is_object(expr),
+        _ => false,
+    }
+}
+
+/// 記憶域クラス指定子かどうか
+fn is_storage_class(token: Token) -> bool {
+    matches!(token, Token::Extern(_) | Token::Static(_))
+}
+
+/// 型名の先頭になりうるトークンかどうか
+fn is_type_name_start(token: Token) -> bool {
+    matches!(
+        token,
+        Token::Char(_)
+            | Token::Short(_)
+            | Token::Int(_)
+            | Token::Long(_)
+            | Token::Signed(_)
+            | Token::Unsigned(_)
+            | Token::Float(_)
+            | Token::Double(_)
+            | Token::Struct(_)
+            | Token::Const(_)
+            | Token::Volatile(_)
+            | Token::Void(_)
+    ) || matches!(token, Token::Ident(ident) if ident.name == VA_LIST)
+}
+
+/// 型名を構成するキーワードの出現回数
+#[derive(Default)]
+struct TypeSpec {
+    void: usize,
+    char: usize,
+    short: usize,
+    int: usize,
+    long: usize,
+    signed: usize,
+    unsigned: usize,
+    float: usize,
+    double: usize,
+    va_list: usize,
+}
+
+impl TypeSpec {
+    /// キーワードを追加する。
+    /// 組み合わせとして不正になる場合は `false` を返す。
+    fn add(&mut self, token: Token) -> bool {
+        match token {
+            Token::Void(_) => self.void += 1,
+            Token::Char(_) => self.char += 1,
+            Token::Short(_) => self.short += 1,
+            Token::Int(_) => self.int += 1,
+            Token::Long(_) => self.long += 1,
+            Token::Signed(_) => self.signed += 1,
+            Token::Unsigned(_) => self.unsigned += 1,
+            Token::Float(_) => self.float += 1,
+            Token::Double(_) => self.double += 1,
+            Token::Ident(ident) if ident.name == VA_LIST => self.va_list += 1,
+            _ => return false,
+        }
+
+        // void と va_list と浮動小数点数型は他のキーワードと組み合わせられない
+        if self.void + self.va_list + self.float + self.double > 0 {
+            return self.void
+                + self.va_list
+                + self.float
+                + self.double
+                + self.char
+                + self.short
+                + self.int
+                + self.long
+                + self.signed
+                + self.unsigned
+                == 1;
+        }
+
+        self.char <= 1
+            && self.short <= 1
+            && self.int <= 1
+            && self.long <= 2
+            && self.signed + self.unsigned <= 1
+            && (self.char == 0 || self.short + self.int + self.long == 0)
+            && (self.short == 0 || self.long == 0)
+    }
+
+    /// キーワードが1つも追加されていなければ `true` を返す
+    fn is_empty(&self) -> bool {
+        self.void
+            + self.char
+            + self.short
+            + self.int
+            + self.long
+            + self.signed
+            + self.unsigned
+            + self.float
+            + self.double
+            + self.va_list
+            == 0
+    }
+
+    fn ty(&self) -> Type {
+        if self.void > 0 {
+            return Type::Void;
+        }
+        if self.va_list > 0 {
+            return Type::VaList;
+        }
+        if self.float > 0 {
+            return Type::Float(FloatType::Float);
+        }
+        if self.double > 0 {
+            return Type::Float(FloatType::Double);
+        }
+
+        let ty = if self.char > 0 {
+            IntType::Char
+        } else if self.short > 0 {
+            IntType::Short
+        } else if self.long > 0 {
+            IntType::Long
+        } else {
+            IntType::Int
+        };
+
+        if self.unsigned > 0 {
+            Type::Int(ty.to_unsigned())
+        } else {
+            Type::Int(ty)
+        }
+    }
+}
+
+/// 構文エラーの後、次のstmtの先頭と思われる位置までトークンを読み飛ばす。
+///
+/// - ";" が現れたら、それを読み飛ばして終了する
+/// - 読み飛ばしている途中に開いた "{" に対応する "}" が現れたら、
+///   それを読み飛ばして終了する
+/// - 外側のブロックを閉じる "}" が現れたら、それを読み飛ばさずに終了する
+///
+/// 初期化子の中でエラーが起きた場合は、`init_depth` 個の閉じられていない "{" の中から読み飛ばし始める。
+/// 初期化子の "}" ではstmtは終わらないので、波括弧の外の ";" まで読み飛ばす。
+fn synchronize(tokens: &mut TokenStream<'_>, init_depth: usize) {
+    let in_init = init_depth > 0;
+    let mut depth = init_depth;
+
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::Semi(_) if depth == 0 => {
+                let _ = tokens.next();
+                return;
+            }
+            Token::BraceLeft(_) => depth += 1,
+            Token::BraceRight(_) if depth == 0 => return,
+            Token::BraceRight(_) => {
+                depth -= 1;
+                if depth == 0 && !in_init {
+                    let _ = tokens.next();
+                    return;
+                }
+            }
+            _ => {}
+        }
+        let _ = tokens.next();
+    }
+}
+
+impl<'src> Default for Parser<'src> {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{tokenize, SourceMap, TokenStream};
+
+    fn count_errors(input: &str, parser: Parser) -> usize {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        let mut parser = parser;
+        match parser.parse(&mut TokenStream::new(&tokens)) {
+            Ok(_) => 0,
+            Err(e) => e.diagnostics.len(),
+        }
+    }
+
+    #[test]
+    fn error_recovery_test() {
+        assert_eq!(count_errors("a = 1; return a;", Parser::new()), 0);
+        assert_eq!(
+            count_errors("a = (1 + 2; b = 3 +; return 1;", Parser::new()),
+            2
+        );
+        assert_eq!(count_errors("{ a = ; b = 2 } c = 1;", Parser::new()), 2);
+        assert_eq!(count_errors("if (a b) { x = 1; } y = 1;", Parser::new()), 1);
+        assert_eq!(count_errors("} 1;", Parser::new()), 1);
+        assert_eq!(count_errors("{ 1;", Parser::new()), 1);
+        // 初期化子の "}" でブロックを閉じない
+        assert_eq!(
+            count_errors(
+                "struct P { int x; }; { struct P p = {.z = 1}; p.x = 1; } 1;",
+                Parser::new()
+            ),
+            1
+        );
+        assert_eq!(
+            count_errors("{ int a[2] = {1, 2, 3}; a[0]; }", Parser::new()),
+            1
+        );
+        assert_eq!(
+            count_errors(
+                "{ int a[2][2] = {{1, 2, 3}, {4}}; a[0][0] = 1; }",
+                Parser::new()
+            ),
+            1
+        );
+        assert_eq!(
+            count_errors("{ int a[2] = {1, +}; { b = ; } }", Parser::new()),
+            2
+        );
+    }
+
+    fn spans(input: &str) -> Vec<(u32, u32)> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        Parser::new()
+            .parse(&mut TokenStream::new(&tokens))
+            .unwrap()
+            .stmts
+            .iter()
+            .map(|stmt| (stmt.span().start, stmt.span().end))
+            .collect()
+    }
+
+    #[test]
+    fn span_test() {
+        assert_eq!(spans("42;"), vec![(0, 3)]);
+        assert_eq!(spans(" a = 1 + 2 * 3; "), vec![(1, 15)]);
+        assert_eq!(spans("a > -b;"), vec![(0, 7)]);
+        assert_eq!(spans("foo(1, 2);"), vec![(0, 10)]);
+        assert_eq!(spans("if (a) { b; } else c;"), vec![(0, 21)]);
+        assert_eq!(spans("while (1) {} return 42;"), vec![(0, 12), (13, 23)]);
+        assert_eq!(spans("{ unsigned char c = 1; }"), vec![(0, 24)]);
+        assert_eq!(spans("(char)300;"), vec![(0, 10)]);
+        assert_eq!(spans("-(unsigned)(a) * 2;"), vec![(0, 19)]);
+    }
+
+    #[test]
+    fn decl_error_test() {
+        assert_eq!(count_errors("int a; int a;", Parser::new()), 1);
+        assert_eq!(count_errors("{ int a; long a; }", Parser::new()), 1);
+        assert_eq!(count_errors("int a; { int a; }", Parser::new()), 0);
+        assert_eq!(count_errors("int a = b;", Parser::new()), 1);
+        assert_eq!(count_errors("int a = 1 + 2 * 3;", Parser::new()), 0);
+        assert_eq!(count_errors("unsigned double a;", Parser::new()), 1);
+        assert_eq!(count_errors("(int 1;", Parser::new()), 1);
+        assert_eq!(count_errors("(int);", Parser::new()), 1);
+        assert_eq!(count_errors("extern int a; int a = 1;", Parser::new()), 0);
+        assert_eq!(
+            count_errors("int a; extern int a; extern int a;", Parser::new()),
+            0
+        );
+        assert_eq!(count_errors("extern int a; long a;", Parser::new()), 1);
+        assert_eq!(count_errors("extern int a = 1;", Parser::new()), 1);
+    }
+
+    #[test]
+    fn func_decl_test() {
+        assert_eq!(
+            count_errors("int f(int a, long); f(1, 2);", Parser::new()),
+            0
+        );
+        assert_eq!(count_errors("extern int f(); f();", Parser::new()), 0);
+        assert_eq!(count_errors("int f(int); int f(int a);", Parser::new()), 0);
+        assert_eq!(count_errors("int f(int); int f(long);", Parser::new()), 1);
+        assert_eq!(count_errors("int f(int); long f(int);", Parser::new()), 1);
+        assert_eq!(count_errors("int f; int f();", Parser::new()), 1);
+        assert_eq!(count_errors("int f(); int f;", Parser::new()), 1);
+        assert_eq!(count_errors("int f(int, ); f(1);", Parser::new()), 1);
+        assert_eq!(count_errors("int a; a(1);", Parser::new()), 1);
+        assert_eq!(
+            count_errors("int f(int, ...); f(1, 2, 3);", Parser::new()),
+            0
+        );
+        assert_eq!(count_errors("int f(int, ...); f();", Parser::new()), 1);
+        assert_eq!(count_errors("int f(...);", Parser::new()), 1);
+        assert_eq!(count_errors("int f(int, ..., int);", Parser::new()), 1);
+        assert_eq!(
+            count_errors("int f(int, ...); int f(int);", Parser::new()),
+            1
+        );
+
+        // "()" の宣言は、拡張で型の変わらない仮引数を取る関数の宣言と両立する
+        assert_eq!(
+            count_errors("int f(); int f(int a, double d);", Parser::new()),
+            0
+        );
+        assert_eq!(count_errors("int f(long); int f(); f();", Parser::new()), 1);
+        assert_eq!(count_errors("int f(); int f(char);", Parser::new()), 1);
+        assert_eq!(count_errors("int f(); int f(float);", Parser::new()), 1);
+        assert_eq!(count_errors("int f(); int f(int, ...);", Parser::new()), 1);
+        assert_eq!(count_errors("int f(); long f();", Parser::new()), 1);
+    }
+
+    fn first_error(input: &str) -> (u32, u32, String) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        let e = Parser::new()
+            .parse(&mut TokenStream::new(&tokens))
+            .unwrap_err();
+        let diagnostic = &e.diagnostics[0];
+        (
+            diagnostic.span.start,
+            diagnostic.span.end,
+            diagnostic.msg.clone(),
+        )
+    }
+
+    #[test]
+    fn call_args_test() {
+        assert_eq!(
+            first_error("int f(int a, int b); f(1);"),
+            (
+                22,
+                23,
+                "too few arguments to function call, expected 2, have 1".to_string()
+            )
+        );
+        assert_eq!(
+            first_error("int f(void); 1 + f(1, 2);"),
+            (
+                18,
+                19,
+                "too many arguments to function call, expected 0, have 2".to_string()
+            )
+        );
+        // "()" で宣言された関数は任意の引数で呼び出せる
+        assert_eq!(count_errors("int f(); f(1, 2, 3); f();", Parser::new()), 0);
+        assert_eq!(
+            count_errors("int (*p)(); p(1); p = 0; p(1.5, 2);", Parser::new()),
+            0
+        );
+        assert_eq!(
+            first_error("int f(int, int, ...); f(1);").2,
+            "too few arguments to function call, expected at least 2, have 1"
+        );
+    }
+
+    #[test]
+    fn func_def_test() {
+        assert_eq!(
+            count_errors("int f(int a) { return a; } f(1);", Parser::new()),
+            0
+        );
+        assert_eq!(
+            count_errors("int f(int a); int f(int a) { return a; }", Parser::new()),
+            0
+        );
+        assert_eq!(
+            first_error("int f() { return 1; } int f() { return 2; }"),
+            (26, 27, "redefinition of \"f\"".to_string())
+        );
+        assert_eq!(
+            first_error("int main() { return 0; }").2,
+            "\"main\" is defined implicitly by top-level statements"
+        );
+        assert_eq!(
+            first_error("int f(int) { return 0; }").2,
+            "parameter name omitted"
+        );
+        // 仮引数は関数の外からは見えない
+        assert_eq!(
+            count_errors("int f(int a) { return a; } int a;", Parser::new()),
+            0
+        );
+    }
+
+    #[test]
+    fn static_test() {
+        assert_eq!(
+            count_errors(
+                "static int x; static int f(); static int f() { return x; } int g() { static int x = 1; return x; }",
+                Parser::new()
+            ),
+            0
+        );
+        // 関数は先に宣言された内部結合を引き継ぐ
+        assert_eq!(
+            count_errors("static int f(); int f() { return 0; }", Parser::new()),
+            0
+        );
+        assert_eq!(
+            count_errors("static int x; extern int x;", Parser::new()),
+            0
+        );
+        assert_eq!(
+            first_error("int f(); static int f();").2,
+            "static declaration of \"f\" follows non-static declaration"
+        );
+        assert_eq!(
+            first_error("extern int x; static int x;").2,
+            "static declaration of \"x\" follows non-static declaration"
+        );
+        assert_eq!(
+            first_error("static int x; int x;").2,
+            "redefinition of \"x\""
+        );
+        assert_eq!(
+            first_error("int f() { long a; static int x = a; return x; }").2,
+            "initializer element is not a compile-time constant"
+        );
+        // 関数のアドレスは定数だが、関数へのポインタの変数の値は定数でない
+        assert_eq!(
+            count_errors("int g(int); int (*p)(int) = g;", Parser::new()),
+            0
+        );
+        assert_eq!(
+            first_error("int g(int); int (*p)(int) = g; int (*q)(int) = p;").2,
+            "initializer element is not a compile-time constant"
+        );
+        assert_eq!(
+            first_error("int f() { static int x; static int x; return x; }").2,
+            "redefinition of \"x\""
+        );
+    }
+
+    #[test]
+    fn va_test() {
+        assert_eq!(
+            count_errors(
+                "int f(int n, ...) { va_list ap; va_start(ap, n); n = va_arg(ap, int); va_end(ap); return n; }",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("int f(int n) { va_list ap; va_start(ap, n); return 0; }").2,
+            "\"va_start\" used in function with fixed arguments"
+        );
+        assert_eq!(
+            first_error("int f(int a, int b, ...) { va_list ap; va_start(ap, a); return 0; }").2,
+            "second argument to \"va_start\" is not the last named parameter"
+        );
+        assert_eq!(
+            first_error("int f(int n, ...) { int x; va_start(x, n); return 0; }").2,
+            "first argument to \"va_start\" must be \"va_list\", found \"int\""
+        );
+        assert_eq!(
+            first_error("int f(int n, ...) { va_list ap; va_arg(ap, va_list); return 0; }").2,
+            "\"va_arg\" cannot read a \"va_list\""
+        );
+        assert_eq!(
+            first_error("va_list ap;").2,
+            "\"va_list\" cannot be used for global variables"
+        );
+    }
+
+    #[test]
+    fn float_type_test() {
+        assert_eq!(
+            count_errors("float f; double d; d = f + 1.5;", Parser::new()),
+            0
+        );
+        assert_eq!(
+            first_error("long double d;").2,
+            "cannot combine \"double\" with previous type specifiers"
+        );
+        assert_eq!(
+            first_error("unsigned float f;").2,
+            "cannot combine \"float\" with previous type specifiers"
+        );
+        assert_eq!(
+            first_error("int f(int n, ...) { va_list ap; va_arg(ap, float); return 0; }").2,
+            "\"va_arg\" cannot read a \"float\" promoted to \"double\""
+        );
+    }
+
+    #[test]
+    fn func_ptr_test() {
+        assert_eq!(
+            count_errors(
+                "int f(int a) { return a; } int (*p)(int); p = f; p = &f; (*p)(1); p(2);",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            count_errors(
+                "int apply(int (*)(int, long), int); long (*g)(double, ...);",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("int (*p)(int, int); p(1);").2,
+            "too few arguments to function call, expected 2, have 1"
+        );
+        assert_eq!(
+            first_error("int x; x(1);"),
+            (8, 9, "called object \"x\" is not a function".to_string())
+        );
+        assert_eq!(
+            first_error("int f(); f()(1);").2,
+            "called object type \"int\" is not a function or function pointer"
+        );
+        assert_eq!(
+            first_error("int x; &(x + 1);").2,
+            "cannot take the address of an rvalue of type \"int\""
+        );
+        assert_eq!(
+            first_error("long x; *x;").2,
+            "indirection requires pointer operand (\"long\" invalid)"
+        );
+        assert_eq!(
+            first_error("int (*)(int);").2,
+            "expected identifier but found ;"
+        );
+        assert_eq!(
+            first_error("long x; (int (*p)(int))x;").2,
+            "unexpected identifier \"p\" in type name"
+        );
+    }
+
+    #[test]
+    fn ptr_test() {
+        assert_eq!(
+            count_errors(
+                "int x, *p = &x, **pp = &p; struct S { int *m; } s; int *f(int *a, char **); \
+                 *p = 1; **pp = *p; *s.m = 2; *&x = 3; p = (int *)0; p = &*p;",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("int x; void *v = &x; *v;"),
+            (22, 23, "cannot dereference \"void *\"".to_string())
+        );
+        assert_eq!(
+            first_error("int x; const int *p = &x; *p = 1;"),
+            (
+                26,
+                28,
+                "cannot assign through pointer to const-qualified type \"const int *\"".to_string()
+            )
+        );
+        assert_eq!(
+            first_error("int x; int *const p = &x; p = 0;").2,
+            "cannot assign to variable \"p\" with const-qualified type \"int *const\""
+        );
+        assert_eq!(
+            first_error("va_list *ap;").2,
+            "pointer to \"va_list\" is not supported"
+        );
+        assert_eq!(
+            first_error("int f(int n, ...) { va_list ap; &ap; return 0; }").2,
+            "taking the address of \"va_list\" is not supported"
+        );
+    }
+
+    /// 最初のグローバル変数の型と、初期化される要素のオフセットを返す
+    fn global_init(input: &str) -> (String, Vec<usize>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        let program = Parser::new().parse(&mut TokenStream::new(&tokens)).unwrap();
+        let global = &program.globals[0];
+        (
+            global.decl.var.ty.to_string(),
+            global.init_values.iter().map(|init| init.offset).collect(),
+        )
+    }
+
+    #[test]
+    fn aggregate_test() {
+        assert_eq!(
+            global_init("int a[] = {1, 2, 3};"),
+            ("int [3]".to_string(), vec![0, 4, 8])
+        );
+        assert_eq!(
+            global_init("char a[] = {[5] = 1, 2, [1] = 3};"),
+            ("char [7]".to_string(), vec![1, 5, 6])
+        );
+        // 入れ子の集成体は波括弧を省略できる
+        assert_eq!(
+            global_init("int a[][2] = {1, 2, {3}, 4};"),
+            ("int [3][2]".to_string(), vec![0, 4, 8, 16])
+        );
+        assert_eq!(
+            global_init("struct P { char c; int x; long l; } p = {.l = 1, .c = 2, 3};"),
+            ("struct P".to_string(), vec![0, 4, 8])
+        );
+        assert_eq!(
+            global_init("struct { int a[2]; char c; } s[2] = {1, 2, 3, [1].c = 4, [1].a[1] = 5};"),
+            ("struct (anonymous) [2]".to_string(), vec![0, 4, 8, 16, 20])
+        );
+        assert_eq!(
+            global_init("int a[3] = {1, [0] = 2};"),
+            ("int [3]".to_string(), vec![0])
+        );
+        assert_eq!(
+            count_errors(
+                "struct P { int x; int y; }; struct P p; p.x + p.y; int a[2][3]; a[1][2];",
+                Parser::new()
+            ),
+            0
+        );
+
+        assert_eq!(
+            first_error("int a[2] = {1, 2, 3};"),
+            (
+                18,
+                18,
+                "excess elements in initializer for \"int [2]\"".to_string()
+            )
+        );
+        assert_eq!(
+            first_error("int a[];").2,
+            "definition of variable \"a\" with array type needs an explicit size or an initializer"
+        );
+        // 初期化子が誤っていても変数は宣言される
+        assert_eq!(
+            count_errors("int a[2] = {1, 2, 3}; a[0];", Parser::new()),
+            1
+        );
+        assert_eq!(
+            count_errors("int a[] = {1, .x = 2}; a[0];", Parser::new()),
+            1
+        );
+        assert_eq!(
+            first_error("int a[2] = 1;").2,
+            "initializer for \"int [2]\" must be an initializer list"
+        );
+        // スカラの初期化式は1つだけ波括弧で囲める
+        assert_eq!(
+            count_errors("int x = {42}; { int y = {{x}}; }", Parser::new()),
+            0
+        );
+        assert_eq!(
+            first_error("int x = {1, 2};"),
+            (12, 13, "expected } but found number".to_string())
+        );
+        assert_eq!(
+            first_error("int a[0];").2,
+            "array size must be greater than zero"
+        );
+        assert_eq!(
+            first_error("{ long a[3000000000000000000]; }"),
+            (9, 28, "array is too large".to_string())
+        );
+        assert_eq!(
+            first_error("char a[4][4611686018427387904];").2,
+            "array is too large"
+        );
+        assert_eq!(
+            first_error("int a[2][];").2,
+            "array has incomplete element type"
+        );
+        assert_eq!(
+            first_error("struct P { int x; } p = {.y = 1};"),
+            (26, 27, "no member named \"y\" in \"struct P\"".to_string())
+        );
+        assert_eq!(
+            first_error("int a[2] = {[2] = 1};").2,
+            "array designator index (2) exceeds array bounds"
+        );
+        assert_eq!(
+            first_error("int a[2] = {[0].x = 1};").2,
+            "designator \".\" cannot initialize \"int\""
+        );
+        assert_eq!(
+            first_error("int a[2] = {.x = 1};").2,
+            "designator \".\" cannot initialize \"int [2]\""
+        );
+        assert_eq!(
+            first_error("struct Q q;"),
+            (7, 8, "use of undefined \"struct Q\"".to_string())
+        );
+        assert_eq!(
+            first_error("struct P { int x; }; struct P { int y; };").2,
+            "redefinition of \"struct P\""
+        );
+        assert_eq!(
+            first_error("struct P { int x; long x; };").2,
+            "duplicate member \"x\""
+        );
+        // 代入は右結合の式で、カンマ演算子のオペランドにできる
+        assert_eq!(
+            count_errors(
+                "int i, j, n, a[2]; i = 0, j = n; i = j = a[0] = 1; a[i = 1] = (j = 2);",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("int a; (a = 1) = 2;").2,
+            "expression is not assignable"
+        );
+        assert_eq!(
+            first_error("int a, b; a + b = 1;"),
+            (10, 15, "expression is not assignable".to_string())
+        );
+        // 配列の要素と構造体のメンバには代入できる
+        assert_eq!(
+            count_errors(
+                "struct P { int x; int a[2]; } p, ps[2]; int a[2][3]; \
+                 a[1][2] = 1; p.x = 2; ps[1].a[0] = 3; (p).a[1] = 4;",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("int f(); f() = 1;"),
+            (9, 12, "expression is not assignable".to_string())
+        );
+        assert_eq!(
+            first_error("int a; 1 = a;").2,
+            "expression is not assignable"
+        );
+        assert_eq!(
+            first_error("int x; x[0];").2,
+            "subscripted value is not an array"
+        );
+        assert_eq!(
+            first_error("int a[2]; a[1.5];").2,
+            "array subscript is not an integer"
+        );
+        assert_eq!(
+            first_error("struct P { int x; } p; p.y;").2,
+            "no member named \"y\" in \"struct P\""
+        );
+        assert_eq!(
+            first_error("int x; x.y;").2,
+            "member reference base type \"int\" is not a structure"
+        );
+        assert_eq!(
+            first_error("struct P { int x; }; int f(struct P p);").2,
+            "parameter of type \"struct P\" is not supported"
+        );
+    }
+
+    fn locals_len(input: &str) -> (usize, Vec<usize>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let tokens = tokenize(source_map.file(file)).unwrap();
+        let program = Parser::new().parse(&mut TokenStream::new(&tokens)).unwrap();
+        (
+            program.locals_len,
+            program.func_defs.iter().map(|f| f.locals_len).collect(),
+        )
+    }
+
+    #[test]
+    fn locals_len_test() {
+        assert_eq!(locals_len("1;"), (0, vec![]));
+        // 変数は8 byte単位で割り当てられる
+        assert_eq!(
+            locals_len("{ char c; long l; struct { char x; } s; int a[5]; }"),
+            (48, vec![])
+        );
+        // 暗黙に宣言された変数もmain関数の領域に含まれる
+        assert_eq!(locals_len("a = 1; b = 2;"), (16, vec![]));
+        assert_eq!(
+            locals_len("int f(int x, int y) { int z; return z; } { int a[100]; }"),
+            (400, vec![24])
+        );
+        // 可変長引数の退避領域は16 byteにalignされる
+        assert_eq!(
+            locals_len("int f(int n, ...) { return n; }"),
+            (0, vec![176 + 8])
+        );
+
+        // RBPからのオフセットが32bitで表せない変数はエラーになる
+        assert_eq!(locals_len("{ char a[2147483632]; }"), (2147483632, vec![]));
+        assert_eq!(
+            first_error("{ int a[99999999999]; return 0; }"),
+            (
+                6,
+                7,
+                "local variable \"a\" exceeds the stack frame limit of 2147483632 bytes"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            first_error("int f() { char a[2147483632]; x = 1; return 0; }").2,
+            "local variable \"x\" exceeds the stack frame limit of 2147483632 bytes"
+        );
+        assert_eq!(
+            count_errors(
+                "{ long a[1152921504606846975]; long b[1152921504606846975]; long c[1]; }",
+                Parser::new()
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn void_test() {
+        assert_eq!(
+            count_errors(
+                "void f(void); void f() { ; return; } void (*p)(int); int g(void) { ;; return 0; }",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("void x;"),
+            (
+                5,
+                6,
+                "variable \"x\" has incomplete type \"void\"".to_string()
+            )
+        );
+        assert_eq!(
+            first_error("int f() { static void x; return 0; }").2,
+            "variable \"x\" has incomplete type \"void\""
+        );
+        assert_eq!(
+            first_error("void a[2];").2,
+            "array has incomplete element type \"void\""
+        );
+        assert_eq!(
+            first_error("struct P { void v; };").2,
+            "field \"v\" has incomplete type \"void\""
+        );
+        assert_eq!(
+            first_error("int f(int x, void);").2,
+            "parameter cannot have type \"void\""
+        );
+        assert_eq!(
+            first_error("void int x;").2,
+            "cannot combine \"int\" with previous type specifiers"
+        );
+        assert_eq!(
+            first_error("int f(int n, ...) { va_list ap; va_arg(ap, void); return 0; }").2,
+            "\"va_arg\" cannot read a \"void\""
+        );
+    }
+
+    #[test]
+    fn const_test() {
+        assert_eq!(
+            count_errors(
+                "const int a = 1; int const b[2] = {1, 2}; const volatile long c; \
+                 volatile int v; v = a + b[1] + c; \
+                 int f(const int x) { static const char s = 1; return x + s; }",
+                Parser::new()
+            ),
+            0
+        );
+        assert_eq!(
+            first_error("const int a = 1; a = 2;"),
+            (
+                17,
+                18,
+                "cannot assign to variable \"a\" with const-qualified type \"const int\""
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            first_error("int f(const int x) { x = 1; return x; }").2,
+            "cannot assign to variable \"x\" with const-qualified type \"const int\""
+        );
+        assert_eq!(
+            first_error("int f() { const volatile int y = 1; y = 2; return y; }").2,
+            "cannot assign to variable \"y\" with const-qualified type \"const volatile int\""
+        );
+        assert_eq!(
+            first_error("const int b[2] = {1, 2}; b[0] = 3;"),
+            (
+                25,
+                26,
+                "cannot assign to variable \"b\" with const-qualified type \"const int [2]\""
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            first_error("struct P { const int x; int y; } p; p.y = 1; p.x = 2;"),
+            (
+                47,
+                48,
+                "cannot assign to member \"x\" with const-qualified type \"const int\"".to_string()
+            )
+        );
+        assert_eq!(
+            first_error("struct P { int x; }; const struct P p; (p).x = 1;").2,
+            "cannot assign to variable \"p\" with const-qualified type \"const struct P\""
+        );
+        assert_eq!(
+            first_error("const x;"),
+            (0, 5, "type specifier missing".to_string())
+        );
+        assert_eq!(
+            first_error("extern int a; const int a;").2,
+            "conflicting types for \"a\""
+        );
+        assert_eq!(
+            first_error("struct P { int x; } const int p;").2,
+            "cannot combine \"int\" with previous type specifiers"
+        );
+    }
+
+    #[test]
+    fn init_declarators_test() {
+        assert_eq!(
+            count_errors(
+                "int a, b[2] = {1, 2}, c = 3; void (*fp)(int), (*gp)(int); \
+                 int f() { static int s, t = 1; int x = 1, y = x; return s + t + x + y; } \
+                 a = (b[0], c);",
+                Parser::new()
+            ),
+            0
+        );
+        // 宣言子の型は基本の型から宣言子ごとに導出される
+        assert_eq!(
+            global_init("int a, b[] = {1, 2, 3};"),
+            ("int".to_string(), vec![])
+        );
+        let types = |input: &str| {
+            let mut source_map = SourceMap::new();
+            let file = source_map.add_file("test", input);
+            let tokens = tokenize(source_map.file(file)).unwrap();
+            let program = Parser::new().parse(&mut TokenStream::new(&tokens)).unwrap();
+            program
+                .globals
+                .iter()
+                .map(|global| global.decl.var.ty.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            types("const char a, b[] = {1, 2, 3}, c[2][4];"),
+            vec!["char", "char [3]", "char [2][4]"]
+        );
+        assert_eq!(
+            types("int a, b = 2, *c, **d, *const e[2]; const int *f, *volatile g;"),
+            vec![
+                "int",
+                "int",
+                "int *",
+                "int **",
+                "int *[2]",
+                "const int *",
+                "const int *"
+            ]
+        );
+        assert_eq!(first_error("int a, a;").2, "redefinition of \"a\"");
+        assert_eq!(
+            first_error("int f() { int x = 1, y = x, x; return y; }").2,
+            "redefinition of \"x\""
+        );
+        assert_eq!(
+            first_error("extern int a, b = 1;").2,
+            "\"extern\" variable cannot have an initializer"
+        );
+    }
+
+    #[test]
+    fn max_errors_test() {
+        let input = "a = ; b = ; c = ; d = ;";
+        assert_eq!(count_errors(input, Parser::new()), 4);
+        // 上限に達したことを知らせるエラーが1つ追加される
+        assert_eq!(count_errors(input, Parser::new().max_errors(2)), 3);
+    }
+}
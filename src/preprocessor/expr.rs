@@ -0,0 +1,230 @@
+use super::lexer::{PpKind, PpToken};
+use crate::{
+    diagnostic::Diagnostic,
+    token::{parse_char_literal, parse_int_literal, split_float, Span},
+};
+
+type EResult = Result<i64, Diagnostic>;
+
+/// "#if" や "#elif" の条件式を評価する。
+/// `tokens` はマクロ展開済みで、"defined" も置き換え済みであること。
+/// 残っている識別子は0とみなす。
+/// 値は全て `long` として計算し、"&&"、"||"、"?:" の評価されないオペランドでは
+/// 0による除算をエラーにしない。
+pub fn eval_cond(tokens: &[PpToken], directive: &PpToken) -> Result<bool, Diagnostic> {
+    let mut eval = CondEval {
+        tokens,
+        idx: 0,
+        unevaluated: 0,
+        end_span: tokens.last().unwrap_or(directive).span,
+    };
+    let value = eval.cond()?;
+    if let Some(token) = eval.peek() {
+        let msg = format!(
+            "unexpected token \"{}\" in preprocessor expression",
+            token.text
+        );
+        return Err(Diagnostic::error(token.span, msg));
+    }
+    Ok(value != 0)
+}
+
+struct CondEval<'a> {
+    tokens: &'a [PpToken],
+    idx: usize,
+    /// 評価されないオペランドの入れ子の深さ
+    unevaluated: usize,
+    /// 条件式が途中で終わっていた場合のエラーの位置
+    end_span: Span,
+}
+
+/// 二項演算子とその優先順位。優先順位の高いものほど大きい
+const BINARY_OPS: [(&str, u8); 18] = [
+    ("||", 1),
+    ("&&", 2),
+    ("|", 3),
+    ("^", 4),
+    ("&", 5),
+    ("==", 6),
+    ("!=", 6),
+    ("<", 7),
+    (">", 7),
+    ("<=", 7),
+    (">=", 7),
+    ("<<", 8),
+    (">>", 8),
+    ("+", 9),
+    ("-", 9),
+    ("*", 10),
+    ("/", 10),
+    ("%", 10),
+];
+
+impl<'a> CondEval<'a> {
+    fn peek(&self) -> Option<&'a PpToken> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self) -> Option<&'a PpToken> {
+        let token = self.peek();
+        self.idx += 1;
+        token
+    }
+
+    fn consume(&mut self, punct: &str) -> bool {
+        if self.peek().is_some_and(|token| token.is_punct(punct)) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `evaluated` が `false` であれば、評価されないオペランドとして `f` を呼ぶ
+    fn eval_if<T>(&mut self, evaluated: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        if !evaluated {
+            self.unevaluated += 1;
+        }
+        let value = f(self);
+        if !evaluated {
+            self.unevaluated -= 1;
+        }
+        value
+    }
+
+    /// > cond    = binary(1) ("?" cond ":" cond)?
+    fn cond(&mut self) -> EResult {
+        let cond = self.binary(1)?;
+        if !self.consume("?") {
+            return Ok(cond);
+        }
+        let then = self.eval_if(cond != 0, Self::cond)?;
+        if !self.consume(":") {
+            let span = self.peek().map_or(self.end_span, |token| token.span);
+            return Err(Diagnostic::error(
+                span,
+                "expected \":\" in preprocessor expression",
+            ));
+        }
+        let els = self.eval_if(cond == 0, Self::cond)?;
+        Ok(if cond != 0 { then } else { els })
+    }
+
+    /// > binary  = unary (binary_op unary)*
+    ///
+    /// 優先順位が `min_prec` 以上の二項演算子を読む
+    fn binary(&mut self, min_prec: u8) -> EResult {
+        let mut lhs = self.unary()?;
+
+        while let Some((op, prec)) = self.peek().and_then(|token| {
+            BINARY_OPS
+                .iter()
+                .find(|(op, _)| token.is_punct(op))
+                .copied()
+        }) {
+            if prec < min_prec {
+                break;
+            }
+            let op_token = self.next().unwrap();
+            let rhs = match op {
+                "&&" => self.eval_if(lhs != 0, |this| this.binary(prec + 1))?,
+                "||" => self.eval_if(lhs == 0, |this| this.binary(prec + 1))?,
+                _ => self.binary(prec + 1)?,
+            };
+
+            lhs = match op {
+                "||" => (lhs != 0 || rhs != 0) as i64,
+                "&&" => (lhs != 0 && rhs != 0) as i64,
+                "|" => lhs | rhs,
+                "^" => lhs ^ rhs,
+                "&" => lhs & rhs,
+                "==" => (lhs == rhs) as i64,
+                "!=" => (lhs != rhs) as i64,
+                "<" => (lhs < rhs) as i64,
+                ">" => (lhs > rhs) as i64,
+                "<=" => (lhs <= rhs) as i64,
+                ">=" => (lhs >= rhs) as i64,
+                "<<" => lhs.wrapping_shl(rhs as u32),
+                ">>" => lhs.wrapping_shr(rhs as u32),
+                "+" => lhs.wrapping_add(rhs),
+                "-" => lhs.wrapping_sub(rhs),
+                "*" => lhs.wrapping_mul(rhs),
+                "/" | "%" if rhs == 0 => {
+                    if self.unevaluated == 0 {
+                        return Err(Diagnostic::error(
+                            op_token.span,
+                            "division by zero in preprocessor expression",
+                        ));
+                    }
+                    0
+                }
+                "/" => lhs.wrapping_div(rhs),
+                "%" => lhs.wrapping_rem(rhs),
+                _ => unreachable!(),
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    /// > unary   = ("+" | "-" | "!" | "~") unary | primary
+    fn unary(&mut self) -> EResult {
+        if self.consume("+") {
+            self.unary()
+        } else if self.consume("-") {
+            Ok(self.unary()?.wrapping_neg())
+        } else if self.consume("!") {
+            Ok((self.unary()? == 0) as i64)
+        } else if self.consume("~") {
+            Ok(!self.unary()?)
+        } else {
+            self.primary()
+        }
+    }
+
+    /// > primary = "(" cond ")" | num | char | ident
+    fn primary(&mut self) -> EResult {
+        let token = match self.next() {
+            Some(token) => token,
+            None => {
+                return Err(Diagnostic::error(
+                    self.end_span,
+                    "expected value in preprocessor expression",
+                ))
+            }
+        };
+
+        match token.kind {
+            PpKind::Punct if token.text == "(" => {
+                let value = self.cond()?;
+                if !self.consume(")") {
+                    let span = self.peek().map_or(self.end_span, |token| token.span);
+                    return Err(Diagnostic::error(
+                        span,
+                        "expected \")\" in preprocessor expression",
+                    ));
+                }
+                Ok(value)
+            }
+            PpKind::Num if split_float(&token.text).is_some() => Err(Diagnostic::error(
+                token.span,
+                "floating literal in preprocessor expression",
+            )),
+            PpKind::Num => parse_int_literal(&token.text)
+                .map(|(num, _)| num as i64)
+                .map_err(|msg| Diagnostic::error(token.span, msg)),
+            PpKind::Char => parse_char_literal(&token.text)
+                .map(|(num, _)| num as i64)
+                .map_err(|(msg, _)| Diagnostic::error(token.span, msg)),
+            // マクロ展開後に残った識別子は0とみなす
+            PpKind::Ident => Ok(0),
+            _ => {
+                let msg = format!(
+                    "invalid token \"{}\" in preprocessor expression",
+                    token.text
+                );
+                Err(Diagnostic::error(token.span, msg))
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    diagnostic::Diagnostic,
+    token::{is_ident_continue, is_ident_start, FileId, Pos, Span},
+};
+
+/// 前処理字句の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpKind {
+    /// 識別子とキーワード
+    Ident,
+    /// 数値リテラルになりうる文字列。
+    /// "1.5e+3f" や "0x2au" のように、接尾辞や指数部の符号も含む
+    Num,
+    /// 文字リテラル
+    Char,
+    /// 文字列リテラル
+    Str,
+    /// 記号と、それ以外の1文字
+    Punct,
+    /// 1つのファイルの終端
+    Eof,
+}
+
+/// プリプロセッサが扱うトークン。
+/// 字句解析器のトークンと違い、元のソースコードの文字列をそのまま持つ。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PpToken {
+    pub kind: PpKind,
+    pub text: String,
+    /// 元のソースコード上の範囲。
+    /// マクロ展開の結果のトークンは、展開されたマクロの呼び出し全体の範囲を持つ
+    pub span: Span,
+    /// 行の先頭のトークンかどうか
+    pub at_bol: bool,
+    /// 直前に空白やコメントがあるかどうか
+    pub has_space: bool,
+    /// このトークンを生成したマクロの名前。
+    /// 展開中のマクロを再帰的に展開しないために使う
+    pub hideset: Vec<String>,
+}
+
+impl PpToken {
+    /// `text` という記号かどうか
+    pub fn is_punct(&self, text: &str) -> bool {
+        self.kind == PpKind::Punct && self.text == text
+    }
+
+    /// `text` という識別子かどうか
+    pub fn is_ident(&self, text: &str) -> bool {
+        self.kind == PpKind::Ident && self.text == text
+    }
+}
+
+/// 2文字以上の記号。長いものから順に並ぶ
+const PUNCTS: [&str; 10] = ["...", "##", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||"];
+
+/// ファイル全体を前処理字句に分割する。
+/// コメントは空白として扱い、行末の "\" による行の継続は行の区切りとみなさない。
+/// 返されるトークン列の末尾には `PpKind::Eof` が置かれる。
+pub fn lex(src: &str, file: FileId, errors: &mut Vec<Diagnostic>) -> Vec<PpToken> {
+    let pos = |offset: usize| Pos::new(file) + offset;
+    let mut tokens = Vec::new();
+    let mut at_bol = true;
+    let mut has_space = false;
+
+    let mut idx = 0;
+    while let Some(c) = src[idx..].chars().next() {
+        let rmn = &src[idx..];
+        if c == '\n' {
+            at_bol = true;
+            has_space = false;
+            idx += 1;
+            continue;
+        }
+        if let Some(len) = ["\\\n", "\\\r\n"]
+            .iter()
+            .find(|splice| rmn.starts_with(*splice))
+            .map(|splice| splice.len())
+        {
+            has_space = true;
+            idx += len;
+            continue;
+        }
+        if c.is_whitespace() {
+            has_space = true;
+            idx += c.len_utf8();
+            continue;
+        }
+        if rmn.starts_with("//") {
+            has_space = true;
+            idx += rmn.find('\n').unwrap_or(rmn.len());
+            continue;
+        }
+        if let Some(comment) = rmn.strip_prefix("/*") {
+            has_space = true;
+            match comment.find("*/") {
+                Some(end) => idx += end + 4,
+                None => {
+                    let span = Span::new(pos(idx), pos(idx + 2));
+                    errors.push(Diagnostic::error(span, "unterminated comment"));
+                    idx = src.len();
+                }
+            }
+            continue;
+        }
+
+        let (kind, len) = split_token(rmn);
+        tokens.push(PpToken {
+            kind,
+            text: rmn[..len].to_string(),
+            span: Span::new(pos(idx), pos(idx + len)),
+            at_bol,
+            has_space,
+            hideset: Vec::new(),
+        });
+        at_bol = false;
+        has_space = false;
+        idx += len;
+    }
+
+    tokens.push(PpToken {
+        kind: PpKind::Eof,
+        text: String::new(),
+        span: Span::from(pos(src.len())),
+        at_bol: true,
+        has_space: false,
+        hideset: Vec::new(),
+    });
+    tokens
+}
+
+// 先頭の前処理字句の種類と長さを返す。
+// 引用符が閉じられていないリテラルは行末までを1つのトークンとし、
+// エラーは字句解析器に任せる。
+// "0x2au+1" -> (Num, 5)
+fn split_token(s: &str) -> (PpKind, usize) {
+    let bytes = s.as_bytes();
+    let c = s.chars().next().unwrap();
+
+    if is_ident_start(c) {
+        let len = s.find(|c| !is_ident_continue(c)).unwrap_or(s.len());
+        return (PpKind::Ident, len);
+    }
+
+    let starts_num =
+        c.is_ascii_digit() || (c == '.' && bytes.get(1).is_some_and(u8::is_ascii_digit));
+    if starts_num {
+        let mut idx = 1;
+        while idx < bytes.len() {
+            let is_exp_sign = matches!(bytes[idx], b'e' | b'E' | b'p' | b'P')
+                && matches!(bytes.get(idx + 1), Some(b'+' | b'-'));
+            let is_separator = bytes[idx] == b'\''
+                && bytes
+                    .get(idx + 1)
+                    .is_some_and(|next| next.is_ascii_alphanumeric());
+            if is_exp_sign || is_separator {
+                idx += 2;
+            } else if bytes[idx].is_ascii_alphanumeric() || matches!(bytes[idx], b'_' | b'.') {
+                idx += 1;
+            } else {
+                break;
+            }
+        }
+        return (PpKind::Num, idx);
+    }
+
+    if c == '\'' || c == '"' {
+        let mut chars = s.char_indices().skip(1);
+        let mut len = s.find('\n').unwrap_or(s.len());
+        while let Some((idx, next)) = chars.next() {
+            match next {
+                '\\' => {
+                    let _ = chars.next();
+                }
+                '\n' => break,
+                _ if next == c => {
+                    len = idx + 1;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let kind = if c == '\'' { PpKind::Char } else { PpKind::Str };
+        return (kind, len);
+    }
+
+    let len = PUNCTS
+        .iter()
+        .find(|punct| s.starts_with(*punct))
+        .map_or(c.len_utf8(), |punct| punct.len());
+    (PpKind::Punct, len)
+}
+
+/// トークン列を元のソースコードの文字列に戻す。
+/// トークンの間の空白は1つの空白にまとめる。
+pub fn join_text(tokens: &[PpToken]) -> String {
+    let mut text = String::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if idx > 0 && token.has_space {
+            text.push(' ');
+        }
+        text.push_str(&token.text);
+    }
+    text
+}
+
+/// `s` を文字列リテラルにする。
+/// "a\"b" -> "\"a\\\"b\""
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,5 @@
+mod expr;
+mod lexer;
+mod process;
+
+pub use process::Preprocessor;
@@ -0,0 +1,1198 @@
+use super::{
+    expr::eval_cond,
+    lexer::{join_text, lex, quote, PpKind, PpToken},
+};
+use crate::{
+    diagnostic::{CompileError, Diagnostic},
+    token::{FileId, Origin, Pos, SourceMap, Span},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// "#include" の入れ子の深さの上限
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+/// 展開されるとファイル名と行番号になる組み込みのマクロ
+const BUILTIN_MACROS: [&str; 2] = ["__FILE__", "__LINE__"];
+
+/// "#define" で定義されたマクロ
+#[derive(Debug, Clone)]
+struct Macro {
+    /// 関数形式マクロの仮引数。
+    /// オブジェクト形式マクロの場合は `None`
+    params: Option<Vec<String>>,
+    body: Vec<PpToken>,
+}
+
+impl Macro {
+    /// 同じ定義かどうか。
+    /// 本体のトークンの間の空白の有無も比べる。
+    fn is_same(&self, other: &Macro) -> bool {
+        let tokens = |body: &[PpToken]| {
+            body.iter()
+                .enumerate()
+                .map(|(idx, token)| (token.text.clone(), idx > 0 && token.has_space))
+                .collect::<Vec<_>>()
+        };
+        self.params == other.params && tokens(&self.body) == tokens(&other.body)
+    }
+}
+
+/// 条件付きコンパイルのディレクティブの入れ子の1段
+#[derive(Debug)]
+struct Cond {
+    /// "#if" などのディレクティブ名の範囲
+    span: Span,
+    /// "#else" を読んだかどうか
+    in_else: bool,
+    /// いずれかの分岐を選んだかどうか
+    taken: bool,
+}
+
+/// Cのプリプロセッサ。
+/// ファイルをインクルードしてマクロを展開し、条件付きコンパイルを行った結果を
+/// `SourceMap` に新しいファイルとして登録する。
+/// 結果のファイル上の範囲は、元のソースコード上の範囲に対応付けられる。
+///
+/// 処理中のトークン列は、次に読むトークンが末尾に来るように逆順に並べたスタックで持つ。
+/// インクルードされたファイルやマクロ展開の結果は、スタックに積むことで次に読まれる。
+pub struct Preprocessor {
+    /// "#include" でファイルを探すディレクトリ
+    include_dirs: Vec<PathBuf>,
+    macros: HashMap<String, Macro>,
+    /// 処理中の条件付きコンパイルのディレクティブ
+    conds: Vec<Cond>,
+    /// 読み込み中のファイルごとの、そのファイルを読み始めた時点の `conds` の深さ
+    file_cond_depths: Vec<usize>,
+    warnings: Vec<Diagnostic>,
+    errors: Vec<Diagnostic>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Preprocessor {
+            include_dirs: Vec::new(),
+            macros: HashMap::new(),
+            conds: Vec::new(),
+            file_cond_depths: Vec::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// "#include" でファイルを探すディレクトリを追加する。
+    /// 追加した順に探す。
+    pub fn include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    /// 処理中に見つかった警告
+    pub fn warnings(&self) -> &[Diagnostic] {
+        self.warnings.as_slice()
+    }
+
+    /// `file` をプリプロセスし、その結果のファイルを `source_map` に登録してIDを返す。
+    /// インクルードされたファイルも `source_map` に登録される。
+    ///
+    /// エラーが見つかっても処理を続け、見つかった全てのエラーをまとめて返す。
+    pub fn preprocess(
+        &mut self,
+        source_map: &mut SourceMap,
+        file: FileId,
+    ) -> Result<FileId, CompileError> {
+        let mut input = Vec::new();
+        self.push_file(source_map, &mut input, file);
+
+        let mut output = Vec::new();
+        while let Some(token) = input.pop() {
+            match token.kind {
+                PpKind::Eof => self.pop_file(),
+                _ if is_directive_start(&token) => self.directive(source_map, &mut input),
+                PpKind::Ident if self.expand_macro(source_map, &mut input, &token) => {}
+                _ => output.push(token),
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(CompileError {
+                diagnostics: std::mem::take(&mut self.errors),
+            });
+        }
+
+        // トークンは空白で区切り、改行は行の先頭のトークンの前にだけ置く
+        let mut src = String::new();
+        let mut origins = Vec::new();
+        for (idx, token) in output.iter().enumerate() {
+            if idx > 0 {
+                src.push(if token.at_bol { '\n' } else { ' ' });
+            }
+            let start = src.len() as u32;
+            src.push_str(&token.text);
+            origins.push(Origin {
+                start,
+                end: src.len() as u32,
+                span: token.span,
+            });
+        }
+        // ソースコードの終端は元のファイルの終端に対応させる
+        let end = Pos::new(file) + source_map.file(file).src.len();
+        origins.push(Origin {
+            start: src.len() as u32,
+            end: src.len() as u32,
+            span: Span::from(end),
+        });
+
+        let name = source_map.file(file).name.clone();
+        Ok(source_map.add_preprocessed_file(name, src, origins))
+    }
+
+    /// `file` を字句解析し、そのトークン列を `input` の先頭に置く
+    fn push_file(&mut self, source_map: &SourceMap, input: &mut Vec<PpToken>, file: FileId) {
+        let tokens = lex(&source_map.file(file).src, file, &mut self.errors);
+        input.extend(tokens.into_iter().rev());
+        self.file_cond_depths.push(self.conds.len());
+    }
+
+    /// ファイルの終端に達したときに呼ばれ、閉じられていない条件付きコンパイルを報告する
+    fn pop_file(&mut self) {
+        let depth = self.file_cond_depths.pop().unwrap();
+        for cond in self.conds.drain(depth..) {
+            self.errors.push(Diagnostic::error(
+                cond.span,
+                "unterminated conditional directive",
+            ));
+        }
+    }
+
+    /// 行の先頭の "#" に続くディレクティブを処理する
+    fn directive(&mut self, source_map: &mut SourceMap, input: &mut Vec<PpToken>) {
+        let line = read_line(input);
+        let (name, args) = match line.split_first() {
+            Some((name, args)) => (name, args.to_vec()),
+            // "#" だけの行は何もしない
+            None => return,
+        };
+
+        match name.text.as_str() {
+            "include" => self.include(source_map, input, name, args),
+            "define" => self.define(name, args),
+            "undef" => {
+                if let Some(ident) = self.macro_name(name, &args) {
+                    self.macros.remove(&ident);
+                }
+            }
+            "ifdef" | "ifndef" => {
+                let is_defined = self
+                    .macro_name(name, &args)
+                    .is_some_and(|ident| self.is_defined(&ident));
+                self.push_cond(input, name, is_defined == (name.text == "ifdef"));
+            }
+            "if" => {
+                let cond = self.eval_if(source_map, name, args);
+                self.push_cond(input, name, cond);
+            }
+            "elif" => self.elif(source_map, input, name, args),
+            "else" => self.els(input, name, &args),
+            "endif" => {
+                if self.current_cond(name).is_some() {
+                    self.conds.pop();
+                }
+                self.warn_extra_tokens(name, &args);
+            }
+            "error" => {
+                let msg = if args.is_empty() {
+                    "#error".to_string()
+                } else {
+                    join_text(&args)
+                };
+                self.errors.push(Diagnostic::error(name.span, msg));
+            }
+            _ => {
+                let msg = format!("invalid preprocessing directive \"#{}\"", name.text);
+                self.errors.push(Diagnostic::error(name.span, msg));
+            }
+        }
+    }
+
+    /// ディレクティブの後ろに余分なトークンがあれば警告する
+    fn warn_extra_tokens(&mut self, name: &PpToken, extra: &[PpToken]) {
+        if let Some(token) = extra.first() {
+            let msg = format!("extra tokens at end of #{} directive", name.text);
+            self.warnings.push(Diagnostic::warning(token.span, msg));
+        }
+    }
+
+    /// "#undef" や "#ifdef" の引数のマクロ名を読む
+    fn macro_name(&mut self, name: &PpToken, args: &[PpToken]) -> Option<String> {
+        match args.split_first() {
+            Some((ident, extra)) if ident.kind == PpKind::Ident => {
+                self.warn_extra_tokens(name, extra);
+                Some(ident.text.clone())
+            }
+            Some((token, _)) => {
+                self.errors.push(Diagnostic::error(
+                    token.span,
+                    "macro name must be an identifier",
+                ));
+                None
+            }
+            None => {
+                self.errors
+                    .push(Diagnostic::error(name.span, "macro name missing"));
+                None
+            }
+        }
+    }
+
+    fn is_defined(&self, ident: &str) -> bool {
+        self.macros.contains_key(ident) || BUILTIN_MACROS.contains(&ident)
+    }
+
+    /// > "#include" ("\"" path "\"" | "<" path ">")
+    ///
+    /// ファイルを探し、その内容を `input` の先頭に置く。
+    /// "\"...\"" で指定されたファイルは、インクルードしているファイルのディレクトリからも探す。
+    /// 引数がどちらの形でもなければ、マクロを展開してから読む。
+    fn include(
+        &mut self,
+        source_map: &mut SourceMap,
+        input: &mut Vec<PpToken>,
+        name: &PpToken,
+        args: Vec<PpToken>,
+    ) {
+        let args = match args.first() {
+            Some(token) if token.kind == PpKind::Str || token.is_punct("<") => args,
+            _ => self.expand_all(source_map, args),
+        };
+
+        let (path, is_quoted, extra) = match args.split_first() {
+            Some((token, extra)) if token.kind == PpKind::Str && token.text.len() >= 2 => {
+                let path = token.text[1..token.text.len() - 1].to_string();
+                (path, true, extra)
+            }
+            Some((token, rmn)) if token.is_punct("<") => {
+                match rmn.iter().position(|token| token.is_punct(">")) {
+                    Some(end) => (join_text(&rmn[..end]), false, &rmn[end + 1..]),
+                    None => {
+                        self.errors.push(Diagnostic::error(
+                            token.span,
+                            "expected \">\" after the file name",
+                        ));
+                        return;
+                    }
+                }
+            }
+            _ => {
+                let span = args.first().unwrap_or(name).span;
+                self.errors.push(Diagnostic::error(
+                    span,
+                    "expected \"FILENAME\" or <FILENAME>",
+                ));
+                return;
+            }
+        };
+        self.warn_extra_tokens(name, extra);
+
+        if self.file_cond_depths.len() >= MAX_INCLUDE_DEPTH {
+            self.errors
+                .push(Diagnostic::error(name.span, "#include nested too deeply"));
+            return;
+        }
+
+        let current_dir = Path::new(&source_map.file(name.span.file).name)
+            .parent()
+            .map(Path::to_path_buf);
+        let found = current_dir
+            .filter(|_| is_quoted)
+            .iter()
+            .chain(self.include_dirs.iter())
+            .map(|dir| dir.join(&path))
+            .find(|path| path.is_file());
+        let (path, src) = match found.and_then(|path| {
+            let src = std::fs::read_to_string(&path).ok()?;
+            Some((path, src))
+        }) {
+            Some(found) => found,
+            None => {
+                let msg = format!("\"{}\" file not found", path);
+                self.errors.push(Diagnostic::error(name.span, msg));
+                return;
+            }
+        };
+
+        let file = source_map.add_file(path.to_string_lossy(), src);
+        self.push_file(source_map, input, file);
+    }
+
+    /// > "#define" ident ("(" (ident ("," ident)*)? ")")? token*
+    ///
+    /// マクロを定義する。
+    /// マクロ名の直後に空白を挟まずに "(" が続く場合は関数形式マクロになる。
+    fn define(&mut self, name: &PpToken, args: Vec<PpToken>) {
+        let ident = match self.macro_name(name, &args[..args.len().min(1)]) {
+            Some(ident) => ident,
+            None => return,
+        };
+        if ident == "defined" {
+            self.errors.push(Diagnostic::error(
+                args[0].span,
+                "\"defined\" cannot be used as a macro name",
+            ));
+            return;
+        }
+
+        let (params, body) = match args.get(1) {
+            Some(token) if token.is_punct("(") && !token.has_space => {
+                match self.macro_params(&args[2..], token) {
+                    Some((params, body)) => (Some(params), body),
+                    None => return,
+                }
+            }
+            _ => (None, &args[1..]),
+        };
+
+        let mut body = body.to_vec();
+        for token in body.iter_mut() {
+            token.at_bol = false;
+        }
+        if !self.check_macro_body(&body, params.as_deref()) {
+            return;
+        }
+
+        let mac = Macro { params, body };
+        if let Some(prev) = self.macros.get(&ident) {
+            if !prev.is_same(&mac) {
+                let msg = format!("\"{}\" macro redefined", ident);
+                self.warnings.push(Diagnostic::warning(args[0].span, msg));
+            }
+        }
+        self.macros.insert(ident, mac);
+    }
+
+    /// 関数形式マクロの "(" より後ろの仮引数の並びを読み、仮引数とマクロの本体を返す
+    fn macro_params<'a>(
+        &mut self,
+        tokens: &'a [PpToken],
+        paren_left: &PpToken,
+    ) -> Option<(Vec<String>, &'a [PpToken])> {
+        let mut params: Vec<String> = Vec::new();
+        let mut idx = 0;
+        if tokens.first().is_some_and(|token| token.is_punct(")")) {
+            return Some((params, &tokens[1..]));
+        }
+
+        loop {
+            let token = match tokens.get(idx) {
+                Some(token) if token.kind == PpKind::Ident => token,
+                token => {
+                    let span = token.unwrap_or(paren_left).span;
+                    self.errors
+                        .push(Diagnostic::error(span, "expected parameter name"));
+                    return None;
+                }
+            };
+            if params.contains(&token.text) {
+                let msg = format!("duplicate macro parameter \"{}\"", token.text);
+                self.errors.push(Diagnostic::error(token.span, msg));
+                return None;
+            }
+            params.push(token.text.clone());
+            idx += 1;
+
+            match tokens.get(idx) {
+                Some(token) if token.is_punct(",") => idx += 1,
+                Some(token) if token.is_punct(")") => return Some((params, &tokens[idx + 1..])),
+                token => {
+                    let span = token.unwrap_or(paren_left).span;
+                    self.errors.push(Diagnostic::error(
+                        span,
+                        "expected \",\" or \")\" in macro parameter list",
+                    ));
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// マクロの本体の "#" と "##" の使い方を調べる
+    fn check_macro_body(&mut self, body: &[PpToken], params: Option<&[String]>) -> bool {
+        let ends = [body.first(), body.last()];
+        if let Some(token) = ends.iter().flatten().find(|token| token.is_punct("##")) {
+            self.errors.push(Diagnostic::error(
+                token.span,
+                "\"##\" cannot appear at either end of a macro expansion",
+            ));
+            return false;
+        }
+
+        // オブジェクト形式マクロの "#" はただの記号として扱う
+        let params = match params {
+            Some(params) => params,
+            None => return true,
+        };
+        for (idx, token) in body.iter().enumerate() {
+            let is_param =
+                |token: &PpToken| token.kind == PpKind::Ident && params.contains(&token.text);
+            if token.is_punct("#") && !body.get(idx + 1).is_some_and(is_param) {
+                self.errors.push(Diagnostic::error(
+                    token.span,
+                    "\"#\" is not followed by a macro parameter",
+                ));
+                return false;
+            }
+        }
+        true
+    }
+
+    /// 条件付きコンパイルの分岐に入る。
+    /// `taken` が `false` であれば、次の "#elif"、"#else"、"#endif" まで読み飛ばす。
+    fn push_cond(&mut self, input: &mut Vec<PpToken>, name: &PpToken, taken: bool) {
+        self.conds.push(Cond {
+            span: name.span,
+            in_else: false,
+            taken,
+        });
+        if !taken {
+            skip_cond_block(input);
+        }
+    }
+
+    /// 現在のファイルで処理中の条件付きコンパイルのディレクティブを返す。
+    /// 無ければエラーを報告する。
+    fn current_cond(&mut self, name: &PpToken) -> Option<&mut Cond> {
+        let depth = *self.file_cond_depths.last().unwrap();
+        if self.conds.len() <= depth {
+            let msg = format!("#{} without #if", name.text);
+            self.errors.push(Diagnostic::error(name.span, msg));
+            return None;
+        }
+        self.conds.last_mut()
+    }
+
+    fn elif(
+        &mut self,
+        source_map: &SourceMap,
+        input: &mut Vec<PpToken>,
+        name: &PpToken,
+        args: Vec<PpToken>,
+    ) {
+        let (in_else, taken) = match self.current_cond(name) {
+            Some(cond) => (cond.in_else, cond.taken),
+            None => return,
+        };
+        if in_else {
+            self.errors
+                .push(Diagnostic::error(name.span, "#elif after #else"));
+        }
+
+        // 既に分岐を選んでいれば、条件式は評価しない
+        if taken || !self.eval_if(source_map, name, args) {
+            skip_cond_block(input);
+        } else {
+            self.conds.last_mut().unwrap().taken = true;
+        }
+    }
+
+    fn els(&mut self, input: &mut Vec<PpToken>, name: &PpToken, args: &[PpToken]) {
+        let cond = match self.current_cond(name) {
+            Some(cond) => cond,
+            None => return,
+        };
+        let in_else = std::mem::replace(&mut cond.in_else, true);
+        let taken = std::mem::replace(&mut cond.taken, true);
+
+        if in_else {
+            self.errors
+                .push(Diagnostic::error(name.span, "#else after #else"));
+        }
+        self.warn_extra_tokens(name, args);
+        if taken {
+            skip_cond_block(input);
+        }
+    }
+
+    /// "#if" や "#elif" の条件式を評価する。
+    /// "defined X" と "defined(X)" はマクロを展開する前に1か0に置き換える。
+    fn eval_if(&mut self, source_map: &SourceMap, name: &PpToken, args: Vec<PpToken>) -> bool {
+        let mut replaced = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(token) = args.next() {
+            if !token.is_ident("defined") {
+                replaced.push(token);
+                continue;
+            }
+
+            let mut next = args.next();
+            let has_paren = next.as_ref().is_some_and(|next| next.is_punct("("));
+            if has_paren {
+                next = args.next();
+            }
+            let ident = match next {
+                Some(ident) if ident.kind == PpKind::Ident => ident,
+                next => {
+                    let span = next.map_or(token.span, |next| next.span);
+                    self.errors
+                        .push(Diagnostic::error(span, "macro name must be an identifier"));
+                    return false;
+                }
+            };
+            if has_paren && !args.next().is_some_and(|token| token.is_punct(")")) {
+                self.errors.push(Diagnostic::error(
+                    ident.span,
+                    "missing \")\" after \"defined\"",
+                ));
+                return false;
+            }
+
+            let value = if self.is_defined(&ident.text) {
+                "1"
+            } else {
+                "0"
+            };
+            replaced.push(PpToken {
+                kind: PpKind::Num,
+                text: value.to_string(),
+                ..token
+            });
+        }
+
+        let tokens = self.expand_all(source_map, replaced);
+        match eval_cond(&tokens, name) {
+            Ok(cond) => cond,
+            Err(e) => {
+                self.errors.push(e);
+                false
+            }
+        }
+    }
+
+    /// `tokens` に含まれる全てのマクロを展開する
+    fn expand_all(&mut self, source_map: &SourceMap, tokens: Vec<PpToken>) -> Vec<PpToken> {
+        let mut input: Vec<_> = tokens.into_iter().rev().collect();
+        let mut output = Vec::new();
+        while let Some(token) = input.pop() {
+            if token.kind == PpKind::Ident && self.expand_macro(source_map, &mut input, &token) {
+                continue;
+            }
+            output.push(token);
+        }
+        output
+    }
+
+    /// `token` がマクロ名であれば展開し、その結果を `input` の先頭に置く。
+    /// 展開の結果は再び読まれる際に、続くトークンと合わせて展開される。
+    /// 展開した場合は `true` を返す。
+    fn expand_macro(
+        &mut self,
+        source_map: &SourceMap,
+        input: &mut Vec<PpToken>,
+        token: &PpToken,
+    ) -> bool {
+        // 展開中のマクロは展開しない
+        if token.hideset.contains(&token.text) {
+            return false;
+        }
+
+        let mac = match self.macros.get(&token.text) {
+            Some(mac) => mac.clone(),
+            None => return self.expand_builtin(source_map, input, token),
+        };
+        let mut hideset = token.hideset.clone();
+
+        let (span, body) = match &mac.params {
+            None => (token.span, mac.body),
+            Some(params) => {
+                // 直後に "(" が無ければ関数形式マクロの呼び出しではない
+                if !input.last().is_some_and(|next| next.is_punct("(")) {
+                    return false;
+                }
+                let _ = input.pop();
+                let (args, paren_right) = match read_macro_args(input) {
+                    Some(args) => args,
+                    None => {
+                        self.errors.push(Diagnostic::error(
+                            token.span,
+                            "unterminated function-like macro invocation",
+                        ));
+                        return true;
+                    }
+                };
+
+                // 引数の無いマクロは、空の引数1つで呼び出される
+                let args_len = if params.is_empty() && args == [Vec::new()] {
+                    0
+                } else {
+                    args.len()
+                };
+                let span = if paren_right.span.file == token.span.file {
+                    token.span.join(paren_right.span)
+                } else {
+                    token.span
+                };
+                if args_len != params.len() {
+                    let msg = format!(
+                        "too {} arguments provided to function-like macro invocation",
+                        if args_len > params.len() {
+                            "many"
+                        } else {
+                            "few"
+                        }
+                    );
+                    self.errors.push(Diagnostic::error(span, msg));
+                    return true;
+                }
+
+                // 呼び出しの ")" を生成したマクロも展開しない
+                hideset.retain(|name| paren_right.hideset.contains(name));
+                (
+                    span,
+                    self.substitute(source_map, &mac.body, params, args, span),
+                )
+            }
+        };
+        hideset.push(token.text.clone());
+
+        let expanded = body.into_iter().enumerate().map(|(idx, body_token)| {
+            let mut body_hideset = hideset.clone();
+            body_hideset.extend(body_token.hideset);
+            PpToken {
+                span,
+                at_bol: idx == 0 && token.at_bol,
+                has_space: if idx == 0 {
+                    token.has_space
+                } else {
+                    body_token.has_space
+                },
+                hideset: body_hideset,
+                ..body_token
+            }
+        });
+        let expanded: Vec<_> = expanded.collect();
+        input.extend(expanded.into_iter().rev());
+        true
+    }
+
+    /// "__FILE__" と "__LINE__" を、`token` の位置のファイル名と行番号に展開する。
+    /// マクロの本体に含まれていた場合は、そのマクロを呼び出した位置になる。
+    fn expand_builtin(
+        &self,
+        source_map: &SourceMap,
+        input: &mut Vec<PpToken>,
+        token: &PpToken,
+    ) -> bool {
+        let file = source_map.file(token.span.file);
+        let (kind, text) = match token.text.as_str() {
+            "__FILE__" => (PpKind::Str, quote(&file.name)),
+            "__LINE__" => (PpKind::Num, file.line_col(token.span.start).0.to_string()),
+            _ => return false,
+        };
+        input.push(PpToken {
+            kind,
+            text,
+            ..token.clone()
+        });
+        true
+    }
+
+    /// 関数形式マクロの本体の仮引数を実引数で置き換える。
+    /// "#" の付いた仮引数は文字列リテラルにし、"##" の両側はトークンを連結する。
+    /// それ以外の仮引数は、実引数のマクロを展開してから置き換える。
+    fn substitute(
+        &mut self,
+        source_map: &SourceMap,
+        body: &[PpToken],
+        params: &[String],
+        args: Vec<Vec<PpToken>>,
+        span: Span,
+    ) -> Vec<PpToken> {
+        let param_index = |token: &PpToken| match token.kind {
+            PpKind::Ident => params.iter().position(|param| *param == token.text),
+            _ => None,
+        };
+        // `idx` 番目の仮引数を、マクロを展開せずに実引数で置き換える
+        let raw_arg = |idx: usize, token: &PpToken| {
+            let mut arg = args[idx].clone();
+            if let Some(first) = arg.first_mut() {
+                first.has_space = token.has_space;
+            }
+            arg
+        };
+
+        let mut result: Vec<PpToken> = Vec::new();
+        // 直前の "##" の左辺が空の実引数だったかどうか
+        let mut lhs_is_empty = false;
+        let mut idx = 0;
+        while idx < body.len() {
+            let token = &body[idx];
+            let next = body.get(idx + 1);
+
+            if token.is_punct("#") {
+                if let Some(arg_idx) = next.and_then(param_index) {
+                    result.push(PpToken {
+                        kind: PpKind::Str,
+                        text: quote(&join_text(&args[arg_idx])),
+                        ..token.clone()
+                    });
+                    lhs_is_empty = false;
+                    idx += 2;
+                    continue;
+                }
+            }
+
+            if token.is_punct("##") {
+                let rhs = next.unwrap();
+                let rhs = match param_index(rhs) {
+                    Some(arg_idx) => raw_arg(arg_idx, rhs),
+                    None => vec![rhs.clone()],
+                };
+                idx += 2;
+
+                // 空の実引数との連結は、もう一方をそのまま残す
+                match (lhs_is_empty, rhs.split_first()) {
+                    (true, _) => result.extend(rhs.iter().cloned()),
+                    (false, Some((first, rest))) => {
+                        let lhs = result.pop().unwrap();
+                        let pasted = self.paste(&lhs, first, span);
+                        result.push(pasted);
+                        result.extend(rest.iter().cloned());
+                    }
+                    (false, None) => {}
+                }
+                lhs_is_empty = lhs_is_empty && rhs.is_empty();
+                continue;
+            }
+
+            match param_index(token) {
+                Some(arg_idx) if next.is_some_and(|next| next.is_punct("##")) => {
+                    let arg = raw_arg(arg_idx, token);
+                    lhs_is_empty = arg.is_empty();
+                    result.extend(arg);
+                }
+                Some(arg_idx) => {
+                    let mut arg = self.expand_all(source_map, args[arg_idx].clone());
+                    if let Some(first) = arg.first_mut() {
+                        first.has_space = token.has_space;
+                    }
+                    result.extend(arg);
+                    lhs_is_empty = false;
+                }
+                None => {
+                    result.push(token.clone());
+                    lhs_is_empty = false;
+                }
+            }
+            idx += 1;
+        }
+
+        result
+    }
+
+    /// "##" で2つのトークンを連結する。
+    /// 連結した結果は1つの前処理字句でなければならない。
+    fn paste(&mut self, lhs: &PpToken, rhs: &PpToken, span: Span) -> PpToken {
+        let text = format!("{}{}", lhs.text, rhs.text);
+        let mut errors = Vec::new();
+        let tokens = lex(&text, span.file, &mut errors);
+
+        let kind = match tokens.as_slice() {
+            [token, _] if errors.is_empty() && token.text == text => token.kind,
+            _ => {
+                let msg = format!(
+                    "pasting formed \"{}\", an invalid preprocessing token",
+                    text
+                );
+                self.errors.push(Diagnostic::error(span, msg));
+                PpKind::Punct
+            }
+        };
+        PpToken {
+            kind,
+            text,
+            ..lhs.clone()
+        }
+    }
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Preprocessor::new()
+    }
+}
+
+/// ディレクティブの始まりの "#" かどうか。
+/// マクロ展開の結果の "#" はディレクティブにならない。
+fn is_directive_start(token: &PpToken) -> bool {
+    token.at_bol && token.is_punct("#") && token.hideset.is_empty()
+}
+
+/// 行の終わりまでのトークンを読む
+fn read_line(input: &mut Vec<PpToken>) -> Vec<PpToken> {
+    let mut line = Vec::new();
+    while let Some(token) = input.last() {
+        if token.at_bol || token.kind == PpKind::Eof {
+            break;
+        }
+        line.push(input.pop().unwrap());
+    }
+    line
+}
+
+/// 関数形式マクロの呼び出しの "(" より後ろを読み、実引数と ")" を返す。
+/// 括弧で囲まれていない "," で実引数を区切る。
+/// ファイルの終端までに ")" が無ければ `None` を返す。
+fn read_macro_args(input: &mut Vec<PpToken>) -> Option<(Vec<Vec<PpToken>>, PpToken)> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+    loop {
+        let token = match input.last() {
+            Some(token) if token.kind != PpKind::Eof => input.pop().unwrap(),
+            _ => return None,
+        };
+        match token.text.as_str() {
+            ")" if depth == 0 && token.kind == PpKind::Punct => return Some((args, token)),
+            "," if depth == 0 && token.kind == PpKind::Punct => {
+                args.push(Vec::new());
+                continue;
+            }
+            "(" if token.kind == PpKind::Punct => depth += 1,
+            ")" if token.kind == PpKind::Punct => depth -= 1,
+            _ => {}
+        }
+        args.last_mut().unwrap().push(token);
+    }
+}
+
+/// 条件を満たさない条件付きコンパイルの分岐を読み飛ばす。
+/// 入れ子になった分岐も読み飛ばし、同じ深さの "#elif"、"#else"、"#endif" の "#" を
+/// `input` の先頭に残す。
+fn skip_cond_block(input: &mut Vec<PpToken>) {
+    let mut depth = 0;
+    while let Some(token) = input.pop() {
+        if token.kind == PpKind::Eof {
+            input.push(token);
+            return;
+        }
+        if !is_directive_start(&token) {
+            continue;
+        }
+        let name = match input.last() {
+            Some(name) if !name.at_bol && name.kind == PpKind::Ident => name.text.clone(),
+            _ => continue,
+        };
+        match name.as_str() {
+            "if" | "ifdef" | "ifndef" => depth += 1,
+            "elif" | "else" | "endif" if depth == 0 => {
+                input.push(token);
+                return;
+            }
+            "endif" => depth -= 1,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `input` をプリプロセスした結果の文字列か、エラーのメッセージを返す
+    fn preprocess_with(
+        mut preprocessor: Preprocessor,
+        name: &str,
+        input: &str,
+    ) -> Result<String, Vec<String>> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file(name, input);
+        match preprocessor.preprocess(&mut source_map, file) {
+            Ok(file) => Ok(source_map.file(file).src.clone()),
+            Err(e) => Err(e.diagnostics.into_iter().map(|d| d.msg).collect()),
+        }
+    }
+
+    fn preprocess(input: &str) -> Result<String, Vec<String>> {
+        preprocess_with(Preprocessor::new(), "test.c", input)
+    }
+
+    fn first_error(input: &str) -> String {
+        preprocess(input).unwrap_err().remove(0)
+    }
+
+    fn warnings(input: &str) -> Vec<String> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test.c", input);
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.preprocess(&mut source_map, file).unwrap();
+        preprocessor
+            .warnings()
+            .iter()
+            .map(|warning| warning.msg.clone())
+            .collect()
+    }
+
+    #[test]
+    fn object_macro_test() {
+        assert_eq!(
+            preprocess("a = 1;\nb  =  a;"),
+            Ok("a = 1 ;\nb = a ;".into())
+        );
+        assert_eq!(preprocess("#define N 42\nN + N;"), Ok("42 + 42 ;".into()));
+        assert_eq!(
+            preprocess("#define A B + 1\n#define B A\nA;"),
+            Ok("A + 1 ;".into())
+        );
+        assert_eq!(preprocess("#define N 1\n#undef N\nN;"), Ok("N ;".into()));
+        // コメントは空白として扱い、"\" で行を継続できる
+        assert_eq!(
+            preprocess("#define N /* x */ 4\\\n2 // y\nN;"),
+            Ok("4 2 ;".into())
+        );
+        // マクロ展開の結果の "#" はディレクティブにならない
+        assert_eq!(
+            preprocess("#define D # define X\nD\nX;"),
+            Ok("# define X\nX ;".into())
+        );
+    }
+
+    #[test]
+    fn func_macro_test() {
+        assert_eq!(
+            preprocess("#define ADD(a, b) a + b\nADD(1, ADD(2, 3));"),
+            Ok("1 + 2 + 3 ;".into())
+        );
+        assert_eq!(
+            preprocess("#define F(a) [a]\nF((1, 2)) F(\n3\n) F;"),
+            Ok("[ ( 1 , 2 ) ] [ 3 ] F ;".into())
+        );
+        assert_eq!(preprocess("#define F() 42\nF();"), Ok("42 ;".into()));
+        assert_eq!(
+            preprocess("#define F (a) a\nF(1);"),
+            Ok("( a ) a ( 1 ) ;".into())
+        );
+        // 展開の結果に続くトークンも実引数になる
+        assert_eq!(
+            preprocess("#define F(a) a * 2\n#define G F\nG(21);"),
+            Ok("21 * 2 ;".into())
+        );
+        assert_eq!(
+            preprocess("#define F(a) a F\nF(1)(2);"),
+            Ok("1 F ( 2 ) ;".into())
+        );
+    }
+
+    #[test]
+    fn stringize_paste_test() {
+        assert_eq!(
+            preprocess("#define S(x) #x\nS(a  +  \"b\\n\");"),
+            Ok(r#""a + \"b\\n\"" ;"#.into())
+        );
+        assert_eq!(
+            preprocess("#define CAT(a, b) a ## b\nCAT(foo, 42); CAT(, x); CAT(x, ); CAT(<, =);"),
+            Ok("foo42 ; x ; x ; <= ;".into())
+        );
+        // "##" の被演算子になる実引数はマクロを展開しない
+        assert_eq!(
+            preprocess("#define N 1\n#define CAT(a) a ## N\n#define ID(a) a\nCAT(N) ID(N);"),
+            Ok("NN 1 ;".into())
+        );
+        assert_eq!(
+            first_error("#define CAT(a, b) a ## b\nCAT(+, /);"),
+            "pasting formed \"+/\", an invalid preprocessing token"
+        );
+    }
+
+    #[test]
+    fn cond_test() {
+        let input = "#define A 2
+#if A == 1
+one
+#elif A == 2 && defined(A) && !defined B
+two
+#else
+other
+#endif
+#ifdef A
+#ifndef A
+#if 1 / 0
+x
+#else
+y
+#endif
+#endif
+#else
+#error skipped
+#endif
+end";
+        assert_eq!(preprocess(input), Ok("two\nend".into()));
+        assert_eq!(
+            preprocess("#if (1 + 2) * 3 == 9 ? 0x10 >> 4 : -1\nyes\n#endif"),
+            Ok("yes".into())
+        );
+        // 評価されないオペランドでは0で割ってもよい
+        assert_eq!(
+            preprocess("#if 0 && 1 / 0 || 1 ? 1 : 1 % 0\nyes\n#endif"),
+            Ok("yes".into())
+        );
+        assert_eq!(
+            preprocess("#if undefined_name || 'a' != 97\nno\n#else\nyes\n#endif"),
+            Ok("yes".into())
+        );
+    }
+
+    #[test]
+    fn builtin_macro_test() {
+        assert_eq!(
+            preprocess("a;\n\n__LINE__ __FILE__;"),
+            Ok("a ;\n3 \"test.c\" ;".into())
+        );
+        // マクロの本体では、マクロを呼び出した行になる
+        assert_eq!(preprocess("#define L __LINE__\n\nL;"), Ok("3 ;".into()));
+        assert_eq!(preprocess("#ifdef __FILE__\nyes\n#endif"), Ok("yes".into()));
+    }
+
+    #[test]
+    fn include_test() {
+        let input = "#include \"include/atom.h\"\n#include \"include/atom.h\"\nSQUARE(ANSWER);";
+        assert_eq!(
+            preprocess_with(Preprocessor::new(), "tests/main.c", input),
+            Ok("int add ( int a , int b ) ;\n( ( 42 ) * ( 42 ) ) ;".into())
+        );
+
+        let input = "#define HEADER <atom.h>\n#include HEADER\nANSWER;";
+        assert_eq!(
+            preprocess_with(
+                Preprocessor::new().include_dir("tests/include"),
+                "main.c",
+                input
+            ),
+            Ok("int add ( int a , int b ) ;\n42 ;".into())
+        );
+        // "<...>" は追加したディレクトリからだけ探す
+        assert_eq!(
+            preprocess_with(
+                Preprocessor::new(),
+                "tests/main.c",
+                "#include <include/atom.h>"
+            ),
+            Err(vec!["\"include/atom.h\" file not found".to_string()])
+        );
+    }
+
+    #[test]
+    fn error_test() {
+        assert_eq!(first_error("#error stop  here\n"), "stop here");
+        assert_eq!(first_error("#if 1\n"), "unterminated conditional directive");
+        assert_eq!(first_error("#endif"), "#endif without #if");
+        assert_eq!(
+            first_error("#if 1\n#else\n#else\n#endif"),
+            "#else after #else"
+        );
+        assert_eq!(
+            first_error("#if 0\n#else\n#elif 1\n#endif"),
+            "#elif after #else"
+        );
+        assert_eq!(
+            first_error("#if\n#endif"),
+            "expected value in preprocessor expression"
+        );
+        assert_eq!(
+            first_error("#if 1 / 0\n#endif"),
+            "division by zero in preprocessor expression"
+        );
+        assert_eq!(
+            first_error("#if (1\n#endif"),
+            "expected \")\" in preprocessor expression"
+        );
+        assert_eq!(
+            first_error("#foo"),
+            "invalid preprocessing directive \"#foo\""
+        );
+        assert_eq!(
+            first_error("#define 1 2"),
+            "macro name must be an identifier"
+        );
+        assert_eq!(
+            first_error("#define F(a, a) a"),
+            "duplicate macro parameter \"a\""
+        );
+        assert_eq!(
+            first_error("#define F(a) #b"),
+            "\"#\" is not followed by a macro parameter"
+        );
+        assert_eq!(
+            first_error("#define F(a) ## a"),
+            "\"##\" cannot appear at either end of a macro expansion"
+        );
+        assert_eq!(
+            first_error("#define F(a, b) a\nF(1);"),
+            "too few arguments provided to function-like macro invocation"
+        );
+        assert_eq!(
+            first_error("#define F(a) a\nF(1, 2);"),
+            "too many arguments provided to function-like macro invocation"
+        );
+        assert_eq!(
+            first_error("#define F(a) a\nF(1;"),
+            "unterminated function-like macro invocation"
+        );
+        assert_eq!(
+            first_error("#include \"missing.h\""),
+            "\"missing.h\" file not found"
+        );
+        assert_eq!(first_error("/* a"), "unterminated comment");
+        // インクルードされたファイルの条件付きコンパイルは、そのファイルの中で閉じる
+        assert_eq!(
+            preprocess_with(
+                Preprocessor::new(),
+                "tests/main.c",
+                "#if 1\n#include \"include/atom.h\"\n#endif\n#endif"
+            ),
+            Err(vec!["#endif without #if".to_string()])
+        );
+        // エラーが見つかっても処理を続ける
+        assert_eq!(preprocess("#error a\n#error b").unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn warning_test() {
+        assert_eq!(
+            warnings("#define N 1\n#define N 2\n#define M 1\n#define M  1"),
+            vec!["\"N\" macro redefined"]
+        );
+        assert_eq!(
+            warnings("#ifdef N X\n#endif N"),
+            vec![
+                "extra tokens at end of #ifdef directive",
+                "extra tokens at end of #endif directive"
+            ]
+        );
+    }
+
+    #[test]
+    fn origin_test() {
+        let mut source_map = SourceMap::new();
+        let input = "#define N 42\nint a = N;";
+        let file = source_map.add_file("test.c", input);
+        let output = Preprocessor::new()
+            .preprocess(&mut source_map, file)
+            .unwrap();
+        assert_eq!(source_map.file(output).src, "int a = 42 ;");
+
+        let resolve = |start: u32, end: u32| {
+            let span = source_map.resolve(Span {
+                file: output,
+                start,
+                end,
+            });
+            (span.file, span.start, span.end)
+        };
+        // "a" はそのままの位置に、マクロ展開の結果はマクロの呼び出しに対応する
+        assert_eq!(resolve(4, 5), (file, 17, 18));
+        assert_eq!(resolve(8, 10), (file, 21, 22));
+        assert_eq!(resolve(0, 12), (file, 13, 23));
+        // ソースコードの終端は元のファイルの終端に対応する
+        assert_eq!(resolve(12, 12), (file, 23, 23));
+    }
+}
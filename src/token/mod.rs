@@ -1,6 +1,14 @@
 mod pos;
-pub mod token;
+mod source_map;
+mod span;
 mod tokenizer;
+pub mod tokens;
 
 pub use pos::Pos;
-pub use tokenizer::{tokenize, TokenStream};
+pub use source_map::{FileId, Origin, SourceFile, SourceMap};
+pub use span::Span;
+pub use tokenizer::{tokenize, tokenize_with_max_errors, TokenStream};
+
+pub(crate) use tokenizer::{
+    is_ident_continue, is_ident_start, parse_char_literal, parse_int_literal, split_float,
+};
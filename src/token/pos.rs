@@ -1,46 +1,36 @@
+use super::source_map::FileId;
 use std::ops::{Add, AddAssign};
 
 /// オリジナルのソースコード上のある位置を表す。
+/// どのファイルの何バイト目かを `SourceMap` 上のIDとオフセットで表す。
+/// プリプロセスの結果のファイル上の位置は、表示の際に元のファイル上の位置に戻される。
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Pos<'origin> {
-    pub origin: &'origin str,
-    pub pos: usize,
+pub struct Pos {
+    pub file: FileId,
+    pub offset: u32,
 }
 
-impl<'origin> Pos<'origin> {
-    pub fn new(origin: &'origin str) -> Self {
-        Pos { origin, pos: 0 }
-    }
-
-    /// ```text
-    /// let i = return;
-    ///         ^ unexpected token "return"
-    /// ```
-    ///
-    /// のような文字列を返す
-    pub fn display(&self, msg: &str) -> String {
-        // TODO
-        // 複数行のソースコードに対応
-        let leading_spaces = " ".repeat(self.pos);
-        format!("{}\n{}^ {}", self.origin, leading_spaces, msg)
+impl Pos {
+    pub fn new(file: FileId) -> Self {
+        Pos { file, offset: 0 }
     }
 }
 
 /// 位置を動かす
-impl<'origin> Add<usize> for Pos<'origin> {
+impl Add<usize> for Pos {
     type Output = Self;
 
     fn add(self, rhs: usize) -> Self {
         Pos {
-            origin: self.origin,
-            pos: self.pos + rhs,
+            file: self.file,
+            offset: self.offset + rhs as u32,
         }
     }
 }
 
 /// 位置を動かす
-impl<'origin> AddAssign<usize> for Pos<'origin> {
+impl AddAssign<usize> for Pos {
     fn add_assign(&mut self, rhs: usize) {
-        self.pos += rhs;
+        self.offset += rhs as u32;
     }
 }
@@ -0,0 +1,225 @@
+use super::{pos::Pos, span::Span};
+
+/// `SourceMap` に登録されたファイルを識別するID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+/// コンパイル対象の1つのソースファイル
+#[derive(Debug)]
+pub struct SourceFile {
+    pub id: FileId,
+    /// 診断メッセージに表示するファイル名
+    pub name: String,
+    pub src: String,
+    /// 各行の先頭のオフセット
+    line_starts: Vec<u32>,
+    /// プリプロセスの結果のファイルの場合、各トークンの元になった範囲。
+    /// オフセットの順に並ぶ。通常のファイルでは空。
+    origins: Vec<Origin>,
+}
+
+/// プリプロセスの結果のファイル上の範囲と、その元になったソースコード上の範囲の対応。
+/// マクロ展開の結果のトークンは、展開されたマクロの呼び出し全体に対応する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Origin {
+    pub start: u32,
+    pub end: u32,
+    pub span: Span,
+}
+
+impl SourceFile {
+    /// `offset` が何行目の何文字目かを返す。どちらも1始まり。
+    pub fn line_col(&self, offset: u32) -> (usize, usize) {
+        let line_idx = match self.line_starts.binary_search(&offset) {
+            Ok(idx) => idx,
+            Err(idx) => idx - 1,
+        };
+        let line_start = self.line_starts[line_idx] as usize;
+        let col = self.src[line_start..offset as usize].chars().count();
+        (line_idx + 1, col + 1)
+    }
+
+    /// `line` 行目（1始まり）の文字列を改行文字を除いて返す
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1] as usize;
+        let end = self
+            .line_starts
+            .get(line)
+            .map(|end| *end as usize)
+            .unwrap_or(self.src.len());
+        self.src[start..end].trim_end_matches(&['\n', '\r'][..])
+    }
+}
+
+/// コンパイル対象の全てのソースファイルを保持する。
+/// `Pos` や `Span` はこれを通して実際のファイルや行に対応付けられる。
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap { files: Vec::new() }
+    }
+
+    pub fn add_file(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        self.add_preprocessed_file(name, src, Vec::new())
+    }
+
+    /// プリプロセスの結果のファイルを登録する。
+    /// このファイル上の範囲は、表示の際に `origins` を通して元のファイル上の範囲に戻される。
+    pub fn add_preprocessed_file(
+        &mut self,
+        name: impl Into<String>,
+        src: impl Into<String>,
+        origins: Vec<Origin>,
+    ) -> FileId {
+        let src = src.into();
+        assert!(src.len() <= u32::MAX as usize, "source file is too large");
+
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(idx, _)| idx as u32 + 1))
+            .collect();
+
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile {
+            id,
+            name: name.into(),
+            src,
+            line_starts,
+            origins,
+        });
+        id
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    /// ```text
+    /// main.atom:2:9: undefined variable
+    /// a = 1 + foo;
+    ///         ^~~
+    /// ```
+    ///
+    /// のような文字列を返す
+    pub fn display(&self, span: Span, msg: &str) -> String {
+        let span = self.resolve(span);
+        let file = self.file(span.file);
+        let (line, col) = file.line_col(span.start);
+        let line_str = file.line(line);
+
+        // 複数行にまたがる範囲は、最初の行の終わりまでに下線を引く
+        let line_end = file.line_starts[line - 1] as usize + line_str.len();
+        let start = span.start as usize;
+        let end = (span.end as usize).min(line_end).max(start);
+        let width = file.src[start..end].chars().count().max(1);
+
+        format!(
+            "{}:{}:{}: {}\n{}\n{}^{}",
+            file.name,
+            line,
+            col,
+            msg,
+            line_str,
+            " ".repeat(col - 1),
+            "~".repeat(width - 1)
+        )
+    }
+
+    pub fn display_pos(&self, pos: Pos, msg: &str) -> String {
+        self.display(Span::from(pos), msg)
+    }
+
+    /// プリプロセスの結果のファイル上の範囲を、元のソースコード上の範囲に戻す。
+    /// 通常のファイル上の範囲はそのまま返す。
+    pub fn resolve(&self, span: Span) -> Span {
+        let origins = &self.file(span.file).origins;
+        if origins.is_empty() {
+            return span;
+        }
+
+        let start_origin = find_origin(origins, span.start);
+        let start = start_origin.resolve(span.start, false);
+        if span.is_empty() {
+            return Span::new(start, start);
+        }
+        let end = find_origin(origins, span.end - 1).resolve(span.end, true);
+
+        // 異なるファイルにまたがる範囲は、先頭のトークンの範囲に縮める
+        if end.file == start.file && end.offset >= start.offset {
+            Span::new(start, end)
+        } else {
+            Span::new(start, start_origin.span.end())
+        }
+    }
+}
+
+/// `offset` を含むか、`offset` より前にある最後の対応を探す
+fn find_origin(origins: &[Origin], offset: u32) -> &Origin {
+    let idx = origins.partition_point(|origin| origin.start <= offset);
+    &origins[idx.saturating_sub(1)]
+}
+
+impl Origin {
+    /// プリプロセスの結果のファイル上の `offset` を元のソースコード上の位置に戻す。
+    /// 長さの変わらないトークンは文字単位で対応させ、
+    /// マクロ展開のように長さの変わるトークンは元の範囲の端に対応させる。
+    fn resolve(&self, offset: u32, is_end: bool) -> Pos {
+        let is_verbatim = self.end - self.start == self.span.end - self.span.start;
+        if offset < self.start {
+            self.span.start()
+        } else if offset > self.end {
+            self.span.end()
+        } else if is_verbatim {
+            self.span.start() + (offset - self.start) as usize
+        } else if is_end && offset > self.start {
+            self.span.end()
+        } else {
+            self.span.start()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_col_test() {
+        let mut map = SourceMap::new();
+        let id = map.add_file("test", "a = 1;\nb = a;\n\nreturn b;");
+        let file = map.file(id);
+
+        assert_eq!(file.line_col(0), (1, 1));
+        assert_eq!(file.line_col(4), (1, 5));
+        assert_eq!(file.line_col(7), (2, 1));
+        assert_eq!(file.line_col(14), (3, 1));
+        assert_eq!(file.line_col(15), (4, 1));
+        assert_eq!(file.line(2), "b = a;");
+        assert_eq!(file.line(3), "");
+    }
+
+    #[test]
+    fn display_test() {
+        let mut map = SourceMap::new();
+        let id = map.add_file("test", "a = 1;\nb = a + foo;");
+        let span = Span {
+            file: id,
+            start: 15,
+            end: 18,
+        };
+
+        assert_eq!(
+            map.display(span, "undefined variable"),
+            "test:2:9: undefined variable\nb = a + foo;\n        ^~~"
+        );
+    }
+
+    #[test]
+    fn compact_pos_test() {
+        assert_eq!(std::mem::size_of::<Pos>(), 8);
+        assert_eq!(std::mem::size_of::<Span>(), 12);
+    }
+}
@@ -0,0 +1,59 @@
+use super::{pos::Pos, source_map::FileId};
+
+/// オリジナルのソースコード上のある範囲を表す。
+/// `start` の位置の文字を含み、`end` の位置の文字を含まない。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub file: FileId,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Span {
+    pub fn new(start: Pos, end: Pos) -> Self {
+        debug_assert!(start.file == end.file && start.offset <= end.offset);
+        Span {
+            file: start.file,
+            start: start.offset,
+            end: end.offset,
+        }
+    }
+
+    pub fn start(&self) -> Pos {
+        Pos {
+            file: self.file,
+            offset: self.start,
+        }
+    }
+
+    pub fn end(&self) -> Pos {
+        Pos {
+            file: self.file,
+            offset: self.end,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// `self` と `other` の両方を覆う範囲を返す
+    pub fn join(self, other: Span) -> Self {
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// 長さ0の範囲に変換する
+impl From<Pos> for Span {
+    fn from(pos: Pos) -> Self {
+        Span::new(pos, pos)
+    }
+}
@@ -1,33 +1,70 @@
-use super::{pos::Pos, token::*};
+use super::{pos::Pos, source_map::SourceFile, span::Span, tokens::*};
+use crate::{
+    diagnostic::{CompileError, Diagnostic, DEFAULT_MAX_ERRORS},
+    ty::{FloatType, IntType, Type},
+};
 
+/// 字句解析済みのトークン列を先頭から読み進めるためのカーソル。
+/// `Copy` なので、コピーしておくことで簡単に読み戻すことができる。
 #[derive(Debug, Clone, Copy)]
 pub struct TokenStream<'src> {
-    s: &'src str,
-    // ソースコード上における現在の文字の位置
-    pub pos: Pos<'src>,
+    /// 末尾は必ず `Token::Eof`
+    tokens: &'src [Token<'src>],
+}
+
+/// ソースコード全体を字句解析し、トークン列を返す。
+/// 返されるトークン列の末尾には `Token::Eof` が置かれる。
+///
+/// 字句解析のエラーが見つかっても解析を続け、
+/// 見つかった全てのエラーをまとめて返す。
+pub fn tokenize(file: &SourceFile) -> Result<Vec<Token<'_>>, CompileError> {
+    tokenize_with_max_errors(file, DEFAULT_MAX_ERRORS)
 }
 
-pub fn tokenize<'src>(src: &'src str) -> TokenStream<'src> {
-    TokenStream {
-        s: src,
-        pos: Pos::new(src),
+/// `tokenize` と同じだが、報告するエラーの最大数を `max_errors` にする。
+/// これに達した時点で字句解析を打ち切る。
+pub fn tokenize_with_max_errors(
+    file: &SourceFile,
+    max_errors: usize,
+) -> Result<Vec<Token<'_>>, CompileError> {
+    let mut lexer = Lexer {
+        s: file.src.as_str(),
+        pos: Pos::new(file.id),
+        tokens: Vec::new(),
+        errors: Vec::new(),
+        max_errors: max_errors.max(1),
+    };
+    lexer.lex();
+
+    if lexer.errors.is_empty() {
+        Ok(lexer.tokens)
+    } else {
+        Err(CompileError {
+            diagnostics: lexer.errors,
+        })
     }
 }
 
 impl<'src> TokenStream<'src> {
+    pub fn new(tokens: &'src [Token<'src>]) -> Self {
+        assert!(matches!(tokens.last(), Some(Token::Eof(_))));
+        TokenStream { tokens }
+    }
+
     pub fn peek(&self) -> Option<Token<'src>> {
         let mut copied = *self;
         copied.next()
     }
 
-    fn update_s(&mut self, next_s: &'src str) {
-        self.pos += self.s.len() - next_s.len();
-        self.s = next_s;
+    /// 次のトークンの位置。
+    /// 全てのトークンを読み終えている場合はソースコードの終端の位置。
+    pub fn pos(&self) -> Pos {
+        self.tokens[0].pos()
     }
 
-    pub fn exit_with_err_msg(&self, msg: &'static str) -> ! {
-        eprintln!("{}", self.pos.display(msg));
-        std::process::exit(1)
+    /// 現在の位置を指すエラーを生成する
+    pub fn error(&self, msg: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(self.pos(), msg)
     }
 }
 
@@ -35,113 +72,543 @@ impl<'src> Iterator for TokenStream<'src> {
     type Item = Token<'src>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.update_s(self.s.trim_start());
+        match self.tokens[0] {
+            Token::Eof(_) => None,
+            token => {
+                self.tokens = &self.tokens[1..];
+                Some(token)
+            }
+        }
+    }
+}
 
-        let s = self.s;
+struct Lexer<'src> {
+    /// まだ読んでいないソースコード
+    s: &'src str,
+    // ソースコード上における現在の文字の位置
+    pos: Pos,
+    tokens: Vec<Token<'src>>,
+    errors: Vec<Diagnostic>,
+    /// 報告するエラーの最大数
+    max_errors: usize,
+}
 
-        if s.is_empty() {
-            return None;
+impl<'src> Lexer<'src> {
+    fn update_s(&mut self, next_s: &'src str) {
+        self.pos += self.s.len() - next_s.len();
+        self.s = next_s;
+    }
+
+    fn lex(&mut self) {
+        loop {
+            self.update_s(self.s.trim_start());
+            if self.s.is_empty() {
+                break;
+            }
+
+            if self.errors.len() >= self.max_errors {
+                self.errors.push(Diagnostic::error(
+                    self.pos,
+                    "too many errors emitted, stopping now",
+                ));
+                return;
+            }
+
+            if !self.lex_token() {
+                self.lex_unexpected();
+            }
         }
 
-        // 2文字の演算子を調べる
-        if s.len() >= 2 {
-            let (token_str, rmn) = s.split_at(2);
-            if let Some(token) = match token_str {
-                "<=" => Some(Token::Lte(Lte::new(self.pos))),
-                ">=" => Some(Token::Gte(Gte::new(self.pos))),
-                "==" => Some(Token::Eq(Eq::new(self.pos))),
-                "!=" => Some(Token::Neq(Neq::new(self.pos))),
-                _ => None,
-            } {
-                self.update_s(rmn);
-                return Some(token);
+        self.tokens.push(Token::Eof(Eof::new(self.pos)));
+    }
+
+    /// 先頭のトークンを1つ読む。
+    /// どのトークンの先頭でもなければ何もせずに `false` を返す。
+    fn lex_token(&mut self) -> bool {
+        let c = match self.s.chars().next() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        let starts_num = c.is_ascii_digit()
+            || (c == '.' && self.s[1..].starts_with(|c: char| c.is_ascii_digit()));
+        if starts_num {
+            self.lex_num();
+        } else if c == '\'' {
+            self.lex_char();
+        } else if c == '"' {
+            self.lex_str();
+        } else if is_ident_start(c) {
+            self.lex_ident();
+        } else {
+            return self.lex_punct();
+        }
+        true
+    }
+
+    /// 不正な文字を読み飛ばす。
+    /// 同じ行で続く不正な文字は、間に空白があってもまとめて1つのエラーにする。
+    /// プリプロセス後のソースコードではトークンの間に空白が置かれるため。
+    fn lex_unexpected(&mut self) {
+        let start = self.pos;
+        let first = self.s.chars().next().unwrap();
+        let err_idx = self.errors.len();
+        let mut count = 0;
+
+        loop {
+            let c = self.s.chars().next().unwrap();
+            count += 1;
+            self.update_s(&self.s[c.len_utf8()..]);
+            let span = Span::new(start, self.pos);
+
+            self.update_s(
+                self.s
+                    .trim_start_matches(|c: char| c.is_whitespace() && c != '\n'),
+            );
+            if self.s.is_empty() || self.s.starts_with('\n') || self.lex_token() {
+                let msg = match count {
+                    1 => format!("unexpected character {:?}", first),
+                    _ => format!("unexpected characters starting with {:?}", first),
+                };
+                // 続くトークンのエラーより前に報告する
+                self.errors.insert(err_idx, Diagnostic::error(span, msg));
+                return;
             }
         }
+    }
 
-        // 1文字のトークンを調べる
-        let (token_str, rmn) = s.split_at(1);
-        if let Some(token) = match token_str.as_bytes()[0] {
-            b'+' => Some(Token::Add(Add::new(self.pos))),
-            b'-' => Some(Token::Sub(Sub::new(self.pos))),
-            b'*' => Some(Token::Mul(Mul::new(self.pos))),
-            b'/' => Some(Token::Div(Div::new(self.pos))),
-            b'<' => Some(Token::Lt(Lt::new(self.pos))),
-            b'>' => Some(Token::Gt(Gt::new(self.pos))),
-            b'=' => Some(Token::Assign(Assign::new(self.pos))),
-            b'(' => Some(Token::ParenLeft(ParenLeft::new(self.pos))),
-            b')' => Some(Token::ParenRight(ParenRight::new(self.pos))),
-            b'{' => Some(Token::BraceLeft(BraceLeft::new(self.pos))),
-            b'}' => Some(Token::BraceRight(BraceRight::new(self.pos))),
-            b';' => Some(Token::Semi(Semi::new(self.pos))),
-            b',' => Some(Token::Comma(Comma::new(self.pos))),
-            _ => None,
-        } {
-            self.update_s(rmn);
-            return Some(token);
+    /// 文字列リテラルを読み飛ばし、リテラル全体を1つのエラーとして報告する
+    fn lex_str(&mut self) {
+        let bytes = self.s.as_bytes();
+        let mut len = 1;
+        let msg = loop {
+            match bytes.get(len) {
+                Some(b'"') => {
+                    len += 1;
+                    break "string literals are not supported";
+                }
+                Some(b'\\') if bytes.get(len + 1).is_some_and(|c| *c != b'\n') => len += 2,
+                None | Some(b'\n') => break "missing terminating '\"' character",
+                Some(_) => len += 1,
+            }
+        };
+
+        let span = Span::new(self.pos, self.pos + len);
+        self.errors.push(Diagnostic::error(span, msg));
+        self.update_s(&self.s[len..]);
+    }
+
+    /// 整数リテラルか浮動小数点数リテラルを読む
+    fn lex_num(&mut self) {
+        let (literal, rmn, result) = match split_float(self.s) {
+            Some((literal, rmn)) => {
+                let result = parse_float_literal(literal).map(|(num, ty)| (num, Type::Float(ty)));
+                (literal, rmn, result)
+            }
+            None => {
+                let (literal, rmn) = split_num(self.s);
+                let result = parse_int_literal(literal).map(|(num, ty)| (num, Type::Int(ty)));
+                (literal, rmn, result)
+            }
+        };
+        let span = Span::new(self.pos, self.pos + literal.len());
+
+        match result {
+            Ok((num, ty)) => {
+                let token = Token::Num(Num::new(num, ty, self.pos, literal.len()));
+                self.tokens.push(token);
+            }
+            Err(msg) => self.errors.push(Diagnostic::error(span, msg)),
         }
+        self.update_s(rmn);
+    }
 
-        // 数値リテラルを調べる
-        if let Some((digit, rmn)) = split_digit(s) {
-            let token = Token::Num(Num::new(digit, self.pos));
-            self.update_s(rmn);
-            return Some(token);
+    /// 文字リテラルを読む
+    fn lex_char(&mut self) {
+        match parse_char_literal(self.s) {
+            Ok((num, len)) => {
+                let token = Token::Num(Num::new(num, Type::Int(IntType::Int), self.pos, len));
+                self.tokens.push(token);
+                self.update_s(&self.s[len..]);
+            }
+            Err((msg, len)) => {
+                let span = Span::new(self.pos, self.pos + len);
+                self.errors.push(Diagnostic::error(span, msg));
+                self.update_s(&self.s[len..]);
+            }
         }
+    }
 
-        // キーワード/識別子を調べる
-        let (token_str, rmn) = split_delim(s);
+    /// キーワード/識別子を読む
+    fn lex_ident(&mut self) {
+        let idx = self
+            .s
+            .find(|c| !is_ident_continue(c))
+            .unwrap_or(self.s.len());
+        let (token_str, rmn) = self.s.split_at(idx);
         let token = match token_str {
             "return" => Token::Return(Return::new(self.pos)),
             "if" => Token::If(If::new(self.pos)),
             "else" => Token::Else(Else::new(self.pos)),
             "while" => Token::While(While::new(self.pos)),
+            "char" => Token::Char(Char::new(self.pos)),
+            "short" => Token::Short(Short::new(self.pos)),
+            "int" => Token::Int(Int::new(self.pos)),
+            "long" => Token::Long(Long::new(self.pos)),
+            "signed" => Token::Signed(Signed::new(self.pos)),
+            "unsigned" => Token::Unsigned(Unsigned::new(self.pos)),
+            "float" => Token::Float(Float::new(self.pos)),
+            "double" => Token::Double(Double::new(self.pos)),
+            "extern" => Token::Extern(Extern::new(self.pos)),
+            "static" => Token::Static(Static::new(self.pos)),
+            "struct" => Token::Struct(Struct::new(self.pos)),
+            "const" => Token::Const(Const::new(self.pos)),
+            "volatile" => Token::Volatile(Volatile::new(self.pos)),
+            "void" => Token::Void(Void::new(self.pos)),
             ident => Token::Ident(Ident::new(ident, self.pos)),
         };
+        self.tokens.push(token);
         self.update_s(rmn);
-        return Some(token);
+    }
+
+    /// 記号を読む。
+    /// 既知の記号でなければ何もせずに `false` を返す。
+    fn lex_punct(&mut self) -> bool {
+        let s = self.s;
+
+        // 3文字の記号を調べる
+        if let Some(rmn) = s.strip_prefix("...") {
+            self.tokens.push(Token::Ellipsis(Ellipsis::new(self.pos)));
+            self.update_s(rmn);
+            return true;
+        }
+
+        // 2文字の演算子を調べる
+        let token = match s.get(..2).unwrap_or("") {
+            "<=" => Some(Token::Lte(Lte::new(self.pos))),
+            ">=" => Some(Token::Gte(Gte::new(self.pos))),
+            "==" => Some(Token::Eq(Eq::new(self.pos))),
+            "!=" => Some(Token::Neq(Neq::new(self.pos))),
+            _ => None,
+        };
+        if let Some(token) = token {
+            self.tokens.push(token);
+            self.update_s(&s[2..]);
+            return true;
+        }
+
+        // 1文字のトークンを調べる
+        let token = match s.as_bytes()[0] {
+            b'+' => Token::Add(Add::new(self.pos)),
+            b'-' => Token::Sub(Sub::new(self.pos)),
+            b'*' => Token::Mul(Mul::new(self.pos)),
+            b'/' => Token::Div(Div::new(self.pos)),
+            b'<' => Token::Lt(Lt::new(self.pos)),
+            b'>' => Token::Gt(Gt::new(self.pos)),
+            b'=' => Token::Assign(Assign::new(self.pos)),
+            b'&' => Token::Amp(Amp::new(self.pos)),
+            b'(' => Token::ParenLeft(ParenLeft::new(self.pos)),
+            b')' => Token::ParenRight(ParenRight::new(self.pos)),
+            b'{' => Token::BraceLeft(BraceLeft::new(self.pos)),
+            b'}' => Token::BraceRight(BraceRight::new(self.pos)),
+            b'[' => Token::BracketLeft(BracketLeft::new(self.pos)),
+            b']' => Token::BracketRight(BracketRight::new(self.pos)),
+            b';' => Token::Semi(Semi::new(self.pos)),
+            b',' => Token::Comma(Comma::new(self.pos)),
+            b'.' => Token::Dot(Dot::new(self.pos)),
+            _ => return false,
+        };
+        self.tokens.push(token);
+        self.update_s(&s[1..]);
+        true
     }
 }
 
-// 先頭から数値を読み込む
-// "42world" -> (42, "world")
-fn split_digit(s: &str) -> Option<(usize, &str)> {
-    let first_non_num_idx = s.find(|c| !char::is_digit(c, 10)).unwrap_or(s.len());
-    if first_non_num_idx == 0 {
-        None
-    } else {
-        let (digit_s, rmn) = s.split_at(first_non_num_idx);
-        Some((usize::from_str_radix(digit_s, 10).unwrap(), rmn))
+/// 識別子の先頭に使える文字かどうか
+pub(crate) fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+/// 識別子の2文字目以降に使える文字かどうか
+pub(crate) fn is_ident_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// 先頭から整数リテラルと思われる部分を切り出す。
+// 数字の後に続く英数字や桁区切り文字も含める。
+// "0x2au;" -> ("0x2au", ";")
+fn split_num(s: &str) -> (&str, &str) {
+    let bytes = s.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let c = bytes[idx];
+        let is_separator = c == b'\''
+            && bytes
+                .get(idx + 1)
+                .is_some_and(|next| next.is_ascii_alphanumeric());
+        if c.is_ascii_alphanumeric() || c == b'_' || is_separator {
+            idx += 1;
+        } else {
+            break;
+        }
     }
+    s.split_at(idx)
 }
 
-// 特定のdelimiterで区切った文字列を返す。
-// delimiterは、
-// - whitespace, "{", "}", "(", ")"
-fn split_delim(s: &str) -> (&str, &str) {
-    assert!(s.len() != 0);
+// 先頭から浮動小数点数リテラルを切り出す。
+// 小数点も指数部も含まない場合は整数リテラルなので `None` を返す。
+// 16進数の浮動小数点数リテラルは扱わない。
+// "1.5e3f;" -> Some(("1.5e3f", ";"))
+pub(crate) fn split_float(s: &str) -> Option<(&str, &str)> {
+    let bytes = s.as_bytes();
+    let skip_digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
 
-    let delimiters = [
-        ' ', '{', '}', '(', ')', '=', ';', ',', '+', '-', '*', '/', '<', '>',
-    ];
+    if s.starts_with("0x") || s.starts_with("0X") {
+        return None;
+    }
+
+    let int_end = skip_digits(0);
+    let mut idx = int_end;
+    let mut is_float = false;
 
-    let idx = s.find(&delimiters[..]).unwrap_or(s.len());
-    if idx == 0 {
-        // '{' などを返す
-        s.split_at(1)
+    // 小数部
+    if bytes.get(idx) == Some(&b'.') {
+        let frac_end = skip_digits(idx + 1);
+        if int_end == 0 && frac_end == idx + 1 {
+            return None;
+        }
+        idx = frac_end;
+        is_float = true;
+    }
+
+    // 指数部
+    if matches!(bytes.get(idx), Some(b'e' | b'E')) {
+        let mut exp_start = idx + 1;
+        if matches!(bytes.get(exp_start), Some(b'+' | b'-')) {
+            exp_start += 1;
+        }
+        let exp_end = skip_digits(exp_start);
+        if exp_end > exp_start {
+            idx = exp_end;
+            is_float = true;
+        }
+    }
+
+    if !is_float {
+        return None;
+    }
+
+    // 接尾辞も含める
+    idx += bytes[idx..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+        .count();
+    Some(s.split_at(idx))
+}
+
+// 浮動小数点数リテラルを解釈し、その値のビット列と型を返す。
+// "1.5f" -> (0x3fc00000, float)
+fn parse_float_literal(literal: &str) -> Result<(u64, FloatType), String> {
+    let body_len = literal
+        .rfind(|c: char| c.is_ascii_digit() || c == '.')
+        .map_or(0, |idx| idx + 1);
+    let (body, suffix) = literal.split_at(body_len);
+
+    let ty = match suffix {
+        "" => FloatType::Double,
+        "f" | "F" => FloatType::Float,
+        "l" | "L" => return Err("\"long double\" is not supported".to_string()),
+        _ => return Err(format!("invalid suffix \"{}\" on floating literal", suffix)),
+    };
+
+    // float は直接その精度で丸める
+    let num = match ty {
+        FloatType::Float => body.parse::<f32>().map(|v| v.to_bits() as u64),
+        FloatType::Double => body.parse::<f64>().map(f64::to_bits),
+    };
+    let num = num.map_err(|_| format!("invalid floating literal \"{}\"", literal))?;
+    Ok((num, ty))
+}
+
+// 整数リテラルを解釈し、その値と型を返す。
+// "0x2au" -> (42, unsigned int)
+pub(crate) fn parse_int_literal(literal: &str) -> Result<(u64, IntType), String> {
+    let lower = literal.to_ascii_lowercase();
+    let (radix, body) = if let Some(body) = lower.strip_prefix("0x") {
+        (16, body)
+    } else if let Some(body) = lower.strip_prefix("0b") {
+        (2, body)
+    } else if lower.starts_with('0') && lower[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        (8, &lower[1..])
     } else {
-        s.split_at(idx)
+        (10, lower.as_str())
+    };
+
+    // 数字部分と接尾辞を分ける。
+    // 8進数の場合は不正な数字を見つけるため、10進数の数字までを数字部分とみなす。
+    let digit_radix = if radix == 8 { 10 } else { radix };
+    let digits_len = body
+        .find(|c: char| !c.is_digit(digit_radix) && c != '\'')
+        .unwrap_or(body.len());
+    let (digits, suffix) = body.split_at(digits_len);
+
+    if digits.is_empty() {
+        return Err(format!("invalid integer literal \"{}\"", literal));
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_digit(radix) && *c != '\'') {
+        return Err(format!("invalid digit '{}' in octal literal", c));
+    }
+    if digits.starts_with('\'') || digits.ends_with('\'') || digits.contains("''") {
+        return Err("digit separator must appear between digits".to_string());
+    }
+
+    // 接尾辞は元の表記で判定する。"lL" のように大文字と小文字の混ざった "ll" は使えない
+    let suffix = &literal[literal.len() - suffix.len()..];
+    let (unsigned, long_suffix) = match suffix
+        .strip_prefix(['u', 'U'])
+        .or_else(|| suffix.strip_suffix(['u', 'U']))
+    {
+        Some(long_suffix) => (true, long_suffix),
+        None => (false, suffix),
+    };
+    let long = match long_suffix {
+        "" => false,
+        "l" | "L" | "ll" | "LL" => true,
+        _ => return Err(format!("invalid suffix \"{}\" on integer literal", suffix)),
+    };
+
+    let num = digits
+        .chars()
+        .filter(|c| *c != '\'')
+        .try_fold(0u64, |acc, c| {
+            acc.checked_mul(radix as u64)?
+                .checked_add(c.to_digit(radix).unwrap() as u64)
+        })
+        .ok_or_else(|| format!("integer literal \"{}\" is too large", literal))?;
+
+    // 値が収まる最初の型をリテラルの型とする。
+    // 10進数のリテラルは接尾辞が無い限り符号なしの型にはならない。
+    let candidates: &[IntType] = match (unsigned, long, radix == 10) {
+        (false, false, true) => &[IntType::Int, IntType::Long],
+        (false, false, false) => &[IntType::Int, IntType::UInt, IntType::Long, IntType::ULong],
+        (true, false, _) => &[IntType::UInt, IntType::ULong],
+        (false, true, true) => &[IntType::Long],
+        (false, true, false) => &[IntType::Long, IntType::ULong],
+        (true, true, _) => &[IntType::ULong],
+    };
+
+    candidates
+        .iter()
+        .find(|ty| num <= ty.max_value())
+        .map(|ty| (num, *ty))
+        .ok_or_else(|| format!("integer literal \"{}\" is too large", literal))
+}
+
+// 先頭から文字リテラルを読み込み、その値とリテラルの長さを返す。
+// エラーの場合はメッセージと読み飛ばすべき長さを返す。
+// "'a' + 1" -> (97, 3)
+pub(crate) fn parse_char_literal(s: &str) -> Result<(u64, usize), (String, usize)> {
+    // 閉じる "'" を探す。同じ行に無ければエラー
+    let mut chars = s.char_indices().skip(1);
+    let mut body_end = None;
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => {
+                let _ = chars.next();
+            }
+            '\'' => {
+                body_end = Some(idx);
+                break;
+            }
+            '\n' => break,
+            _ => {}
+        }
     }
+    let body_end = match body_end {
+        Some(idx) => idx,
+        None => {
+            let len = s.find('\n').unwrap_or(s.len());
+            return Err(("unterminated character literal".to_string(), len));
+        }
+    };
+    let len = body_end + 1;
+    let body = &s[1..body_end];
+
+    let num = match body.as_bytes() {
+        [] => return Err(("empty character literal".to_string(), len)),
+        [c] if c.is_ascii() => *c as u64,
+        [b'\\', escape @ ..] => match parse_escape(escape) {
+            Some(num) => num,
+            None => {
+                let msg = format!("invalid escape sequence \"{}\"", body);
+                return Err((msg, len));
+            }
+        },
+        _ => {
+            let msg = "character literal must be a single ASCII character".to_string();
+            return Err((msg, len));
+        }
+    };
+
+    Ok((num, len))
+}
+
+// "\" に続くエスケープシーケンスを解釈する。
+// "x41" -> 65
+fn parse_escape(escape: &[u8]) -> Option<u64> {
+    let num = match escape {
+        [b'n'] => b'\n',
+        [b't'] => b'\t',
+        [b'r'] => b'\r',
+        [b'a'] => 0x07,
+        [b'b'] => 0x08,
+        [b'f'] => 0x0c,
+        [b'v'] => 0x0b,
+        [b'\\'] => b'\\',
+        [b'\''] => b'\'',
+        [b'"'] => b'"',
+        [b'?'] => b'?',
+        [b'x', hex @ ..] if !hex.is_empty() && hex.len() <= 2 => {
+            let hex = std::str::from_utf8(hex).ok()?;
+            u8::from_str_radix(hex, 16).ok()?
+        }
+        oct if !oct.is_empty() && oct.len() <= 3 => {
+            let oct = std::str::from_utf8(oct).ok()?;
+            u8::from_str_radix(oct, 8).ok()?
+        }
+        _ => return None,
+    };
+    Some(num as u64)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::token::SourceMap;
     use TokenKind as Kind;
 
-    fn assert_kind<'src>(input: &'src str, expected: Vec<Kind>) {
-        let found = tokenize(input)
-            .map(|token| token.kind())
-            .collect::<Vec<_>>();
-        assert_eq!(found, expected);
+    fn lex(input: &str) -> Result<Vec<Kind>, Vec<(u32, u32)>> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        match tokenize(source_map.file(file)) {
+            Ok(tokens) => Ok(TokenStream::new(&tokens)
+                .map(|token| token.kind())
+                .collect()),
+            Err(e) => Err(e
+                .diagnostics
+                .iter()
+                .map(|d| (d.span.start, d.span.end))
+                .collect()),
+        }
+    }
+
+    fn assert_kind(input: &str, expected: Vec<Kind>) {
+        assert_eq!(lex(input), Ok(expected));
     }
 
     #[test]
@@ -166,5 +633,197 @@ mod tests {
             vec![Kind::ParenRight, Kind::Else, Kind::Ident],
         );
         assert_kind("while (", vec![Kind::While, Kind::ParenLeft]);
+        assert_kind(
+            "unsigned long int",
+            vec![Kind::Unsigned, Kind::Long, Kind::Int],
+        );
+        assert_kind("integer", vec![Kind::Ident]);
+        assert_kind("extern int", vec![Kind::Extern, Kind::Int]);
+        assert_kind("static int", vec![Kind::Static, Kind::Int]);
+        assert_kind("1.5+.5", vec![Kind::Num, Kind::Add, Kind::Num]);
+        assert_kind("float double", vec![Kind::Float, Kind::Double]);
+        assert_kind("void", vec![Kind::Void]);
+        assert_kind(
+            "a[1].x",
+            vec![
+                Kind::Ident,
+                Kind::BracketLeft,
+                Kind::Num,
+                Kind::BracketRight,
+                Kind::Dot,
+                Kind::Ident,
+            ],
+        );
+        assert_kind("struct P", vec![Kind::Struct, Kind::Ident]);
+        assert_kind(
+            "const volatile int",
+            vec![Kind::Const, Kind::Volatile, Kind::Int],
+        );
+        assert_kind(
+            "f(1, ...)",
+            vec![
+                Kind::Ident,
+                Kind::ParenLeft,
+                Kind::Num,
+                Kind::Comma,
+                Kind::Ellipsis,
+                Kind::ParenRight,
+            ],
+        );
+        assert_kind(
+            "(int,...)",
+            vec![
+                Kind::ParenLeft,
+                Kind::Int,
+                Kind::Comma,
+                Kind::Ellipsis,
+                Kind::ParenRight,
+            ],
+        );
+    }
+
+    #[test]
+    fn whitespace_test() {
+        assert_kind("return\n42;", vec![Kind::Return, Kind::Num, Kind::Semi]);
+        assert_kind("a\t=\r\n1", vec![Kind::Ident, Kind::Assign, Kind::Num]);
+        assert_kind("a\u{3000}b", vec![Kind::Ident, Kind::Ident]);
+    }
+
+    fn num(input: &str) -> Result<(u64, IntType), String> {
+        parse_int_literal(input)
+    }
+
+    #[test]
+    fn int_literal_test() {
+        assert_eq!(num("0"), Ok((0, IntType::Int)));
+        assert_eq!(num("42"), Ok((42, IntType::Int)));
+        assert_eq!(num("0x2A"), Ok((42, IntType::Int)));
+        assert_eq!(num("052"), Ok((42, IntType::Int)));
+        assert_eq!(num("0b101010"), Ok((42, IntType::Int)));
+        assert_eq!(num("1'000'000"), Ok((1_000_000, IntType::Int)));
+        assert_eq!(num("0u"), Ok((0, IntType::UInt)));
+        assert_eq!(num("42l"), Ok((42, IntType::Long)));
+        assert_eq!(num("42LL"), Ok((42, IntType::Long)));
+        assert_eq!(num("42ul"), Ok((42, IntType::ULong)));
+        assert_eq!(num("42LLU"), Ok((42, IntType::ULong)));
+
+        // 値の大きさによって型が決まる
+        assert_eq!(num("2147483647"), Ok((2147483647, IntType::Int)));
+        assert_eq!(num("2147483648"), Ok((2147483648, IntType::Long)));
+        assert_eq!(num("0xffffffff"), Ok((0xffffffff, IntType::UInt)));
+        assert_eq!(num("0x100000000"), Ok((0x100000000, IntType::Long)));
+        assert_eq!(num("0xffffffffffffffff"), Ok((u64::MAX, IntType::ULong)));
+        assert_eq!(num("4294967295u"), Ok((4294967295, IntType::UInt)));
+        assert_eq!(num("4294967296u"), Ok((4294967296, IntType::ULong)));
+
+        assert!(num("9223372036854775808").is_err());
+        assert!(num("18446744073709551616u").is_err());
+        assert!(num("0x").is_err());
+        assert!(num("09").is_err());
+        assert!(num("0b12").is_err());
+        assert!(num("42world").is_err());
+        assert!(num("42lul").is_err());
+        assert_eq!(num("42Lu"), Ok((42, IntType::ULong)));
+        assert_eq!(num("42uLL"), Ok((42, IntType::ULong)));
+        assert!(num("42lL").is_err());
+        assert!(num("42Ll").is_err());
+        assert!(num("42ulL").is_err());
+        assert!(num("42uu").is_err());
+        assert!(num("1''0").is_err());
+        assert!(num("1'").is_err());
+    }
+
+    #[test]
+    fn float_literal_test() {
+        let float = |input| parse_float_literal(input);
+        assert_eq!(float("1.5"), Ok((1.5f64.to_bits(), FloatType::Double)));
+        assert_eq!(float("1."), Ok((1.0f64.to_bits(), FloatType::Double)));
+        assert_eq!(float(".25"), Ok((0.25f64.to_bits(), FloatType::Double)));
+        assert_eq!(float("1e3"), Ok((1000.0f64.to_bits(), FloatType::Double)));
+        assert_eq!(float("2.5E-1"), Ok((0.25f64.to_bits(), FloatType::Double)));
+        assert_eq!(
+            float("0.1f"),
+            Ok((0.1f32.to_bits() as u64, FloatType::Float))
+        );
+        assert!(float("1.5l").is_err());
+        assert!(float("1.5ff").is_err());
+        assert!(float("1.5e").is_err());
+    }
+
+    #[test]
+    fn split_float_test() {
+        assert_eq!(split_float("1.5;"), Some(("1.5", ";")));
+        assert_eq!(split_float(".5+1"), Some((".5", "+1")));
+        assert_eq!(split_float("1e-3*2"), Some(("1e-3", "*2")));
+        assert_eq!(split_float("1.5f)"), Some(("1.5f", ")")));
+        assert_eq!(split_float("1.5e)"), Some(("1.5e", ")")));
+        assert_eq!(split_float("42;"), None);
+        assert_eq!(split_float("1e;"), None);
+        assert_eq!(split_float("0x1.8p1"), None);
+    }
+
+    #[test]
+    fn split_num_test() {
+        assert_eq!(split_num("42;"), ("42", ";"));
+        assert_eq!(split_num("0x2au+1"), ("0x2au", "+1"));
+        assert_eq!(split_num("1'000'000 "), ("1'000'000", " "));
+        assert_eq!(split_num("42world"), ("42world", ""));
+        assert_eq!(split_num("1'"), ("1", "'"));
+    }
+
+    #[test]
+    fn char_literal_test() {
+        assert_eq!(parse_char_literal("'a'"), Ok((97, 3)));
+        assert_eq!(parse_char_literal("'\\n' + 1"), Ok((10, 4)));
+        assert_eq!(parse_char_literal("'\\''"), Ok((39, 4)));
+        assert_eq!(parse_char_literal("'\\x41'"), Ok((65, 6)));
+        assert_eq!(parse_char_literal("'\\0'"), Ok((0, 4)));
+        assert_eq!(parse_char_literal("'\\101'"), Ok((65, 6)));
+        assert!(parse_char_literal("''").is_err());
+        assert!(parse_char_literal("'ab'").is_err());
+        assert!(parse_char_literal("'\\q'").is_err());
+        assert!(parse_char_literal("'a\nb'").is_err());
+        assert!(parse_char_literal("'あ'").is_err());
+    }
+
+    #[test]
+    fn literal_error_pos_test() {
+        assert_eq!(lex("a = 99999999999999999999;"), Err(vec![(4, 24)]));
+        assert_eq!(lex("a = 'xy' + 08;"), Err(vec![(4, 8), (11, 13)]));
+        assert_eq!(lex("a = 'x"), Err(vec![(4, 6)]));
+    }
+
+    #[test]
+    fn unexpected_char_test() {
+        assert_eq!(lex("a!b"), Err(vec![(1, 2)]));
+        assert_eq!(lex("@"), Err(vec![(0, 1)]));
+        assert_eq!(lex("a = $1 # 2;"), Err(vec![(4, 5), (7, 8)]));
+        assert_eq!(lex("あ = 1;"), Err(vec![(0, 3)]));
+        assert_eq!(lex("a = 1; // é"), Err(vec![(10, 12)]));
+
+        // 続けて現れる不正な文字は1つのエラーになる
+        assert_eq!(lex("a @@$b"), Err(vec![(2, 5)]));
+        assert_eq!(lex("@ @\t@\n@"), Err(vec![(0, 5), (6, 7)]));
+        assert_eq!(lex("@@08"), Err(vec![(0, 2), (2, 4)]));
+        assert_eq!(lex("a = \"%d\\n\" + 1;"), Err(vec![(4, 10)]));
+        assert_eq!(lex("a = \"x\\\"y\";"), Err(vec![(4, 10)]));
+        assert_eq!(lex("a = \"x\nb;"), Err(vec![(4, 6)]));
+    }
+
+    #[test]
+    fn max_errors_test() {
+        let input = "@;".repeat(500);
+        let errors = lex(&input).unwrap_err();
+        // 上限に達したことを知らせるエラーが1つ追加される
+        assert_eq!(errors.len(), DEFAULT_MAX_ERRORS + 1);
+
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("test", input);
+        let e = tokenize_with_max_errors(source_map.file(file), 2).unwrap_err();
+        assert_eq!(e.diagnostics.len(), 3);
+        assert_eq!(
+            e.diagnostics[2].msg,
+            "too many errors emitted, stopping now"
+        );
     }
 }
@@ -0,0 +1,292 @@
+use super::{pos::Pos, span::Span};
+use crate::ty::Type;
+
+macro_rules! token {
+    (
+    $(#[$container_meta:meta])*
+    pub enum Token<'src> {
+    $(
+        $(#[$variant_meta:meta])*
+        $variant:ident ($struct:ident $(<$lt:lifetime>)?) as $display:expr,
+    )*
+    }) => {
+        $(#[$container_meta])*
+        pub enum Token<'src> {
+        $(
+            $(#[$variant_meta])*
+            $variant($struct $(<$lt>)?),
+        )*
+        }
+
+        impl<'src> Token<'src> {
+            pub fn kind(&self) -> TokenKind {
+                match self {
+                $(
+                    Token::$variant(_) => TokenKind::$variant,
+                )*
+                }
+            }
+
+            pub fn display(&self) -> &'static str {
+                match self {
+                $(
+                    Token::$variant(_) => $display,
+                )*
+                }
+            }
+
+            pub fn pos(&self) -> Pos {
+                match self {
+                $(
+                    Token::$variant(token) => token.pos,
+                )*
+                }
+            }
+
+            pub fn span(&self) -> Span {
+                match self {
+                $(
+                    Token::$variant(token) => token.span(),
+                )*
+                }
+            }
+        }
+
+        impl<'src> std::fmt::Display for Token<'src> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+                write!(f, "{}", self.display())
+            }
+        }
+
+        $(
+        impl<'src> From<$struct $(<$lt>)?> for Token<'src> {
+            fn from(token: $struct $(<$lt>)?) -> Self {
+                Token::$variant(token)
+            }
+        }
+        )*
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum TokenKind {
+        $(
+            $variant,
+        )*
+        }
+
+        impl TokenKind {
+            pub fn display(&self) -> &'static str {
+                match self {
+                $(
+                    TokenKind::$variant => $display,
+                )*
+                }
+            }
+        }
+
+        impl std::fmt::Display for TokenKind {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+                write!(f, "{}", self.display())
+            }
+        }
+
+        $(
+        impl $(<$lt>)? $struct $(<$lt>)? {
+            pub fn display() -> &'static str {
+                $display
+            }
+        }
+        )*
+
+    };
+}
+
+token! {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'src> {
+    /// "+"
+    Add(Add) as "+",
+    /// "-"
+    Sub(Sub) as "-",
+    /// "*"
+    Mul(Mul) as "*",
+    /// "/"
+    Div(Div) as "/",
+    /// "<"
+    Lt(Lt) as "<",
+    /// "<="
+    Lte(Lte) as "<=",
+    /// ">"
+    Gt(Gt) as ">",
+    /// ">="
+    Gte(Gte) as ">=",
+    /// "=="
+    Eq(Eq) as "==",
+    /// "!="
+    Neq(Neq) as "!=",
+    /// "="
+    Assign(Assign) as "=",
+    /// "&"
+    Amp(Amp) as "&",
+
+    /// "("
+    ParenLeft(ParenLeft) as "(",
+    /// ")"
+    ParenRight(ParenRight) as ")",
+    /// "{"
+    BraceLeft(BraceLeft) as "{",
+    /// "}"
+    BraceRight(BraceRight) as "}",
+    /// "["
+    BracketLeft(BracketLeft) as "[",
+    /// "]"
+    BracketRight(BracketRight) as "]",
+
+    /// 整数リテラル、文字リテラル
+    Num(Num) as "number",
+
+    /// 識別子（変数名とか）
+    Ident(Ident<'src>) as "identifier",
+
+    /// "return" keyword
+    Return(Return) as "return",
+    /// "if" keyword
+    If(If) as "if",
+    /// "else" keyword
+    Else(Else) as "else",
+    /// "while" keyword
+    While(While) as "while",
+    /// "char" keyword
+    Char(Char) as "char",
+    /// "short" keyword
+    Short(Short) as "short",
+    /// "int" keyword
+    Int(Int) as "int",
+    /// "long" keyword
+    Long(Long) as "long",
+    /// "signed" keyword
+    Signed(Signed) as "signed",
+    /// "unsigned" keyword
+    Unsigned(Unsigned) as "unsigned",
+    /// "float" keyword
+    Float(Float) as "float",
+    /// "double" keyword
+    Double(Double) as "double",
+    /// "extern" keyword
+    Extern(Extern) as "extern",
+    /// "static" keyword
+    Static(Static) as "static",
+    /// "struct" keyword
+    Struct(Struct) as "struct",
+    /// "const" keyword
+    Const(Const) as "const",
+    /// "volatile" keyword
+    Volatile(Volatile) as "volatile",
+    /// "void" keyword
+    Void(Void) as "void",
+    /// ";"
+    Semi(Semi) as ";",
+    /// ","
+    Comma(Comma) as ",",
+    /// "."
+    Dot(Dot) as ".",
+    /// "..."
+    Ellipsis(Ellipsis) as "...",
+
+    /// ソースコードの終端
+    Eof(Eof) as "EOF",
+}
+}
+
+macro_rules! plain_token {
+    ($name:tt) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name {
+            pub pos: Pos,
+        }
+
+        impl $name {
+            pub fn new(pos: Pos) -> Self {
+                $name { pos }
+            }
+
+            pub fn span(&self) -> Span {
+                Span::new(self.pos, self.pos + Self::display().len())
+            }
+        }
+    };
+
+    ($name:tt $(, $rmn:tt )+) => {
+        plain_token!($name);
+        $(
+            plain_token!($rmn);
+        )+
+    }
+}
+
+plain_token!(Add, Sub, Mul, Div, Lt, Lte, Gt, Gte, Eq, Neq, Assign, Amp);
+plain_token!(
+    ParenLeft,
+    ParenRight,
+    BraceLeft,
+    BraceRight,
+    BracketLeft,
+    BracketRight
+);
+plain_token!(Return, If, Else, While, Semi, Comma, Dot, Ellipsis);
+plain_token!(Char, Short, Int, Long, Signed, Unsigned, Float, Double);
+plain_token!(Extern, Static, Struct, Const, Volatile, Void);
+
+/// ソースコードの終端を表す。
+/// 長さを持たない。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eof {
+    pub pos: Pos,
+}
+
+impl Eof {
+    pub fn new(pos: Pos) -> Self {
+        Eof { pos }
+    }
+
+    pub fn span(&self) -> Span {
+        Span::from(self.pos)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Num {
+    pub num: u64,
+    /// リテラルの型。
+    /// 値の大きさ、接尾辞、基数から決まる。
+    /// 浮動小数点数リテラルの場合、`num` は値のビット列になる。
+    pub ty: Type,
+    pub pos: Pos,
+    /// ソースコード上での数値リテラルの長さ
+    pub len: usize,
+}
+
+impl Num {
+    pub fn new(num: u64, ty: Type, pos: Pos, len: usize) -> Self {
+        Num { num, ty, pos, len }
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.pos, self.pos + self.len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ident<'src> {
+    pub name: &'src str,
+    pub pos: Pos,
+}
+
+impl<'src> Ident<'src> {
+    pub fn new(name: &'src str, pos: Pos) -> Self {
+        Ident { name, pos }
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.pos, self.pos + self.name.len())
+    }
+}
@@ -0,0 +1,619 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+};
+
+/// 型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// 値を持たない型。関数の返り値の型と、値を捨てるキャストにだけ使える
+    Void,
+    Int(IntType),
+    Float(FloatType),
+    /// 関数へのポインタ。
+    /// 関数名を式として使った場合もこの型になる。
+    FuncPtr(&'static FuncType),
+    /// 関数以外を指すポインタ
+    Ptr(&'static PtrType),
+    /// System V ABI の `va_list`。
+    /// 要素数1の構造体の配列なので、値として使うと先頭のアドレスになる。
+    VaList,
+    /// 配列。値として使うと先頭のアドレスになる。
+    Array(&'static ArrayType),
+    /// 構造体。値として使うと先頭のアドレスになる。
+    Struct(&'static StructType),
+}
+
+impl Type {
+    /// 型のサイズ（バイト数）
+    pub fn size(&self) -> usize {
+        match self {
+            Type::Void => 0,
+            Type::Int(ty) => ty.size(),
+            Type::Float(ty) => ty.size(),
+            Type::FuncPtr(_) | Type::Ptr(_) => 8,
+            Type::VaList => 24,
+            Type::Array(ty) => ty.elem.size() * ty.len.unwrap_or(0),
+            Type::Struct(ty) => ty.size,
+        }
+    }
+
+    /// 型のアラインメント（バイト数）
+    pub fn align(&self) -> usize {
+        match self {
+            Type::Int(_) | Type::Float(_) | Type::FuncPtr(_) | Type::Ptr(_) => self.size(),
+            Type::Void => 1,
+            Type::VaList => 8,
+            Type::Array(ty) => ty.elem.align(),
+            Type::Struct(ty) => ty.align,
+        }
+    }
+
+    /// 集成体型（配列か構造体）かどうか。
+    /// 集成体型の変数は波括弧で囲まれた初期化子で初期化する。
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Type::Array(_) | Type::Struct(_))
+    }
+
+    /// 整数型であればその型を返す
+    pub fn as_int(&self) -> Option<IntType> {
+        match self {
+            Type::Int(ty) => Some(*ty),
+            Type::Void
+            | Type::Float(_)
+            | Type::FuncPtr(_)
+            | Type::Ptr(_)
+            | Type::VaList
+            | Type::Array(_)
+            | Type::Struct(_) => None,
+        }
+    }
+
+    /// 浮動小数点数型であればその型を返す
+    pub fn as_float(&self) -> Option<FloatType> {
+        match self {
+            Type::Float(ty) => Some(*ty),
+            Type::Void
+            | Type::Int(_)
+            | Type::FuncPtr(_)
+            | Type::Ptr(_)
+            | Type::VaList
+            | Type::Array(_)
+            | Type::Struct(_) => None,
+        }
+    }
+
+    /// 算術型（整数型か浮動小数点数型）かどうか
+    pub fn is_arith(&self) -> bool {
+        matches!(self, Type::Int(_) | Type::Float(_))
+    }
+
+    /// スカラ型（算術型かポインタ型）かどうか。
+    /// スカラ型の値は条件式に使える。
+    pub fn is_scalar(&self) -> bool {
+        self.is_arith() || self.is_ptr()
+    }
+
+    /// ポインタ型（関数へのポインタを含む）かどうか
+    pub fn is_ptr(&self) -> bool {
+        matches!(self, Type::FuncPtr(_) | Type::Ptr(_))
+    }
+
+    /// 算術演算を行う際の型を返す。
+    /// どちらかが浮動小数点数型であれば、大きい方の浮動小数点数型に揃える。
+    /// 両方が整数型であれば `IntType::common` に従う。
+    /// 算術型でないオペランドがある場合は `None` を返す。
+    pub fn common(self, other: Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Int(lhs), Type::Int(rhs)) => Some(Type::Int(lhs.common(rhs))),
+            (Type::Float(lhs), Type::Float(rhs)) => Some(Type::Float(lhs.max(rhs))),
+            (Type::Float(ty), Type::Int(_)) | (Type::Int(_), Type::Float(ty)) => {
+                Some(Type::Float(ty))
+            }
+            _ => None,
+        }
+    }
+
+    /// 修飾子を付けた型名を返す。
+    /// ポインタ自体に付く修飾子は `int *const` のように "*" の後に置く。
+    pub fn qualified(self, quals: Qualifiers) -> String {
+        match self {
+            Type::Ptr(_) => format!("{}{}", self, quals).trim_end().to_string(),
+            _ => format!("{}{}", quals, self),
+        }
+    }
+
+    /// 既定の実引数拡張を行った型を返す。
+    /// 整数型は整数拡張され、`float` は `double` になる。
+    pub fn promoted(self) -> Type {
+        match self {
+            Type::Int(ty) => Type::Int(ty.promoted()),
+            Type::Float(_) => Type::Float(FloatType::Double),
+            ty @ (Type::Void
+            | Type::FuncPtr(_)
+            | Type::Ptr(_)
+            | Type::VaList
+            | Type::Array(_)
+            | Type::Struct(_)) => ty,
+        }
+    }
+}
+
+/// C言語での型名
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Type::Void => write!(f, "void"),
+            Type::Int(ty) => write!(f, "{}", ty),
+            Type::Float(ty) => write!(f, "{}", ty),
+            Type::FuncPtr(ty) => {
+                write!(f, "{} (*)(", ty.ret_ty)?;
+                for (i, param_ty) in ty.param_tys.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param_ty)?;
+                }
+                if ty.is_variadic && !ty.param_tys.is_empty() {
+                    write!(f, ", ...")?;
+                }
+                write!(f, ")")
+            }
+            Type::Ptr(ty) => {
+                // `int *` と `int **` のように、指す先の型がポインタなら "*" を続ける
+                let pointee = ty.pointee.qualified(ty.quals);
+                if pointee.ends_with('*') {
+                    write!(f, "{}*", pointee)
+                } else {
+                    write!(f, "{} *", pointee)
+                }
+            }
+            Type::VaList => write!(f, "va_list"),
+            Type::Array(ty) => {
+                // 多次元配列は `int [2][3]` のように要素数を外側から並べる。
+                // ポインタの配列は `int *[2]` のように "*" の後に続ける
+                let mut elem = Type::Array(ty);
+                let mut dims = String::new();
+                while let Type::Array(ty) = elem {
+                    match ty.len {
+                        Some(len) => dims.push_str(&format!("[{}]", len)),
+                        None => dims.push_str("[]"),
+                    }
+                    elem = ty.elem;
+                }
+                match elem {
+                    Type::Ptr(_) => write!(f, "{}{}", elem, dims),
+                    _ => write!(f, "{} {}", elem, dims),
+                }
+            }
+            Type::Struct(ty) => match &ty.tag {
+                Some(tag) => write!(f, "struct {}", tag),
+                None => write!(f, "struct (anonymous)"),
+            },
+        }
+    }
+}
+
+/// 関数の型
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    pub ret_ty: Type,
+    pub param_tys: Vec<Type>,
+    /// 可変長引数を取るかどうか
+    pub is_variadic: bool,
+}
+
+thread_local! {
+    /// `FuncType::intern` で作られた関数の型
+    static FUNC_TYPES: RefCell<HashSet<&'static FuncType>> = RefCell::new(HashSet::new());
+}
+
+impl FuncType {
+    /// `Type` が `Copy` でいられるよう、関数の型を 'static な領域に置いて参照を返す。
+    /// 同じ型は同じ領域を共有する。
+    pub fn intern(self) -> &'static FuncType {
+        FUNC_TYPES.with(|types| {
+            let mut types = types.borrow_mut();
+            if let Some(ty) = types.get(&self) {
+                return *ty;
+            }
+            let ty: &'static FuncType = Box::leak(Box::new(self));
+            types.insert(ty);
+            ty
+        })
+    }
+
+    /// 宣言されていない関数の型。
+    /// `int` を返し、任意の引数を取るものとみなす。
+    pub fn implicit() -> &'static FuncType {
+        FuncType::unprototyped(Type::Int(IntType::Int))
+    }
+
+    /// "()" で宣言された、仮引数の型を宣言しない関数の型。
+    /// 任意の引数を取る可変長引数の関数として表す。
+    pub fn unprototyped(ret_ty: Type) -> &'static FuncType {
+        FuncType {
+            ret_ty,
+            param_tys: Vec::new(),
+            is_variadic: true,
+        }
+        .intern()
+    }
+
+    /// 仮引数の型が宣言されているかどうか。
+    /// 可変長引数の関数は名前のある仮引数を必要とするので、
+    /// 仮引数の無い可変長引数の関数は "()" で宣言されたものに限られる。
+    pub fn is_prototyped(&self) -> bool {
+        !(self.is_variadic && self.param_tys.is_empty())
+    }
+
+    /// 同じ関数の型とみなせるかどうか。
+    /// 仮引数の型が宣言されていない関数の型は、返り値の型が同じで、
+    /// 既定の実引数拡張で型の変わらない仮引数だけを取る関数の型とみなせる。
+    pub fn is_compatible(&self, other: &FuncType) -> bool {
+        if self.ret_ty != other.ret_ty {
+            return false;
+        }
+        match (self.is_prototyped(), other.is_prototyped()) {
+            (true, true) => self == other,
+            (false, false) => true,
+            (true, false) => {
+                !self.is_variadic && self.param_tys.iter().all(|ty| ty.promoted() == *ty)
+            }
+            (false, true) => other.is_compatible(self),
+        }
+    }
+}
+
+/// 関数以外を指すポインタの型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PtrType {
+    pub pointee: Type,
+    /// 指す先のオブジェクトの型修飾子。
+    /// "const int *" を通しては代入できない。
+    pub quals: Qualifiers,
+}
+
+thread_local! {
+    /// `PtrType::intern` で作られたポインタの型
+    static PTR_TYPES: RefCell<HashSet<&'static PtrType>> = RefCell::new(HashSet::new());
+}
+
+impl PtrType {
+    /// `FuncType::intern` と同様に、ポインタの型を 'static な領域に置いて参照を返す。
+    pub fn intern(self) -> &'static PtrType {
+        PTR_TYPES.with(|types| {
+            let mut types = types.borrow_mut();
+            if let Some(ty) = types.get(&self) {
+                return *ty;
+            }
+            let ty: &'static PtrType = Box::leak(Box::new(self));
+            types.insert(ty);
+            ty
+        })
+    }
+
+    /// `from` を暗黙に変換できるかどうか。
+    /// 指す先の型が同じで、型修飾子を減らさない場合に限る。
+    /// `void *` とは指す先の型によらず変換できる。
+    pub fn accepts(&self, from: &PtrType) -> bool {
+        self.quals.merge(from.quals) == self.quals
+            && (self.pointee == from.pointee
+                || self.pointee == Type::Void
+                || from.pointee == Type::Void)
+    }
+}
+
+/// 配列の型
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArrayType {
+    pub elem: Type,
+    /// 要素数。初期化子から推論される場合、推論されるまでは `None` になる。
+    pub len: Option<usize>,
+}
+
+thread_local! {
+    /// `ArrayType::intern` で作られた配列の型
+    static ARRAY_TYPES: RefCell<HashSet<&'static ArrayType>> = RefCell::new(HashSet::new());
+}
+
+impl ArrayType {
+    /// `FuncType::intern` と同様に、配列の型を 'static な領域に置いて参照を返す。
+    pub fn intern(self) -> &'static ArrayType {
+        ARRAY_TYPES.with(|types| {
+            let mut types = types.borrow_mut();
+            if let Some(ty) = types.get(&self) {
+                return *ty;
+            }
+            let ty: &'static ArrayType = Box::leak(Box::new(self));
+            types.insert(ty);
+            ty
+        })
+    }
+}
+
+/// 構造体の型
+///
+/// 構造体は定義ごとに別の型になるので、`id` で区別する。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StructType {
+    pub id: usize,
+    pub tag: Option<String>,
+    pub members: Vec<Member>,
+    pub size: usize,
+    pub align: usize,
+}
+
+/// 構造体のメンバ
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Member {
+    pub name: String,
+    pub ty: Type,
+    pub quals: Qualifiers,
+    /// 構造体の先頭からのオフセット（バイト数）
+    pub offset: usize,
+}
+
+thread_local! {
+    /// `StructType::new` で作られた構造体の数
+    static STRUCT_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+impl StructType {
+    /// メンバを順にアラインメントに従って配置した構造体の型を作る。
+    /// 構造体のサイズは最も大きいアラインメントの倍数に切り上げる。
+    pub fn new(
+        tag: Option<String>,
+        members: Vec<(String, Type, Qualifiers)>,
+    ) -> &'static StructType {
+        let mut size = 0;
+        let mut align = 1;
+        let members = members
+            .into_iter()
+            .map(|(name, ty, quals)| {
+                let offset = align_to(size, ty.align());
+                size = offset + ty.size();
+                align = align.max(ty.align());
+                Member {
+                    name,
+                    ty,
+                    quals,
+                    offset,
+                }
+            })
+            .collect();
+        let id = STRUCT_COUNT.with(|count| {
+            count.set(count.get() + 1);
+            count.get()
+        });
+        Box::leak(Box::new(StructType {
+            id,
+            tag,
+            members,
+            size: align_to(size, align),
+            align,
+        }))
+    }
+
+    /// 名前でメンバを探す
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+/// 型修飾子
+///
+/// 変数や構造体のメンバの宣言に付き、`Type` には含めない。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Qualifiers {
+    /// "const" の付いた変数には代入できない
+    pub is_const: bool,
+    /// "volatile" の付いた変数は、アクセスのたびにメモリを読み書きしなければならない。
+    /// `SubroutineGen` は変数の値をレジスタに保持せず、アクセスを省略することも無いので、
+    /// 他の変数と同じコードで扱える。
+    pub is_volatile: bool,
+}
+
+impl Qualifiers {
+    /// 両方の修飾子を合わせた修飾子を返す
+    pub fn merge(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            is_const: self.is_const || other.is_const,
+            is_volatile: self.is_volatile || other.is_volatile,
+        }
+    }
+}
+
+/// 型名の前に付ける形の型修飾子。
+/// 修飾子が無ければ何も出力しない。
+impl std::fmt::Display for Qualifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.is_const {
+            write!(f, "const ")?;
+        }
+        if self.is_volatile {
+            write!(f, "volatile ")?;
+        }
+        Ok(())
+    }
+}
+
+/// `n` を `align` の倍数に切り上げる
+pub fn align_to(n: usize, align: usize) -> usize {
+    n.div_ceil(align) * align
+}
+
+/// 整数型
+///
+/// `char` は符号付きとして扱い、`signed char` と区別しない。
+/// LP64 では `long long` は `long` と同じ表現なので区別しない。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Long,
+    ULong,
+}
+
+impl IntType {
+    /// 型のサイズ（バイト数）
+    pub fn size(&self) -> usize {
+        match self {
+            IntType::Char | IntType::UChar => 1,
+            IntType::Short | IntType::UShort => 2,
+            IntType::Int | IntType::UInt => 4,
+            IntType::Long | IntType::ULong => 8,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        match self {
+            IntType::Char | IntType::Short | IntType::Int | IntType::Long => true,
+            IntType::UChar | IntType::UShort | IntType::UInt | IntType::ULong => false,
+        }
+    }
+
+    /// この型で表現できる最大の値
+    pub fn max_value(&self) -> u64 {
+        let bits = self.size() as u32 * 8;
+        if self.is_signed() {
+            (1u64 << (bits - 1)) - 1
+        } else {
+            u64::MAX >> (64 - bits)
+        }
+    }
+
+    /// 64bitの値をこの型の値として表現できるよう切り詰め、
+    /// 符号付きなら符号拡張、符号なしならゼロ拡張した値を返す。
+    pub fn wrap(&self, value: u64) -> u64 {
+        let shift = 64 - self.size() as u32 * 8;
+        if self.is_signed() {
+            (((value << shift) as i64) >> shift) as u64
+        } else {
+            (value << shift) >> shift
+        }
+    }
+
+    /// `wrap` された値を数としての値に変換する
+    pub fn to_i128(&self, value: u64) -> i128 {
+        if self.is_signed() {
+            value as i64 as i128
+        } else {
+            value as i128
+        }
+    }
+
+    /// 同じサイズの符号なし整数型
+    pub fn to_unsigned(self) -> Self {
+        match self {
+            IntType::Char | IntType::UChar => IntType::UChar,
+            IntType::Short | IntType::UShort => IntType::UShort,
+            IntType::Int | IntType::UInt => IntType::UInt,
+            IntType::Long | IntType::ULong => IntType::ULong,
+        }
+    }
+
+    /// 算術演算を行う際の型を返す。
+    /// 両辺を整数拡張した上で、大きい方の型に揃える。
+    /// サイズが同じで符号が異なる場合は符号なしの型に揃える。
+    pub fn common(self, other: IntType) -> IntType {
+        let lhs = self.promoted();
+        let rhs = other.promoted();
+
+        if lhs.size() != rhs.size() {
+            if lhs.size() > rhs.size() {
+                lhs
+            } else {
+                rhs
+            }
+        } else if lhs.is_signed() && rhs.is_signed() {
+            lhs
+        } else {
+            lhs.to_unsigned()
+        }
+    }
+
+    /// 整数拡張した型を返す。
+    /// `int` より小さい型は `int` になる。
+    pub fn promoted(self) -> IntType {
+        if self.size() < IntType::Int.size() {
+            IntType::Int
+        } else {
+            self
+        }
+    }
+
+    /// C言語での型名
+    pub fn display(&self) -> &'static str {
+        match self {
+            IntType::Char => "char",
+            IntType::UChar => "unsigned char",
+            IntType::Short => "short",
+            IntType::UShort => "unsigned short",
+            IntType::Int => "int",
+            IntType::UInt => "unsigned int",
+            IntType::Long => "long",
+            IntType::ULong => "unsigned long",
+        }
+    }
+}
+
+impl std::fmt::Display for IntType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.display())
+    }
+}
+
+/// 浮動小数点数型
+///
+/// 値は IEEE 754 の表現のビット列として扱う。
+/// `float` の値は下位32bitに置き、上位32bitは0にする。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FloatType {
+    Float,
+    Double,
+}
+
+impl FloatType {
+    /// 型のサイズ（バイト数）
+    pub fn size(&self) -> usize {
+        match self {
+            FloatType::Float => 4,
+            FloatType::Double => 8,
+        }
+    }
+
+    /// ビット列を数としての値に変換する
+    pub fn to_f64(&self, bits: u64) -> f64 {
+        match self {
+            FloatType::Float => f32::from_bits(bits as u32) as f64,
+            FloatType::Double => f64::from_bits(bits),
+        }
+    }
+
+    /// 数をこの型の値のビット列に変換する
+    pub fn from_f64(&self, value: f64) -> u64 {
+        match self {
+            FloatType::Float => (value as f32).to_bits() as u64,
+            FloatType::Double => value.to_bits(),
+        }
+    }
+
+    /// C言語での型名
+    pub fn display(&self) -> &'static str {
+        match self {
+            FloatType::Float => "float",
+            FloatType::Double => "double",
+        }
+    }
+}
+
+impl std::fmt::Display for FloatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.display())
+    }
+}
@@ -3,8 +3,8 @@ assert() {
   expected="$1"
   input="$2"
 
-  target/debug/atomcc "$input" > tmp.s
-  cc -o tmp tmp.s
+  printf '%s' "$input" | target/debug/atomcc -o tmp.s -
+  cc -o tmp tmp.s -lm
   ./tmp
   actual="$?"
 
@@ -21,8 +21,8 @@ assert_fn() {
   input="$2"
   link="$3"
 
-  target/debug/atomcc "$input" > tmp.s
-  cc -o tmp "$link" tmp.s
+  printf '%s' "$input" | target/debug/atomcc -o tmp.s -
+  cc -o tmp "$link" tmp.s -lm
   output=$(./tmp)
 
   if [ "$output" = "$expected" ]; then
@@ -33,6 +33,21 @@ assert_fn() {
   fi
 }
 
+assert_exit() {
+  expected="$1"
+  shift
+
+  target/debug/atomcc "$@" > /dev/null 2>&1 < /dev/null
+  actual="$?"
+
+  if [ "$actual" = "$expected" ]; then
+    echo "atomcc $* => exit $actual"
+  else
+    echo "atomcc $* => exit $expected expected, but got $actual"
+    exit 1
+  fi
+}
+
 cargo build
 
 assert 0 "0;"
@@ -47,6 +62,61 @@ assert 4 '(3+5)/2;'
 assert 4 '+4;'
 assert 2 '-14+16;'
 assert 20 '-(4+6)*2+40;'
+assert 42 '0x2a;'
+assert 42 '0X2A;'
+assert 42 '052;'
+assert 42 '0b101010;'
+assert 42 "1'000'042 - 1'000'000;"
+assert 42 '42u;'
+assert 42 '42L;'
+assert 42 '42ull;'
+assert 97 "'a';"
+assert 10 "'\\n';"
+assert 39 "'\\'';"
+assert 65 "'\\x41';"
+assert 1 '4294967296 / 4294967296;'
+assert 2 '0x100000000 / 0x80000000;'
+assert 1 '0xffffffffffffffff == 0xffffffffffffffff;'
+assert 44 'char c = 300; return c;'
+assert 255 'unsigned char c = 0 - 1; return c;'
+assert 1 'char c = 255; return c < 0;'
+assert 1 'short s = 65535; return s == 0 - 1;'
+assert 1 'unsigned short s = 65535; return s == 65535;'
+assert 1 'int i = 4294967295; return i == 0 - 1;'
+assert 1 'unsigned int u = 4294967295; return u == 4294967295;'
+assert 42 'long l = 42; l;'
+assert 44 '{ char c = 300; return c; }'
+assert 255 '{ unsigned char c; c = 511; return c; }'
+assert 1 '{ int i; i = 0 - 1; return i < 0; }'
+assert 0 '{ unsigned int u; u = 0 - 1; return u < 0; }'
+assert 42 'char c = 1; { char c = 42; return c; }'
+assert 1 'char c = 1; { char c = 42; } return c;'
+assert 127 '0xffffffffffffffff / 2 / 72057594037927936;'
+assert 1 '(0 - 1) / 2 == 0;'
+assert 0 '(0 - 1u) < 1u;'
+assert 1 '1u <= 0xffffffffu;'
+assert 1 '{ int a; a = 2147483647; a = a + 1; return a < 0; }'
+assert 1 '{ unsigned int u; u = 0; return u - 1 == 4294967295; }'
+assert 1 '{ unsigned int u; u = 0; return u - 1 > 0; }'
+assert 200 '{ char c; c = 100; return c + c; }'
+assert 0 '{ int i; unsigned u; i = 0 - 1; u = 1; return i < u; }'
+assert 1 '{ long l; unsigned u; l = 0 - 1; u = 1; return l < u; }'
+assert 1 '{ unsigned char a; unsigned char b; a = 200; b = 100; return a + b == 300; }'
+assert 1 '{ unsigned short s; s = 65535; return s * s == 4294836225u; }'
+assert 1 '{ unsigned u; u = 4294967295; return u / 2 == 2147483647; }'
+assert 1 '{ char c; c = 0 - 128; return c / 2 == 0 - 64; }'
+assert 44 '(char)300;'
+assert 255 '(unsigned char)-1;'
+assert 1 '(int)4294967297;'
+assert 56 '-(char)200;'
+assert 42 '(short)65536 + 42;'
+assert 1 '(long)(int)4294967295 < 0;'
+assert 1 '(unsigned)-1 == 4294967295;'
+assert 0 '(unsigned)-1 < 0;'
+assert 1 '(long)(unsigned)-1 == 4294967295;'
+assert 1 '{ long l; l = 0 - 1; return (unsigned char)l == 255; }'
+assert 1 '{ unsigned char c; c = 255; return (char)c == (0 - 1); }'
+assert 3 '(1 + 2);'
 assert 0 '1 == 0;'
 assert 1 '1 != 0;'
 assert 1 '(1 + 40) > 2 * 10;'
@@ -55,9 +125,12 @@ assert 24 '42;24;'
 assert 42 'a = 40; a + 2;'
 assert 42 'foo = 40; foo + 2;'
 assert 42 'return 42;'
+assert 42 'return
+42;'
+assert 42 '	foo	=	42;	return	foo;'
 assert 42 'foo = 11; return foo + 31;'
-assert 22 'foo = 11; if ( foo == 11 ) 22; else 42;'
-assert 42 'foo = 11; if(foo == 10) 22; else 42;'
+assert 22 'foo = 11; if ( foo == 11 ) return 22; else return 42;'
+assert 42 'foo = 11; if(foo == 10) return 22; else return 42;'
 assert 10 'i = 1; while (i<10) i = i+1; return i;'
 assert 45 '
 i = 1;
@@ -71,5 +144,250 @@ return n;
 
 assert_fn OK 'foo();' tests/foo.c
 assert_fn 42 '42; foo2(10, 32);' tests/foo.c
+assert_fn 42 'int foo2(int a, int b); foo2(10, 32);' tests/foo.c
+assert_fn 42 'extern int foo2(int, int); foo2(10, 32);' tests/foo.c
+assert_fn 42 'int print_char(char c); print_char(298);' tests/foo.c
+assert_fn 42 'extern int ext_val; int foo2(int a, int b); foo2(ext_val, 0);' tests/foo.c
+assert_fn 42 'int add(int, int); x = add(40, 2); foo2(x, 0);' tests/foo.c
+assert_fn 42 'int add(int, int); foo2(add(1, 2) * add(4, 10), 0);' tests/foo.c
+assert_fn 42 'int add(int, int); foo2(add(add(10, 20), add(5, 7)), 0);' tests/foo.c
+assert_fn 42 'long mul_long(long, long); x = mul_long(6000000000, 7) / 1000000000; foo2(x, 0);' tests/foo.c
+assert_fn 42 'char minus_one(); foo2(minus_one() < 0, 41);' tests/foo.c
+assert_fn 42 'unsigned char max_uchar(); foo2(max_uchar() - 213, 0);' tests/foo.c
+assert_fn 100 'int add(int, int); i = 0; while (i < 100) i = add(i, 1); foo2(i, 0);' tests/foo.c
+assert_fn 42 'long sum_va(int, ...); foo2(sum_va(3, 10, 20, 12), 0);' tests/foo.c
+assert_fn 42 'long sum_va(int n, ...); foo2(sum_va(4, 10, 20, 13, (char)-1), 0);' tests/foo.c
+assert_fn 42 'long sum_va(int n, ...); foo2(sum_va(8, 1, 2, 3, 4, 5, 6, 7, 14), 0);' tests/foo.c
+assert_fn '1 2 3 4 5 6 7' 'print7(1, 2, 3, 4, 5, 6, 7);' tests/foo.c
+assert_fn '1 2 3 4 5 6 7 8' 'long print8(long, long, long, long, long, long, long, long); print8(1, 2, 3, 4, 5, 6, 7, 8);' tests/foo.c
+assert_fn '1 2 3 4 5 6 7 -8' 'long print8(long, long, long, long, long, long, long, long); a = 3; print8(1, 2, a, 4, 5, 6, a + 4, -8);' tests/foo.c
+assert_fn '10 20 30 40 50 60 70' 'int print7(int, int, int, int, int, int, int); { int x; x = 70; print7(10, 20, 30, 40, 50, 60, x); }' tests/foo.c
+assert 55 'int fib(int n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } return fib(10);'
+assert 42 'int add2(int a, int b) { int c; c = a + b; return c; } x = 2; return add2(40, x);'
+assert 36 'long sum8(long a, long b, long c, long d, long e, long f, long g, long h) { return a + b + c + d + e + f + g + h; } return sum8(1, 2, 3, 4, 5, 6, 7, 8);'
+assert 255 'unsigned char uc(int x) { return x; } return uc(-1);'
+assert 42 'int sum(int n, ...) { va_list ap; int s; s = 0; va_start(ap, n); while (n) { s = s + va_arg(ap, int); n = n - 1; } va_end(ap); return s; } return sum(3, 10, 20, 12);'
+assert 42 'long sum(int n, ...) { va_list ap; long s; s = 0; va_start(ap, n); while (n) { s = s + va_arg(ap, long); n = n - 1; } return s; } return sum(9, 1, 2, 3, 4, 5, 6, 7, 8, 6);'
+assert 42 'int last(int a, int b, int c, int d, int e, int f, int g, ...) { va_list ap; va_start(ap, g); return va_arg(ap, int); } return last(1, 2, 3, 4, 5, 6, 7, 42);'
+assert_fn 42 'int vsum(int n, va_list ap); int sum(int n, ...) { va_list ap; va_start(ap, n); return vsum(n, ap); } foo2(sum(4, 10, 20, 7, 5), 0);' tests/foo.c
+assert 3 'int count() { static int n; n = n + 1; return n; } count(); count(); return count();'
+assert 45 'int next() { static int n = 40; n = n + 1; return n; } next(); next(); next(); next(); return next();'
+assert 26 'int a() { static int n = 10; n = n + 1; return n; } int b() { static int n = 5; n = n + 5; return n; } b(); return a() + b();'
+assert 7 '{ static long n = 3; n = n + 4; return n; }'
+assert 42 'static int x = 40; static int add2(int a) { return a + 2; } return add2(x);'
+assert 42 'static int f(); int f() { return 42; } return f();'
+assert 42 '(int)(40.5 + 1.5);'
+assert 42 '{ double x; x = 10.5; return x * 4; }'
+assert 42 '{ float x; x = 84.0f; return x / 2; }'
+assert 1 '0.1 + 0.2 != 0.3;'
+assert 1 '{ double x; x = 1.5; return x < 2; }'
+assert 1 '{ double x; x = .5; return x <= 0.5; }'
+assert 3 '{ double x; x = -3.9; return -x; }'
+assert 1 '{ double z = 0.0; double n = -z; return 1/n < 0; }'
+assert 1 '{ float z = 0.0f; float n = -z; return 1/n < 0; }'
+assert 1 '{ double n = -0.0; return 1/n < 0; }'
+assert 1 'double g = -0.0; return 1/g < 0;'
+assert 42 '{ float f = 42.5f; f = -f; return -f; }'
+assert 42 '{ unsigned char c = 214; return -c + 256; }'
+assert 1 '{ unsigned u = 1; return -u == 4294967295; }'
+assert 42 '{ int a[-(-2)]; a[+1] = 42; return a[1]; }'
+assert 42 'double half(double x) { return x / 2; } return half(84.0);'
+assert 42 'float addf(float a, double b) { return a + b; } return addf(40.25f, 1.75);'
+assert 42 'double sum10(double a, double b, double c, double d, double e, double f, double g, double h, double i, double j) { return a + b + c + d + e + f + g + h + i + j; } return sum10(1, 2, 3, 4, 5, 6, 7, 8, 3.5, 2.5);'
+assert 42 'double mix(int a, double b, long c, float d) { return a * b + c - d; } return mix(10, 4.5, 0, 3.0f);'
+assert 42 'double sum(int n, ...) { va_list ap; double s; s = 0; va_start(ap, n); while (n) { s = s + va_arg(ap, double); n = n - 1; } return s; } return sum(10, 1.5, 2.5, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 2.0, 3.0);'
+assert 42 'double sum(int n, ...) { va_list ap; double s; s = 0; va_start(ap, n); while (n) { s = s + va_arg(ap, int) + va_arg(ap, double); n = n - 1; } return s; } return sum(2, 10, 10.5, 20, 1.5);'
+assert 42 'double sqrt(double); return sqrt(1764.0);'
+assert 42 'double pow(double, double); return pow(2, 5) + 10;'
+assert 42 '{ unsigned long x; double d; x = 0xffffffffffffffff; d = x; x = d - 2048; return x - 0xfffffffffffff7d6; }'
+assert 1 '{ unsigned long x; x = 9223372036854775808.0; return x == 0x8000000000000000; }'
+assert 42 'double g = 41.75; float f = 0.25; return g + f;'
+assert 42 'int f(int x) { return x + 1; } int (*p)(int) = f; return p(41);'
+assert 42 'int f(int x) { return x + 1; } { static int (*p)(int) = &f; return p(41); }'
+assert 42 'int f(int x) { return x * 2; } struct S { int (*fn)(int); int n; }; const struct S s[2] = {{0, 1}, {f, 21}}; return s[1].fn(s[1].n);'
+assert 42 'static double g; g = 42.9; return g;'
+assert_fn 42 'int print_double(double); print_double(41.5 + 0.5);' tests/foo.c
+assert_fn 42 'double add_double(double, float); foo2((int)add_double(40.5, 1.5f), 0);' tests/foo.c
+assert_fn 42 'int add(); foo2(add(40, 2), 0);' tests/foo.c
+assert_fn 42 'int add(); int add(int a, int b); foo2(add(40, 2), 0);' tests/foo.c
+assert_fn 42 'double sum_double_va(int, ...); foo2((int)sum_double_va(3, 10.5, 20.0, 11.5f), 0);' tests/foo.c
+
+assert 42 'int add(int a, int b) { return a + b; } int (*fp)(int, int); fp = add; return fp(40, 2);'
+assert 42 'int add(int a, int b) { return a + b; } int (*fp)(int, int); fp = &add; return (*fp)(40, 2) + (&add)(0, 0);'
+assert 42 'int inc(int x) { return x + 1; } int twice(int (*f)(int), int x) { return f(f(x)); } return twice(inc, 40);'
+assert 42 'int inc(int x) { return x + 1; } int dec(int x) { return x - 1; } int pick(int n, int x) { int (*f)(int); if (n) f = inc; else f = dec; return f(x); } return pick(1, 40) + pick(0, 2);'
+assert 42 'double half(double x) { return x / 2; } { double (*h)(double) = half; return h(84); }'
+assert 36 'long sum8(long a, long b, long c, long d, long e, long f, long g, long h) { return a + b + c + d + e + f + g + h; } { long (*s)(long, long, long, long, long, long, long, long) = sum8; return s(1, 2, 3, 4, 5, 6, 7, 8); }'
+assert 42 'int sum(int n, ...) { va_list ap; int s; s = 0; va_start(ap, n); while (n) { s = s + va_arg(ap, int); n = n - 1; } return s; } { int (*f)(int, ...) = sum; return f(3, 10, 20, 12); }'
+assert 1 'int f(int x) { return x; } int g(int x) { return x; } int (*p)(int); p = f; return (p == f) + (p != g) + (p == 0) - 1;'
+assert 42 'int f(int x) { return x; } int (*p)(int); p = 0; if (p) return 1; p = f; if (p) return 42; return 2;'
+assert 1 'int f(int x) { return x; } return (long)f != 0;'
+assert 42 'int f(int x) { return x + 2; } { long addr = (long)f; int (*p)(int) = (int (*)(int))addr; return p(40); }'
+assert 42 'int abs(int); int (*f)(int); f = abs; return f(-42);'
+assert 42 'double sqrt(double); double (*f)(double); f = sqrt; return f(1764);'
+assert_fn 42 'int apply(int (*)(int, int), int, int); int mul(int a, int b) { return a * b; } foo2(apply(mul, 6, 7), 0);' tests/foo.c
+assert_fn 42 'int sort_first(int (*cmp)(int, int)); int desc(int a, int b) { return b - a; } foo2(sort_first(desc), 0);' tests/foo.c
+assert_fn 1 'int sort_first(int (*cmp)(int, int)); int asc(int a, int b) { return a - b; } foo2(sort_first(asc), 0);' tests/foo.c
+
+assert 42 '{ int x = 3; int *p = &x; int **pp = &p; **pp = 42; return x; }'
+assert 42 '{ char c = -1; char *p = &c; *p = *p + 43; return c; }'
+assert 42 '{ double d; double *p = &d; *p = 20.75; return d * 2 + 0.5; }'
+assert 42 'void set(int *p, int v) { *p = v; } { int x; set(&x, 42); return x; }'
+assert 42 'int *first(int *p, int *q) { return p; } { int a = 42, b = 1; return *first(&a, &b); }'
+assert 42 'struct P { int x; char y; }; { struct P p; struct P *q = &p; (*q).x = 40; p.y = 2; return (*q).x + (*q).y; }'
+assert 42 'int a[3]; int *p = &a[1]; *p = 40; (*&a)[2] = 2; return a[1] + a[2];'
+assert 42 'struct S { int a; int b[3]; } s; int *p = &s.b[2]; *p = 42; return s.b[2];'
+assert 42 'int f(void) { static int n = 40; static int *p = &n; *p = *p + 1; return n; } f(); return f();'
+assert 1 '{ int x; int *p = &x, *q = 0; void *v = p; return (p == v) + (q == 0) + (p != q) - 2; }'
+assert 42 '{ int x = 40; const int *p = &x; x = x + 2; return *p; }'
+assert 42 '{ int x; int *const p = &x; *p = 42; return x; }'
+assert 42 '{ int x = 42; long addr = (long)&x; int *p = (int *)addr; return *p; }'
+assert 42 '{ int x = 0x1234562a; char *p = (char *)&x; return *p; }'
+assert 42 'int *p; if (p) return 1; return 42;'
+assert_fn 42 'extern int ext_val; int *p = &ext_val; foo2(*p, 0);' tests/foo.c
+assert_fn 42 'void swap_int(int *, int *); int a = 2, b = 40; swap_int(&a, &b); foo2(a, b);' tests/foo.c
+
+assert 42 '{ int a[3] = {40, 1, 1}; return a[0] + a[1] + a[2]; }'
+assert 3 '{ int a[] = {1, 2, 3}; return a[2]; }'
+assert 0 '{ long a[4] = {1, 2}; return a[2] + a[3]; }'
+assert 42 '{ char a[5] = {[3] = 40, 2}; return a[0] + a[1] + a[2] + a[3] + a[4]; }'
+assert 42 '{ int a[2][3] = {{1, 2, 3}, {4, 5, 27}}; return a[0][0] + a[0][1] + a[0][2] + a[1][0] + a[1][1] + a[1][2]; }'
+assert 6 '{ int a[][2] = {1, 2, 3}; int i = 1; return a[i][0] * a[0][i]; }'
+assert 42 'struct P { int x; int y; }; { struct P p = {.x = 40, .y = 2}; return p.x + p.y; }'
+assert 42 'struct P { char c; long l; double d; }; { struct P p = {2, .d = 2.0}; return p.c + (int)p.d * 20; }'
+assert 0 'struct P { int x; int y; int z; }; { struct P p = {.y = 1}; return p.x + p.z; }'
+assert 42 'struct P { int x; int y; }; { struct P ps[2] = {{1, 2}, [1].y = 39}; return ps[0].x + ps[0].y + ps[1].y + ps[1].x; }'
+assert 42 '{ struct { int a[2]; char c; } s = {{40, 2}}; return s.a[0] + s.a[1] + s.c; }'
+assert 42 '{ int i = 0; int x = 20; int a[3] = {x, x + 2, i}; return a[0] + a[1] + a[2]; }'
+assert 42 'int g[] = {10, 20, 12}; return g[0] + g[1] + g[2];'
+assert 0 'long g[8] = {1, [6] = 2}; return g[1] + g[5] + g[7];'
+assert 42 'struct P { char c; short s; long l; }; struct P g = {.l = 30, .s = 10, .c = 2}; return g.c + g.s + g.l;'
+assert 42 'static int g[2][2] = {{1, 2}, {3, 36}}; return g[0][0] + g[0][1] + g[1][0] + g[1][1];'
+assert 42 'double g[2] = {40.5, 1.5f}; return g[0] + g[1];'
+assert 42 'int g = {42}; return g;'
+assert 42 '{ int x = {42}; return x; }'
+assert 42 '{ double d = {41.5,}; return d + 0.5; }'
+assert 42 'int f(int i) { static char t[] = {7, 42, 9}; return t[i]; } return f(1);'
+assert 42 'struct P { int x; int y; }; struct P g[] = {{1, 2}, {30, 9}}; int f(int i) { return g[i].x + g[i].y; } return f(0) + f(1);'
+assert 42 '{ int a[3]; int i = 0; while (i < 3) { a[i] = i * 20; i = i + 1; } return a[2] + a[0] + 2; }'
+assert 42 '{ char a[2][2]; a[1][0] = 300; a[0][1] = 2; return a[1][0] + a[0][1] - 4; }'
+assert 42 'struct P { int x; double d; }; { struct P p; p.x = 40; p.d = 2.5; return p.x + p.d; }'
+assert 42 'struct P { int x; int a[2]; }; struct P g[2]; g[1].a[1] = 40; g[0].x = 2; return g[1].a[1] + g[0].x;'
+assert 42 'int f(int i) { static int t[3]; t[i] = t[i] + 21; return t[i]; } f(1); return f(1);'
+
+assert 42 'const int g = 40; const int h[] = {1, 1}; return g + h[0] + h[1];'
+assert 42 'struct P { int x; int y; }; const struct P g = {40, 2}; return g.x + g.y;'
+assert 0 'const long g; return g;'
+assert 42 'int f(const int x) { const int y = x + 1; return y; } return f(41);'
+assert 42 'int f(int i) { static const char t[] = {7, 42, 9}; return t[i]; } return f(1);'
+assert 42 '{ volatile int v = 40; v = v + 2; return v; }'
+assert 42 'volatile long g; int f() { g = 42; return 0; } f(); return g;'
+assert 42 '{ const volatile int x = 42; return x; }'
+
+assert 39 'v0 = 0; v1 = 1; v2 = 2; v3 = 3; v4 = 4; v5 = 5; v6 = 6; v7 = 7; v8 = 8; v9 = 9; v10 = 10; v11 = 11; v12 = 12; v13 = 13; v14 = 14; v15 = 15; v16 = 16; v17 = 17; v18 = 18; v19 = 19; v20 = 20; v21 = 21; v22 = 22; v23 = 23; v24 = 24; v25 = 25; v26 = 26; v27 = 27; v28 = 28; v29 = 29; v30 = 30; v31 = 31; v32 = 32; v33 = 33; v34 = 34; v35 = 35; v36 = 36; v37 = 37; v38 = 38; v39 = 39; return v39;'
+assert 42 '{ long a[40] = {[39] = 40}; long x = 2; return a[39] + x; }'
+assert 42 'int f(int x) { return x; } { char s[301] = {[300] = 42}; return f(s[300]); }'
+
+assert 42 ';; { ; } return 42;'
+assert 42 'int x; void f(void) { x = 42; } f(); return x;'
+assert 42 'int x; void f(int y) { if (y) return; x = 42; return; } f(1); f(0); return x;'
+assert 42 'int x; void f(void) { x = 40; } void g(void) { return f(); } { void (*p)(void) = g; p(); return x + 2; }'
+assert 42 'int f(void) { return 42; } (void)f(); return f();'
+assert 42 'int f(int x) { while (1) { if (x > 41) return x; x = x + 1; } } return f(0);'
+assert_fn OK 'void foo(void); { void (*p)(void) = foo; p(); }' tests/foo.c
+assert_fn 42 'void foo2(int, int); void f(int x) { foo2(x, 2); return; } f(40);' tests/foo.c
+
+assert 0 '42; { x = 1; }'
+assert 1 '42; x = 1;'
+assert 42 '1; 2; 42;'
+assert 42 '{ long i = 0; while (i < 2000000) { i = i + 1; i * 2; } return 42; }'
+assert 42 'int f(int x) { return x; } { int i = 0; while (i < 2000000) { i = i + 1; f(i); } } 42;'
+assert 42 'int f(int n) { while (n < 1000000) { n = n + 1; if (n) n; else n * 2; } return 42; } f(0);'
+
+assert 42 '{ int a, b = 2, c[2] = {1, 3}; a = 36; return a + b + c[0] + c[1]; }'
+assert 2 '{ int a, b = 2, *c; c = &b; return *c; }'
+assert 42 'int a, b = 2, *c; c = &b; *c = 40; return b + 2;'
+assert 42 'int g1 = 40, g2 = 2; return g1 + g2;'
+assert 42 'char a[2] = {40, 1}, b = 2; return a[0] + b;'
+assert 42 'int f(void) { static int n, m = 40; n = n + 1; return m + n; } f(); return f();'
+assert 42 'int x; int f(int y) { x = y; return 0; } return (f(40), x + 2);'
+assert 42 'return (1, 2, 42);'
+assert 42 'int f(int n) { int i, j, s = 0; i = 0, j = n; while (i < j) s = s + 1, i = i + 1, j = j - 1; return s; } return f(84);'
+assert 42 '{ int a; long b; a = b = 21; return a + b; }'
+assert 42 '{ char c; return (c = 298) + 0; }'
+assert 42 '{ double d; int i; i = d = 41.5; return i + 1; }'
+assert 42 '{ int a[2]; int i = 0; a[i = 1] = 40; return a[1] + i + 1; }'
+assert 42 'int g; int f(int x) { return x; } return f(g = 40) + g - 38;'
+assert 42 'int a[3] = {1, 2, 42}; return a[(0, 2)];'
+assert_fn 42 'int add(int, int); void foo2(int, int); foo2(add(1, (2, 41)), 0);' tests/foo.c
+
+assert 42 $'#define ANSWER 42\nreturn ANSWER;'
+assert 42 $'#define ADD(a, b) ((a) + (b))\nreturn ADD(40, ADD(1, 1));'
+assert 42 $'#define VAR(n) var ## n\nint VAR(1) = 40, VAR(2) = 2; return var1 + var2;'
+assert 42 $'#define N 2\n#if N * 2 == 4 && defined N\nreturn 42;\n#else\nreturn 1;\n#endif'
+assert 42 $'#ifdef UNDEFINED\nreturn 1;\n#elif !defined(UNDEFINED)\nreturn 42;\n#endif'
+assert 42 $'#define N 1\n#undef N\n#ifndef N\nreturn 42;\n#endif'
+assert 4 $'// comment\n/* multi\n   line */\nreturn __LINE__;'
+assert 42 $'#include "tests/include/atom.h"\n#include "tests/include/atom.h"\nreturn SQUARE(6) + 6;'
+assert_fn 42 $'#include "tests/include/atom.h"\nvoid foo2(int, int); foo2(add(ANSWER, 0), 0);' tests/foo.c
+
+printf 'return 42;\n' > tmp.c
+assert_exit 0 tmp.c
+assert_exit 0 --help
+assert_exit 0 -o tmp.s -
+assert_exit 2
+assert_exit 2 -x tmp.c
+assert_exit 2 -o tmp.s tmp.c -
+assert_exit 2 -o tmp.c tmp.c
+assert_exit 2 tmp.c ./tmp.c
+assert_exit 2 tmp.s tmp.c
+assert_exit 2 tmp.c -o
+assert_exit 3 missing.c
+assert_exit 3 -o missing/tmp.s tmp.c
+assert_exit 3 -o /dev/full tmp.c
+printf 'return 1 +;\n' > tmp.c
+assert_exit 1 -o tmp.s tmp.c
+assert_exit 2 -ferror-limit=x tmp.c
+
+# 報告するエラーの数は "-ferror-limit" で変えられる
+printf 'a = ; b = ; c = ; d = ;\n' > tmp.c
+for limit in 2 0; do
+  expected=$([ "$limit" = 2 ] && echo 3 || echo 4)
+  actual=$(target/debug/atomcc -ferror-limit=$limit -o tmp.s tmp.c 2>&1 | grep -c 'error:')
+  if [ "$actual" = "$expected" ]; then
+    echo "atomcc -ferror-limit=$limit tmp.c => $actual errors"
+  else
+    echo "atomcc -ferror-limit=$limit tmp.c => $expected errors expected, but got $actual"
+    exit 1
+  fi
+done
+
+# 既存の出力ファイルは切り詰められる
+printf '%08000d\n' 0 > tmp.s
+printf '#include <atom.h>\nreturn ANSWER;\n' > tmp.c
+target/debug/atomcc -I tests/include -o tmp.s tmp.c
+cc -o tmp tmp.s -lm
+./tmp
+actual="$?"
+if [ "$actual" = 42 ]; then
+  echo "atomcc -I tests/include -o tmp.s tmp.c => $actual"
+else
+  echo "atomcc -I tests/include -o tmp.s tmp.c => 42 expected, but got $actual"
+  exit 1
+fi
+
+# 入力ファイルが複数ある場合、失敗したファイルがあっても残りはコンパイルされる
+rm -f tmp.s
+printf 'return 42;\n' > tmp.c
+assert_exit 3 missing.c tmp.c -
+cc -o tmp tmp.s -lm
+./tmp
+actual="$?"
+if [ "$actual" = 42 ]; then
+  echo "atomcc missing.c tmp.c - => tmp.s returns $actual"
+else
+  echo "atomcc missing.c tmp.c - => tmp.s returns 42 expected, but got $actual"
+  exit 1
+fi
 
 echo OK
@@ -1,7 +1,86 @@
+#include <stdarg.h>
 #include <stdio.h>
+#include <stdlib.h>
 
 void foo() { printf("OK\n"); }
 
 void foo2(int a, int b) {
   printf("%d\n", a + b);
 }
+
+int print_char(char c) {
+  printf("%d\n", c);
+  return c;
+}
+
+int ext_val = 42;
+
+int print7(int a, int b, int c, int d, int e, int f, int g) {
+  printf("%d %d %d %d %d %d %d\n", a, b, c, d, e, f, g);
+  return g;
+}
+
+long print8(long a, long b, long c, long d, long e, long f, long g, long h) {
+  printf("%ld %ld %ld %ld %ld %ld %ld %ld\n", a, b, c, d, e, f, g, h);
+  return h;
+}
+
+int add(int a, int b) { return a + b; }
+
+long mul_long(long a, long b) { return a * b; }
+
+char minus_one(void) { return -1; }
+
+unsigned char max_uchar(void) { return 255; }
+
+long sum_va(int n, ...) {
+  va_list ap;
+  va_start(ap, n);
+  long sum = 0;
+  for (int i = 0; i < n; i++)
+    sum += va_arg(ap, int);
+  va_end(ap);
+  return sum;
+}
+
+int vsum(int n, va_list ap) {
+  int sum = 0;
+  for (int i = 0; i < n; i++)
+    sum += va_arg(ap, int);
+  return sum;
+}
+
+int print_double(double x) { return printf("%g\n", x); }
+
+double add_double(double a, float b) { return a + b; }
+
+double sum_double_va(int n, ...) {
+  va_list ap;
+  va_start(ap, n);
+  double sum = 0;
+  for (int i = 0; i < n; i++)
+    sum += va_arg(ap, double);
+  va_end(ap);
+  return sum;
+}
+
+int apply(int (*f)(int, int), int a, int b) { return f(a, b); }
+
+static int (*sort_cmp)(int, int);
+
+static int sort_adapter(const void *a, const void *b) {
+  return sort_cmp(*(const int *)a, *(const int *)b);
+}
+
+int sort_first(int (*cmp)(int, int)) {
+  int a[] = {5, 3, 42, 8, 1};
+  sort_cmp = cmp;
+  qsort(a, 5, sizeof(int), sort_adapter);
+  return a[0];
+}
+
+void swap_int(int *a, int *b) {
+  int t = *a;
+  *a = *b;
+  *b = t;
+}
@@ -0,0 +1,10 @@
+#ifndef ATOM_H
+#define ATOM_H
+
+/* atom のソースで共通して使う定数と関数 */
+#define ANSWER 42
+#define SQUARE(x) ((x) * (x))
+
+int add(int a, int b);
+
+#endif